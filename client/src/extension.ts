@@ -45,7 +45,10 @@ export function activate(context: ExtensionContext) {
     };
 
     const clientOptions: LanguageClientOptions = {
-        documentSelector: [{ scheme: "file", pattern: "**/*.proto" }],
+        documentSelector: [
+            { scheme: "file", pattern: "**/*.proto" },
+            { scheme: "protols", pattern: "**/*.proto" },
+        ],
     };
 
     client = new LanguageClient(
@@ -56,6 +59,18 @@ export function activate(context: ExtensionContext) {
         true
     );
 
+    // The well-known types are bundled with the server. Documents provided
+    // through a content provider are read-only.
+    context.subscriptions.push(
+        workspace.registerTextDocumentContentProvider("protols", {
+            provideTextDocumentContent(uri: vscode.Uri): Thenable<string> {
+                return client.sendRequest<string>("protols/virtualFile", {
+                    uri: uri.toString(),
+                });
+            },
+        })
+    );
+
     client.start();
 }
 
@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use proto_parser::{tokenize, IncrementalParser, Parser};
+
+/// A schema of about 5,000 lines, the size of our larger shared files.
+fn schema() -> String {
+    let mut text = String::from("syntax = \"proto3\";\n\npackage bench.v1;\n\n");
+
+    for idx in 0..250 {
+        text.push_str(&format!(
+            r#"// Message number {idx}.
+message Message{idx} {{
+  // The id.
+  uint64 id = 1;
+  string name = 2 [deprecated = true];
+  repeated Message{idx}.Nested nested = 3;
+  map<string, int32> counts = 4;
+  oneof choice {{
+    string text = 5;
+    bytes data = 6;
+  }}
+  message Nested {{
+    int32 value = 1;
+    reserved 2 to 4;
+  }}
+}}
+
+enum Enum{idx} {{
+  ENUM{idx}_UNSPECIFIED = 0;
+  ENUM{idx}_ONE = 1;
+}}
+
+"#
+        ));
+    }
+
+    text
+}
+
+fn parse(c: &mut Criterion) {
+    let text = schema();
+    let offset = text.find("message Message125 {").unwrap() + "message Message125 {".len();
+
+    c.bench_function("full parse", |b| {
+        b.iter(|| Parser::new(tokenize(black_box(&text))).parse("bench.proto"))
+    });
+
+    c.bench_function("incremental edit", |b| {
+        let mut parser = IncrementalParser::new("bench.proto", &text);
+
+        // Types a char and deletes it again, so every iteration starts from
+        // the same text.
+        b.iter(|| {
+            parser.edit(offset..offset, black_box("x"));
+            parser.edit(offset..offset + 1, black_box(""));
+        })
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -1,45 +1,48 @@
-use crate::{message::Message, Node, Position, Root, RootDeclaration};
+use crate::{message::MessageElement, service::ServiceElement, Position, Root, RootDeclaration};
 
 pub mod completion;
+pub mod features;
+pub mod hover;
+pub mod imports;
+pub mod references;
+pub mod symbols;
+pub mod validation;
+pub mod well_known_types;
 
-#[derive(Debug)]
-
-pub struct ProtoMessage<'a> {
-    pub node: &'a Node<RootDeclaration>,
-    pub file: String,
+/// The package declared in `root`, or an empty string.
+pub(crate) fn package_name(root: &Root) -> String {
+    root.nodes
+        .iter()
+        .find_map(|node| match &node.value {
+            RootDeclaration::Package(package) => Some(package.package_name.value.join(".")),
+            _ => None,
+        })
+        .unwrap_or_default()
 }
 
-pub fn get_messages<'a>(
-    files: impl Iterator<Item = &'a Root> + 'a,
-) -> impl Iterator<Item = ProtoMessage<'a>> + 'a {
-    files.flat_map(|file| {
-        file.nodes.iter().filter_map(|root_node| {
-            if let RootDeclaration::Message(node) = &root_node.value {
-                Some(ProtoMessage {
-                    node: root_node,
-                    file: file.file_name.clone(),
-                })
-            } else {
-                None
-            }
-        })
-    })
+/// Qualifies `name` with the fully qualified name of its `scope`.
+pub(crate) fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{scope}.{name}")
+    }
 }
 
-pub fn find_node<'a>(root: &'a Root, position: Position) -> Option<&'a str> {
-    let start_idx = 0;
-    let end_idx = root.nodes.len() - 1;
+pub fn find_node(root: &Root, position: Position) -> Option<&str> {
+    let mut start_idx = 0;
+    let mut end_idx = root.nodes.len();
 
-    while start_idx <= end_idx {
+    while start_idx < end_idx {
         let mid_idx = (start_idx + end_idx) / 2;
         let mid_node = &root.nodes[mid_idx];
 
         if mid_node.start.line <= position.line && mid_node.end.line >= position.line {
-            return Some(&mid_node.value.name);
+            return find_node_name(&mid_node.value, position).or(mid_node.value.get_name());
         }
 
         if mid_node.start.line > position.line {
-            end_idx = mid_idx - 1;
+            end_idx = mid_idx;
         } else {
             start_idx = mid_idx + 1;
         }
@@ -48,18 +51,29 @@ pub fn find_node<'a>(root: &'a Root, position: Position) -> Option<&'a str> {
     None
 }
 
-fn find_node_name<'a>(decl: &'a RootDeclaration, position: Position) -> Option<&'a str> {
+fn find_node_name(decl: &RootDeclaration, position: Position) -> Option<&str> {
     match decl {
         RootDeclaration::Message(message) => message.elements.iter().find_map(|element| {
             if element.start.line <= position.line && element.end.line >= position.line {
-                Some(&element.value.name)
+                match &element.value {
+                    MessageElement::Field(field) => Some(field.name.value.as_str()),
+                    MessageElement::MapField(field) => Some(field.name.value.as_str()),
+                    MessageElement::Group(group) => Some(group.name.value.as_str()),
+                    MessageElement::OneOf(oneof) => Some(oneof.name.value.as_str()),
+                    MessageElement::Message(message) => Some(message.name.value.as_str()),
+                    MessageElement::Enum(enum_node) => Some(enum_node.name.value.as_str()),
+                    _ => None,
+                }
             } else {
                 None
             }
         }),
         RootDeclaration::Service(service) => service.elements.iter().find_map(|element| {
             if element.start.line <= position.line && element.end.line >= position.line {
-                Some(&element.name)
+                match &element.value {
+                    ServiceElement::Method(method) => Some(method.name.value.as_str()),
+                    _ => None,
+                }
             } else {
                 None
             }
@@ -1,23 +1,907 @@
-use crate::{syntax::Root, RootDeclaration};
+use crate::{
+    message::FieldType,
+    symbols::{Symbol, SymbolKind, SymbolTable},
+    tokenize, CommentKind, Keyword, ScalarType, Token, TokenKind, TypeName,
+};
 
-pub fn get_suggestions<'a>(roots: impl Iterator<Item = &'a Root>) -> Vec<String> {
-    roots
-        .flat_map(|root| {
-            root.nodes
+use super::qualify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Keyword,
+    Scalar,
+    Package,
+    Message,
+    Enum,
+    EnumValue,
+    /// The name of a built-in option, like `java_package`.
+    Option,
+    Extension,
+    /// A literal value, `true` or `false`.
+    Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The fully qualified name of the declaration the completion refers to.
+    pub detail: Option<String>,
+}
+
+impl Completion {
+    fn new(label: &str, kind: CompletionKind) -> Self {
+        Self {
+            label: label.to_string(),
+            kind,
+            detail: None,
+        }
+    }
+}
+
+const FILE_KEYWORDS: &[&str] = &[
+    "syntax", "edition", "package", "import", "option", "message", "enum", "service", "extend",
+];
+const MESSAGE_KEYWORDS: &[&str] = &[
+    "message",
+    "enum",
+    "oneof",
+    "map",
+    "reserved",
+    "extensions",
+    "option",
+    "extend",
+    "optional",
+    "repeated",
+    "required",
+    "group",
+];
+const EXTEND_KEYWORDS: &[&str] = &["optional", "repeated", "required", "group"];
+const SCALARS: &[&str] = &[
+    "double", "float", "int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32",
+    "fixed64", "sfixed32", "sfixed64", "bool", "string", "bytes",
+];
+const MAP_KEYS: &[&str] = &[
+    "int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32", "fixed64", "sfixed32",
+    "sfixed64", "bool", "string",
+];
+/// Options that aren't fields of `google.protobuf.FieldOptions`.
+const PSEUDO_FIELD_OPTIONS: &[&str] = &["default", "json_name"];
+
+/// The kind of block a statement is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    File,
+    Message,
+    Enum,
+    Service,
+    Oneof,
+    Extend,
+    Method,
+    /// The braces of an aggregate option value, or an unknown block.
+    Other,
+}
+
+impl Block {
+    /// The message of `descriptor.proto` holding the options of the block.
+    fn options(self) -> Option<&'static str> {
+        match self {
+            Block::File => Some("google.protobuf.FileOptions"),
+            Block::Message => Some("google.protobuf.MessageOptions"),
+            Block::Enum => Some("google.protobuf.EnumOptions"),
+            Block::Service => Some("google.protobuf.ServiceOptions"),
+            Block::Oneof => Some("google.protobuf.OneofOptions"),
+            Block::Method => Some("google.protobuf.MethodOptions"),
+            Block::Extend | Block::Other => None,
+        }
+    }
+}
+
+/// Which names can be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Names<'a> {
+    /// Messages and enums.
+    Types,
+    /// Only messages, for rpc types and extendees.
+    Messages,
+    /// Extensions of the options message.
+    Extensions(&'a str),
+}
+
+/// A name written before the cursor, `pkg.` in `pkg.Na|`.
+#[derive(Debug)]
+struct Qualifier {
+    absolute: bool,
+    parts: Vec<String>,
+}
+
+struct Context<'a> {
+    file: &'a str,
+    symbols: &'a SymbolTable,
+    block: Block,
+    /// The fully qualified name of the innermost scope.
+    scope: String,
+    /// The tokens of the statement before the name being completed.
+    statement: Vec<&'a Token>,
+    qualifier: Option<Qualifier>,
+}
+
+/// Returns the completions at `offset` in `text`, the text of `file`. The text
+/// before the cursor is tokenized rather than parsed, since the statement
+/// being written usually doesn't parse yet.
+pub fn completions(
+    text: &str,
+    offset: usize,
+    file: &str,
+    symbols: &SymbolTable,
+) -> Vec<Completion> {
+    let Some(prefix) = text.get(..offset) else {
+        return Vec::new();
+    };
+    let tokens: Vec<Token> = tokenize(prefix).collect();
+
+    // Nothing is completed inside comments and strings.
+    if let Some(
+        TokenKind::UnterminatedString
+        | TokenKind::Comment(CommentKind::Line | CommentKind::UnterminatedBlock),
+    ) = tokens.last().map(|token| token.kind)
+    {
+        return Vec::new();
+    }
+
+    let mut tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| {
+            !matches!(
+                token.kind,
+                TokenKind::Comment(_) | TokenKind::NewLine | TokenKind::Eof
+            )
+        })
+        .collect();
+
+    // The word being completed.
+    if tokens
+        .last()
+        .is_some_and(|token| is_name(token) && token.end().offset == offset)
+    {
+        tokens.pop();
+    }
+    let qualifier = qualifier(&mut tokens);
+
+    let (block, scope, statement) = blocks(tokens);
+    let context = Context {
+        file,
+        symbols,
+        block,
+        scope,
+        statement,
+        qualifier,
+    };
+
+    let mut completions = context.complete();
+    completions.sort_by(|a, b| a.label.cmp(&b.label));
+    completions.dedup_by(|a, b| a.label == b.label);
+    completions
+}
+
+fn is_name(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Identifier | TokenKind::Keyword(_))
+}
+
+fn is_keyword(token: &Token, keyword: Keyword) -> bool {
+    token.kind == TokenKind::Keyword(keyword)
+}
+
+/// Takes the dotted name before the word being completed off `tokens`.
+fn qualifier(tokens: &mut Vec<&Token>) -> Option<Qualifier> {
+    let mut qualifier: Option<Qualifier> = None;
+
+    while tokens
+        .last()
+        .is_some_and(|token| token.kind == TokenKind::Dot)
+    {
+        tokens.pop();
+        let qualifier = qualifier.get_or_insert(Qualifier {
+            absolute: false,
+            parts: Vec::new(),
+        });
+
+        match tokens.last() {
+            Some(token) if is_name(token) => {
+                qualifier.parts.insert(0, token.value.clone());
+                tokens.pop();
+            }
+            _ => {
+                qualifier.absolute = true;
+                break;
+            }
+        }
+    }
+
+    qualifier
+}
+
+/// Follows the braces in `tokens` to find the innermost block and its
+/// scope, and returns them with the tokens of the unfinished statement.
+fn blocks(tokens: Vec<&Token>) -> (Block, String, Vec<&Token>) {
+    struct Open<'a> {
+        block: Block,
+        scope: String,
+        /// The statement an aggregate option value is part of.
+        statement: Vec<&'a Token>,
+    }
+
+    let mut stack = vec![Open {
+        block: Block::File,
+        scope: String::new(),
+        statement: Vec::new(),
+    }];
+    let mut statement: Vec<&Token> = Vec::new();
+
+    for token in tokens {
+        let open = stack.last().unwrap();
+        match token.kind {
+            TokenKind::SemiColon => {
+                if open.block == Block::File
+                    && statement
+                        .first()
+                        .is_some_and(|token| is_keyword(token, Keyword::Package))
+                {
+                    let package: Vec<&str> = statement[1..]
+                        .iter()
+                        .filter(|token| is_name(token))
+                        .map(|token| token.value.as_str())
+                        .collect();
+                    stack[0].scope = package.join(".");
+                }
+                statement.clear();
+            }
+            TokenKind::LBrace => {
+                let (block, name) = block_kind(open.block, &statement);
+                let scope = match name {
+                    Some(name) => qualify(&open.scope, name),
+                    None => open.scope.clone(),
+                };
+                let statement = std::mem::take(&mut statement);
+                stack.push(Open {
+                    block,
+                    scope,
+                    statement: if block == Block::Other {
+                        statement
+                    } else {
+                        Vec::new()
+                    },
+                });
+            }
+            TokenKind::RBrace => {
+                if stack.len() > 1 {
+                    statement = stack.pop().unwrap().statement;
+                } else {
+                    statement.clear();
+                }
+            }
+            _ => statement.push(token),
+        }
+    }
+
+    let open = stack.pop().unwrap();
+    (open.block, open.scope, statement)
+}
+
+/// The kind of block the braces after `statement` open, and the name it
+/// adds to the scope.
+fn block_kind<'a>(parent: Block, statement: &[&'a Token]) -> (Block, Option<&'a str>) {
+    let name = |index: usize| {
+        statement
+            .get(index)
+            .filter(|token| is_name(token))
+            .map(|token| token.value.as_str())
+    };
+
+    if parent == Block::Other
+        || statement
+            .iter()
+            .any(|token| token.kind == TokenKind::Equals)
+    {
+        // An aggregate value, unless it's the body of a group.
+        if let Some(group) = statement
+            .iter()
+            .position(|token| is_keyword(token, Keyword::Group))
+        {
+            if parent != Block::Other {
+                return (Block::Message, name(group + 1));
+            }
+        }
+        return (Block::Other, None);
+    }
+
+    match statement.first().map(|token| token.kind) {
+        Some(TokenKind::Keyword(Keyword::Message)) => (Block::Message, name(1)),
+        Some(TokenKind::Keyword(Keyword::Enum)) => (Block::Enum, name(1)),
+        Some(TokenKind::Keyword(Keyword::Service)) => (Block::Service, name(1)),
+        Some(TokenKind::Keyword(Keyword::Oneof)) => (Block::Oneof, None),
+        Some(TokenKind::Keyword(Keyword::Extend)) => (Block::Extend, None),
+        Some(TokenKind::Keyword(Keyword::Rpc)) => (Block::Method, None),
+        _ => (Block::Other, None),
+    }
+}
+
+impl Context<'_> {
+    fn complete(&self) -> Vec<Completion> {
+        let statement = &self.statement;
+
+        if self.block == Block::Other {
+            return Vec::new();
+        }
+
+        // Field options, `int32 a = 1 [deprecated = true, (my_option) = 1];`.
+        let open_bracket = statement
+            .iter()
+            .rposition(|token| token.kind == TokenKind::LBracket);
+        let close_bracket = statement
+            .iter()
+            .rposition(|token| token.kind == TokenKind::RBracket);
+        if let Some(open) = open_bracket.filter(|open| close_bracket < Some(*open)) {
+            let option = &statement[open + 1..];
+            let option = match option
                 .iter()
-                .filter(|root_node| {
-                    matches!(
-                        root_node.value,
-                        RootDeclaration::Message(_) | RootDeclaration::Enum(_)
-                    )
+                .rposition(|token| token.kind == TokenKind::Comma)
+            {
+                Some(comma) => &option[comma + 1..],
+                None => option,
+            };
+
+            let target = if self.block == Block::Enum {
+                "google.protobuf.EnumValueOptions"
+            } else if statement
+                .first()
+                .is_some_and(|token| is_keyword(token, Keyword::Extensions))
+            {
+                "google.protobuf.ExtensionRangeOptions"
+            } else {
+                "google.protobuf.FieldOptions"
+            };
+            return self.option(target, option);
+        }
+
+        if statement
+            .first()
+            .is_some_and(|token| is_keyword(token, Keyword::Option))
+        {
+            return match self.block.options() {
+                Some(target) => self.option(target, &statement[1..]),
+                None => Vec::new(),
+            };
+        }
+
+        let keywords = |keywords: &[&str]| -> Vec<Completion> {
+            if self.qualifier.is_some() {
+                return Vec::new();
+            }
+            keywords
+                .iter()
+                .map(|keyword| Completion::new(keyword, CompletionKind::Keyword))
+                .collect()
+        };
+        let field_types = || {
+            let mut completions = keywords(&[]);
+            if self.qualifier.is_none() {
+                completions.extend(
+                    SCALARS
+                        .iter()
+                        .map(|scalar| Completion::new(scalar, CompletionKind::Scalar)),
+                );
+            }
+            completions.extend(self.names(Names::Types));
+            completions
+        };
+        let kinds: Vec<TokenKind> = statement.iter().map(|token| token.kind).collect();
+        let label = |kind: &TokenKind| {
+            matches!(
+                kind,
+                TokenKind::Keyword(Keyword::Optional | Keyword::Repeated | Keyword::Required)
+            )
+        };
+
+        match (self.block, kinds.as_slice()) {
+            (Block::File, []) => keywords(FILE_KEYWORDS),
+            (Block::File, [TokenKind::Keyword(Keyword::Import)]) => keywords(&["public", "weak"]),
+            (Block::File | Block::Message, [TokenKind::Keyword(Keyword::Extend)]) => {
+                self.names(Names::Messages)
+            }
+
+            (Block::Message, []) => {
+                let mut completions = keywords(MESSAGE_KEYWORDS);
+                completions.extend(field_types());
+                completions
+            }
+            (Block::Message | Block::Extend, [kind]) if label(kind) => {
+                let mut completions = keywords(&["group"]);
+                completions.extend(field_types());
+                completions
+            }
+            (Block::Message, [TokenKind::Keyword(Keyword::Map), TokenKind::LAngle]) => {
+                keywords(MAP_KEYS)
+                    .into_iter()
+                    .map(|completion| Completion {
+                        kind: CompletionKind::Scalar,
+                        ..completion
+                    })
+                    .collect()
+            }
+            (
+                Block::Message,
+                [TokenKind::Keyword(Keyword::Map), TokenKind::LAngle, TokenKind::Keyword(_), TokenKind::Comma],
+            ) => field_types(),
+
+            (Block::Oneof, []) => {
+                let mut completions = keywords(&["option", "group"]);
+                completions.extend(field_types());
+                completions
+            }
+            (Block::Extend, []) => {
+                let mut completions = keywords(EXTEND_KEYWORDS);
+                completions.extend(field_types());
+                completions
+            }
+            (Block::Enum, []) => keywords(&["option", "reserved"]),
+            (Block::Service, []) => keywords(&["rpc", "option"]),
+            (Block::Service, [TokenKind::Keyword(Keyword::Rpc), ..]) => self.rpc(&kinds[1..]),
+            (Block::Method, []) => keywords(&["option"]),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Completes an rpc signature, `rpc Name(stream Request) returns (Response)`,
+    /// after its `rpc` keyword.
+    fn rpc(&self, kinds: &[TokenKind]) -> Vec<Completion> {
+        let returns = kinds.contains(&TokenKind::Keyword(Keyword::Returns));
+
+        let mut completions = Vec::new();
+        match kinds.last() {
+            Some(TokenKind::LParen) => {
+                if self.qualifier.is_none() {
+                    completions.push(Completion::new("stream", CompletionKind::Keyword));
+                }
+                completions.extend(self.names(Names::Messages));
+            }
+            Some(TokenKind::Keyword(Keyword::Stream)) => {
+                completions.extend(self.names(Names::Messages))
+            }
+            Some(TokenKind::RParen) if !returns && self.qualifier.is_none() => {
+                completions.push(Completion::new("returns", CompletionKind::Keyword));
+            }
+            _ => {}
+        }
+        completions
+    }
+
+    /// Completes an option of the options message `target`, given the
+    /// tokens written after `option` or `[`.
+    fn option(&self, target: &str, option: &[&Token]) -> Vec<Completion> {
+        let kinds: Vec<TokenKind> = option.iter().map(|token| token.kind).collect();
+
+        match kinds.as_slice() {
+            [] if self.qualifier.is_none() => {
+                let mut completions: Vec<Completion> = self
+                    .fields(target)
+                    .filter(|name| *name != "uninterpreted_option")
+                    .map(|name| Completion {
+                        detail: Some(format!("{target}.{name}")),
+                        ..Completion::new(name, CompletionKind::Option)
+                    })
+                    .collect();
+                if target == "google.protobuf.FieldOptions" {
+                    completions.extend(
+                        PSEUDO_FIELD_OPTIONS
+                            .iter()
+                            .map(|name| Completion::new(name, CompletionKind::Option)),
+                    );
+                }
+                completions
+            }
+            [TokenKind::LParen] => self.names(Names::Extensions(target)),
+            [.., TokenKind::Equals] if self.qualifier.is_none() => {
+                self.option_value(target, &option[..option.len() - 1])
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The names of the fields of the message `name`.
+    fn fields<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s str> {
+        self.symbols.iter().filter_map(move |(field, symbol)| {
+            let field = field.strip_prefix(name)?.strip_prefix('.')?;
+            (symbol.kind == SymbolKind::Field && !field.contains('.')).then_some(field)
+        })
+    }
+
+    /// Completes the value of the option named `name`.
+    fn option_value(&self, target: &str, name: &[&Token]) -> Vec<Completion> {
+        self.option_type(target, name)
+            .map_or_else(Vec::new, |value_type| self.values(value_type))
+    }
+
+    /// The type of the option named `name`: a field of `target`, an
+    /// extension or the `default` of the field being declared.
+    fn option_type(&self, target: &str, name: &[&Token]) -> Option<ValueType> {
+        match name {
+            [token] if token.value == "default" => self.declared_field_type(),
+            [token] if is_name(token) => {
+                let name = format!("{target}.{}", token.value);
+                let symbol = self.symbols.get(&name).first()?;
+                self.declared_type(&name, symbol)
+            }
+            [open, extension @ .., close]
+                if open.kind == TokenKind::LParen && close.kind == TokenKind::RParen =>
+            {
+                let (name, symbol) = self
+                    .symbols
+                    .resolve(self.file, &self.scope, &type_name(extension), false)
+                    .ok()?;
+                self.declared_type(&name, symbol)
+            }
+            _ => None,
+        }
+    }
+
+    /// Completes the values of a type: the values of an enum or `true` and
+    /// `false`.
+    fn values(&self, value_type: ValueType) -> Vec<Completion> {
+        match value_type {
+            ValueType::Scalar(ScalarType::Bool) => ["true", "false"]
+                .iter()
+                .map(|value| Completion::new(value, CompletionKind::Value))
+                .collect(),
+            ValueType::Scalar(_) => Vec::new(),
+            ValueType::Enum(enum_name) => {
+                let enum_type = FieldType::TypeName(TypeName::from(format!(".{enum_name}")));
+                self.symbols
+                    .iter()
+                    .filter(|(_, symbol)| {
+                        symbol.kind == SymbolKind::EnumValue
+                            && symbol.value_type.as_ref() == Some(&enum_type)
+                    })
+                    .map(|(name, _)| Completion {
+                        detail: Some(name.to_string()),
+                        ..Completion::new(
+                            name.rsplit('.').next().unwrap_or(name),
+                            CompletionKind::EnumValue,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The type of the symbol `name`, if it's a scalar or an enum.
+    fn declared_type(&self, name: &str, symbol: &Symbol) -> Option<ValueType> {
+        match symbol.value_type.as_ref()? {
+            FieldType::ScalarType(scalar) => Some(ValueType::Scalar(scalar.clone())),
+            FieldType::TypeName(type_name) => {
+                let (resolved, symbol) = self.symbols.resolve_declared(name, symbol, type_name)?;
+                (symbol.kind == SymbolKind::Enum).then_some(ValueType::Enum(resolved))
+            }
+        }
+    }
+
+    /// The type of the field the statement declares, for its `default`.
+    fn declared_field_type(&self) -> Option<ValueType> {
+        let equals = self
+            .statement
+            .iter()
+            .position(|token| token.kind == TokenKind::Equals)?;
+        let start = usize::from(self.statement.first().is_some_and(|token| {
+            matches!(
+                token.kind,
+                TokenKind::Keyword(Keyword::Optional | Keyword::Repeated | Keyword::Required)
+            )
+        }));
+        let field_type = self.statement.get(start..equals.checked_sub(1)?)?;
+
+        if let [token] = field_type {
+            if let Ok(scalar) = ScalarType::try_from(token.kind) {
+                return Some(ValueType::Scalar(scalar));
+            }
+        }
+
+        let (resolved, symbol) = self
+            .symbols
+            .resolve(self.file, &self.scope, &type_name(field_type), true)
+            .ok()?;
+        (symbol.kind == SymbolKind::Enum).then_some(ValueType::Enum(resolved))
+    }
+
+    /// Completes the names of declarations. After a qualifier, these are the
+    /// names declared in the package or message it refers to, otherwise the
+    /// names visible from the scope, relative to it.
+    fn names(&self, names: Names) -> Vec<Completion> {
+        let matches = |name: &str, symbol: &Symbol| match names {
+            Names::Types => symbol.kind.is_type(),
+            Names::Messages => symbol.kind == SymbolKind::Message,
+            Names::Extensions(target) => {
+                symbol.kind == SymbolKind::Extension
+                    && symbol
+                        .extendee
+                        .as_ref()
+                        .and_then(|extendee| self.symbols.resolve_declared(name, symbol, extendee))
+                        .is_some_and(|(extendee, _)| extendee == target)
+            }
+        };
+        let kind = |symbol: &Symbol| match symbol.kind {
+            SymbolKind::Package => CompletionKind::Package,
+            SymbolKind::Message => CompletionKind::Message,
+            SymbolKind::Enum => CompletionKind::Enum,
+            _ => CompletionKind::Extension,
+        };
+
+        let Some(qualifier) = &self.qualifier else {
+            return self
+                .symbols
+                .visible(self.file)
+                .filter(|(name, symbol)| {
+                    matches(name, symbol)
+                        || (symbol.kind == SymbolKind::Package && !name.contains('.'))
                 })
-                .map(|root_node| match root_node.value {
-                    RootDeclaration::Message(message_declaration) => {
-                        message_declaration.name.clone()
+                .map(|(name, symbol)| {
+                    let label = match symbol.kind {
+                        SymbolKind::Package => name.to_string(),
+                        _ => self.relative_name(name, names),
+                    };
+                    Completion {
+                        detail: Some(name.to_string()),
+                        ..Completion::new(&label, kind(symbol))
                     }
-                    RootDeclaration::Enum(enum_declaration) => enum_declaration.name.clone(),
-                    _ => unreachable!(),
                 })
-        })
-        .collect()
+                .collect();
+        };
+
+        let parent = if qualifier.parts.is_empty() {
+            String::new()
+        } else {
+            let type_name = TypeName {
+                absolute: qualifier.absolute,
+                parts: qualifier.parts.clone(),
+            };
+            match self
+                .symbols
+                .resolve(self.file, &self.scope, &type_name, false)
+            {
+                Ok((parent, symbol)) if symbol.kind.is_aggregate() => parent,
+                _ => return Vec::new(),
+            }
+        };
+
+        self.symbols
+            .visible(self.file)
+            .filter(|(name, _)| {
+                let child = match parent.as_str() {
+                    "" => Some(*name),
+                    parent => name
+                        .strip_prefix(parent)
+                        .and_then(|name| name.strip_prefix('.')),
+                };
+                child.is_some_and(|child| !child.contains('.'))
+            })
+            .filter(|(name, symbol)| {
+                matches(name, symbol)
+                    || matches!(symbol.kind, SymbolKind::Package | SymbolKind::Message)
+            })
+            .map(|(name, symbol)| Completion {
+                detail: Some(name.to_string()),
+                ..Completion::new(name.rsplit('.').next().unwrap_or(name), kind(symbol))
+            })
+            .collect()
+    }
+
+    /// The shortest name that refers to the symbol `name` from the scope.
+    fn relative_name(&self, name: &str, names: Names) -> String {
+        let types_only = !matches!(names, Names::Extensions(_));
+        let parts: Vec<&str> = name.split('.').collect();
+
+        (0..parts.len())
+            .rev()
+            .map(|start| parts[start..].join("."))
+            .find(|relative| {
+                let type_name = TypeName::from(relative.clone());
+                self.symbols
+                    .resolve(self.file, &self.scope, &type_name, types_only)
+                    .is_ok_and(|(resolved, _)| resolved == name)
+            })
+            .unwrap_or_else(|| format!(".{name}"))
+    }
+}
+
+/// A type whose values can be completed.
+enum ValueType {
+    Scalar(ScalarType),
+    /// The fully qualified name of an enum.
+    Enum(String),
+}
+
+fn type_name(tokens: &[&Token]) -> TypeName {
+    TypeName {
+        absolute: tokens
+            .first()
+            .is_some_and(|token| token.kind == TokenKind::Dot),
+        parts: tokens
+            .iter()
+            .filter(|token| is_name(token))
+            .map(|token| token.value.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Position, Source};
+
+    use super::CompletionKind;
+
+    fn position(offset: usize) -> Position {
+        Position {
+            offset,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the labels of the completions at `|` in `text`.
+    fn complete(source: &mut Source, text: &str) -> Vec<(String, CompletionKind)> {
+        let offset = text.find('|').unwrap();
+        let text = text.replacen('|', "", 1);
+        source.parse("/ws/test.proto", &text);
+
+        source
+            .completions("/ws/test.proto", position(offset))
+            .into_iter()
+            .map(|completion| (completion.label, completion.kind))
+            .collect()
+    }
+
+    fn labels(source: &mut Source, text: &str) -> Vec<String> {
+        complete(source, text)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect()
+    }
+
+    fn source() -> Source {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/types.proto",
+            r#"
+            syntax = "proto2";
+            package acme.types;
+            import "google/protobuf/descriptor.proto";
+            message Money { enum Currency { USD = 0; EUR = 1; } }
+            enum Color { RED = 0; GREEN = 1; }
+            extend google.protobuf.FieldOptions { optional Color color = 50000; }
+            extend google.protobuf.MessageOptions { optional bool cached = 50000; }
+            "#,
+        );
+        source
+    }
+
+    #[test]
+    fn completes_keywords_at_statement_starts() {
+        let mut source = source();
+
+        let top = labels(&mut source, "syntax = \"proto3\";\n|");
+        assert!(top.contains(&"message".to_string()));
+        assert!(top.contains(&"package".to_string()));
+        assert!(!top.contains(&"int32".to_string()));
+
+        let enum_body = labels(&mut source, "enum E {\n  A = 0;\n  |\n}");
+        assert_eq!(enum_body, ["option", "reserved"]);
+
+        let service = labels(&mut source, "service S { r| }");
+        assert_eq!(service, ["option", "rpc"]);
+
+        assert!(labels(&mut source, "// a comment |").is_empty());
+        assert!(labels(&mut source, "message M { string a = 1; |").contains(&"oneof".to_string()));
+    }
+
+    #[test]
+    fn completes_field_types() {
+        let mut source = source();
+        let text = r#"
+            package acme.api;
+            import "types.proto";
+            message Order {
+                message Line {}
+                repeated |
+            }
+        "#;
+        let completions = complete(&mut source, text);
+
+        for expected in [
+            ("int32", CompletionKind::Scalar),
+            ("Line", CompletionKind::Message),
+            ("Order", CompletionKind::Message),
+            ("types.Color", CompletionKind::Enum),
+            ("types.Money.Currency", CompletionKind::Enum),
+            ("acme", CompletionKind::Package),
+        ] {
+            let expected = (expected.0.to_string(), expected.1);
+            assert!(
+                completions.contains(&expected),
+                "{expected:?} {completions:?}"
+            );
+        }
+        assert!(!completions.iter().any(|(label, _)| label == "oneof"));
+
+        let map_keys = labels(&mut source, "message M { map<| }");
+        assert!(map_keys.contains(&"string".to_string()));
+        assert!(!map_keys.contains(&"double".to_string()));
+    }
+
+    #[test]
+    fn completes_qualified_names() {
+        let mut source = source();
+        let text = "import \"types.proto\"; message M { acme.types.| }";
+        assert_eq!(labels(&mut source, text), ["Color", "Money"]);
+
+        let text = "import \"types.proto\"; message M { .acme.types.Money.C| }";
+        assert_eq!(labels(&mut source, text), ["Currency"]);
+
+        let text = "import \"google/protobuf/timestamp.proto\"; message M { google.protobuf.| }";
+        assert_eq!(labels(&mut source, text), ["Timestamp"]);
+    }
+
+    #[test]
+    fn completes_options_and_values() {
+        let mut source = source();
+
+        let file_options = labels(&mut source, "option |");
+        assert!(file_options.contains(&"java_package".to_string()));
+        assert!(file_options.contains(&"optimize_for".to_string()));
+        assert!(!file_options.contains(&"uninterpreted_option".to_string()));
+
+        assert_eq!(
+            labels(&mut source, "option optimize_for = |"),
+            ["CODE_SIZE", "LITE_RUNTIME", "SPEED"]
+        );
+        assert_eq!(
+            labels(&mut source, "option java_multiple_files = |"),
+            ["false", "true"]
+        );
+
+        let text = "package acme.types; import \"types.proto\"; message M { optional Color c = 1 [deprecated = true, |] }";
+        let field_options = labels(&mut source, text);
+        assert!(field_options.contains(&"default".to_string()));
+        assert!(field_options.contains(&"lazy".to_string()));
+
+        let text = "package acme.types; import \"types.proto\"; message M { optional Color c = 1 [default = |] }";
+        assert_eq!(labels(&mut source, text), ["GREEN", "RED"]);
+
+        let text =
+            "package acme.types; import \"types.proto\"; message M { optional int32 c = 1 [(|] }";
+        assert_eq!(labels(&mut source, text), ["acme", "color"]);
+
+        let text = "package acme.types; import \"types.proto\"; message M { option (|) }";
+        assert_eq!(labels(&mut source, text), ["acme", "cached"]);
+
+        let text = "package acme.types; import \"types.proto\"; message M { optional int32 c = 1 [(color) = |] }";
+        assert_eq!(labels(&mut source, text), ["GREEN", "RED"]);
+    }
+
+    #[test]
+    fn completes_rpc_signatures() {
+        let mut source = source();
+        let text = "message Req {} service S { rpc Get(|";
+        let completions = labels(&mut source, text);
+        assert!(completions.contains(&"stream".to_string()));
+        assert!(completions.contains(&"Req".to_string()));
+        assert!(!completions.contains(&"int32".to_string()));
+
+        assert_eq!(
+            labels(&mut source, "message Req {} service S { rpc Get(Req) |"),
+            ["returns"]
+        );
+        assert!(labels(
+            &mut source,
+            "message Req {} service S { rpc Get(Req) returns (stream |"
+        )
+        .contains(&"Req".to_string()));
+    }
 }
@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+
+use crate::{
+    message::{FieldCardinality, GroupDeclaration, Message, MessageElement, OneofElement},
+    option::{
+        MessageLiteralFieldName, MessageLiteralValue, OptionNamePart, OptionNode, OptionValue,
+    },
+    service::{ServiceElement, ServiceNode},
+    Edition, EnumElement, EnumNode, ExtensionElement, ExtensionNode, Node, Position, Root,
+    RootDeclaration, SyntaxType,
+};
+
+use super::{package_name, qualify};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldPresence {
+    Explicit,
+    Implicit,
+    LegacyRequired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumType {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatedFieldEncoding {
+    Packed,
+    Expanded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Validation {
+    Verify,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageEncoding {
+    LengthPrefixed,
+    Delimited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    Allow,
+    LegacyBestEffort,
+}
+
+/// The resolved value of every `google.protobuf.FeatureSet` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSet {
+    pub field_presence: FieldPresence,
+    pub enum_type: EnumType,
+    pub repeated_field_encoding: RepeatedFieldEncoding,
+    pub utf8_validation: Utf8Validation,
+    pub message_encoding: MessageEncoding,
+    pub json_format: JsonFormat,
+}
+
+impl FeatureSet {
+    /// The defaults protoc uses for files with the given syntax or edition.
+    pub fn defaults(syntax: &SyntaxType) -> Self {
+        match syntax {
+            SyntaxType::Proto2 => Self {
+                field_presence: FieldPresence::Explicit,
+                enum_type: EnumType::Closed,
+                repeated_field_encoding: RepeatedFieldEncoding::Expanded,
+                utf8_validation: Utf8Validation::None,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::LegacyBestEffort,
+            },
+            SyntaxType::Proto3 => Self {
+                field_presence: FieldPresence::Implicit,
+                enum_type: EnumType::Open,
+                repeated_field_encoding: RepeatedFieldEncoding::Packed,
+                utf8_validation: Utf8Validation::Verify,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::Allow,
+            },
+            SyntaxType::Edition(Edition::Edition2023 | Edition::Edition2024) => Self {
+                field_presence: FieldPresence::Explicit,
+                enum_type: EnumType::Open,
+                repeated_field_encoding: RepeatedFieldEncoding::Packed,
+                utf8_validation: Utf8Validation::Verify,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::Allow,
+            },
+        }
+    }
+
+    fn set(&mut self, feature: &str, value: &str) -> Result<(), String> {
+        match feature {
+            "field_presence" => {
+                self.field_presence = feature_value(
+                    feature,
+                    value,
+                    &[
+                        ("EXPLICIT", FieldPresence::Explicit),
+                        ("IMPLICIT", FieldPresence::Implicit),
+                        ("LEGACY_REQUIRED", FieldPresence::LegacyRequired),
+                    ],
+                )?
+            }
+            "enum_type" => {
+                self.enum_type = feature_value(
+                    feature,
+                    value,
+                    &[("OPEN", EnumType::Open), ("CLOSED", EnumType::Closed)],
+                )?
+            }
+            "repeated_field_encoding" => {
+                self.repeated_field_encoding = feature_value(
+                    feature,
+                    value,
+                    &[
+                        ("PACKED", RepeatedFieldEncoding::Packed),
+                        ("EXPANDED", RepeatedFieldEncoding::Expanded),
+                    ],
+                )?
+            }
+            "utf8_validation" => {
+                self.utf8_validation = feature_value(
+                    feature,
+                    value,
+                    &[
+                        ("VERIFY", Utf8Validation::Verify),
+                        ("NONE", Utf8Validation::None),
+                    ],
+                )?
+            }
+            "message_encoding" => {
+                self.message_encoding = feature_value(
+                    feature,
+                    value,
+                    &[
+                        ("LENGTH_PREFIXED", MessageEncoding::LengthPrefixed),
+                        ("DELIMITED", MessageEncoding::Delimited),
+                    ],
+                )?
+            }
+            "json_format" => {
+                self.json_format = feature_value(
+                    feature,
+                    value,
+                    &[
+                        ("ALLOW", JsonFormat::Allow),
+                        ("LEGACY_BEST_EFFORT", JsonFormat::LegacyBestEffort),
+                    ],
+                )?
+            }
+            _ => return Err(format!("Unknown feature: features.{feature}")),
+        }
+
+        Ok(())
+    }
+}
+
+fn feature_value<T: Copy>(feature: &str, value: &str, values: &[(&str, T)]) -> Result<T, String> {
+    values
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| format!("Invalid value {value} for feature features.{feature}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureError {
+    pub message: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// The effective features of a file and of every message, field, enum and
+/// service declared in it, keyed by fully qualified name (`pkg.Message.field`).
+#[derive(Debug, Clone)]
+pub struct ResolvedFeatures {
+    pub file: FeatureSet,
+    pub symbols: HashMap<String, FeatureSet>,
+    pub errors: Vec<FeatureError>,
+}
+
+impl ResolvedFeatures {
+    pub fn get(&self, name: &str) -> Option<&FeatureSet> {
+        self.symbols.get(name.strip_prefix('.').unwrap_or(name))
+    }
+}
+
+/// Computes the effective `FeatureSet` of every declaration in `root`.
+///
+/// Each declaration starts from the features of its parent and applies its own
+/// `features.*` options on top, the same way protoc merges them. Files using
+/// `syntax` instead of `edition` get the equivalent legacy behaviour, e.g.
+/// `required` fields resolve to `LEGACY_REQUIRED` presence.
+pub fn resolve_features(root: &Root) -> ResolvedFeatures {
+    let syntax = root
+        .nodes
+        .iter()
+        .find_map(|node| match &node.value {
+            RootDeclaration::Syntax(syntax) => Some(syntax.proto_type.clone()),
+            _ => None,
+        })
+        .unwrap_or(SyntaxType::Proto2);
+
+    let package = package_name(root);
+
+    let mut resolver = Resolver {
+        syntax,
+        symbols: HashMap::new(),
+        errors: Vec::new(),
+    };
+
+    let file_options = root.nodes.iter().filter_map(|node| match &node.value {
+        RootDeclaration::Option(option) => Some(option),
+        _ => None,
+    });
+    let file = resolver.apply(FeatureSet::defaults(&resolver.syntax), file_options);
+
+    for node in &root.nodes {
+        match &node.value {
+            RootDeclaration::Message(message) => resolver.message(&package, message, file),
+            RootDeclaration::Enum(enum_node) => resolver.enum_node(&package, enum_node, file),
+            RootDeclaration::Service(service) => resolver.service(&package, service, file),
+            RootDeclaration::Extension(extension) => resolver.extension(&package, extension, file),
+            _ => {}
+        }
+    }
+
+    ResolvedFeatures {
+        file,
+        symbols: resolver.symbols,
+        errors: resolver.errors,
+    }
+}
+
+struct Resolver {
+    syntax: SyntaxType,
+    symbols: HashMap<String, FeatureSet>,
+    errors: Vec<FeatureError>,
+}
+
+impl Resolver {
+    fn apply<'a>(
+        &mut self,
+        mut features: FeatureSet,
+        options: impl IntoIterator<Item = &'a OptionNode>,
+    ) -> FeatureSet {
+        for option in options {
+            let name = &option.name.value;
+            let Some(OptionNamePart::SimpleName(first)) = name.first().map(|part| &part.value)
+            else {
+                continue;
+            };
+            if first != "features" {
+                continue;
+            }
+
+            if !matches!(self.syntax, SyntaxType::Edition(_)) {
+                self.error("Features are only valid under editions".to_string(), option);
+                continue;
+            }
+
+            match (name.get(1).map(|part| &part.value), &option.value.value) {
+                // Features defined by extensions of `FeatureSet`, such as
+                // `features.(pb.cpp).legacy_closed_enum`, are language specific.
+                (Some(OptionNamePart::ExtensionName(_)), _) => {}
+                (Some(OptionNamePart::SimpleName(feature)), OptionValue::Identifier(value))
+                    if name.len() == 2 =>
+                {
+                    if let Err(message) = features.set(feature, value) {
+                        self.error(message, option);
+                    }
+                }
+                (None, OptionValue::MessageLiteral(literal)) => {
+                    for field in &literal.fields {
+                        let MessageLiteralFieldName::Simple(feature) = &field.value.name.value
+                        else {
+                            continue;
+                        };
+                        let result = match &field.value.value.value {
+                            MessageLiteralValue::Identifier(value) => features.set(feature, value),
+                            _ => Err(format!(
+                                "Expected enum value for feature features.{feature}"
+                            )),
+                        };
+                        if let Err(message) = result {
+                            self.error(message, option);
+                        }
+                    }
+                }
+                _ => self.error("Invalid features option".to_string(), option),
+            }
+        }
+
+        features
+    }
+
+    fn error(&mut self, message: String, option: &OptionNode) {
+        self.errors.push(FeatureError {
+            message,
+            start: option.name.start,
+            end: option.value.end,
+        });
+    }
+
+    fn insert(&mut self, name: String, features: FeatureSet) {
+        self.symbols.insert(name, features);
+    }
+
+    fn message(&mut self, scope: &str, message: &Message, parent: FeatureSet) {
+        let name = qualify(scope, &message.name.value);
+        let options = message
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                MessageElement::Option(option) => Some(option),
+                _ => None,
+            });
+        let features = self.apply(parent, options);
+        self.insert(name.clone(), features);
+        self.message_elements(&name, &message.elements, features);
+    }
+
+    fn message_elements(
+        &mut self,
+        scope: &str,
+        elements: &[Node<MessageElement>],
+        features: FeatureSet,
+    ) {
+        for element in elements {
+            match &element.value {
+                MessageElement::Field(field) => {
+                    let mut field_features = self.apply(features, option_values(&field.options));
+                    self.legacy_field_features(
+                        &mut field_features,
+                        &field.cardinality,
+                        &field.options,
+                        false,
+                    );
+                    self.insert(qualify(scope, &field.name.value), field_features);
+                }
+                MessageElement::MapField(field) => {
+                    let field_features = self.apply(features, option_values(&field.options));
+                    self.insert(qualify(scope, &field.name.value), field_features);
+                }
+                MessageElement::Group(group) => self.group(scope, group, features, false),
+                MessageElement::OneOf(oneof) => {
+                    let options =
+                        oneof
+                            .elements
+                            .iter()
+                            .filter_map(|element| match &element.value {
+                                OneofElement::Option(option) => Some(option),
+                                _ => None,
+                            });
+                    let oneof_features = self.apply(features, options);
+                    self.insert(qualify(scope, &oneof.name.value), oneof_features);
+
+                    for element in &oneof.elements {
+                        match &element.value {
+                            OneofElement::OneofField(field) => {
+                                let options = field.options.as_deref().unwrap_or_default();
+                                let mut field_features =
+                                    self.apply(oneof_features, option_values(options));
+                                // Members of a oneof always track presence.
+                                if !matches!(self.syntax, SyntaxType::Edition(_)) {
+                                    field_features.field_presence = FieldPresence::Explicit;
+                                }
+                                self.insert(qualify(scope, &field.name.value), field_features);
+                            }
+                            OneofElement::Group(group) => {
+                                self.group(scope, group, oneof_features, true)
+                            }
+                            OneofElement::Option(_) => {}
+                        }
+                    }
+                }
+                MessageElement::Message(message) => self.message(scope, message, features),
+                MessageElement::Enum(enum_node) => self.enum_node(scope, enum_node, features),
+                MessageElement::Extension(extension) => self.extension(scope, extension, features),
+                _ => {}
+            }
+        }
+    }
+
+    /// A group declares both a field, named after the lowercased group name,
+    /// and a nested message type.
+    fn group(&mut self, scope: &str, group: &GroupDeclaration, parent: FeatureSet, oneof: bool) {
+        let mut field_features = self.apply(parent, option_values(&group.options));
+        self.legacy_field_features(
+            &mut field_features,
+            &group.cardinality,
+            &group.options,
+            oneof,
+        );
+        field_features.message_encoding = MessageEncoding::Delimited;
+        self.insert(
+            qualify(scope, &group.name.value.to_ascii_lowercase()),
+            field_features,
+        );
+
+        let name = qualify(scope, &group.name.value);
+        let options = group
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                MessageElement::Option(option) => Some(option),
+                _ => None,
+            });
+        let features = self.apply(parent, options);
+        self.insert(name.clone(), features);
+        self.message_elements(&name, &group.elements, features);
+    }
+
+    /// Maps proto2 and proto3 field syntax onto the features it implies.
+    fn legacy_field_features(
+        &self,
+        features: &mut FeatureSet,
+        cardinality: &Node<FieldCardinality>,
+        options: &[Node<OptionNode>],
+        oneof: bool,
+    ) {
+        if matches!(self.syntax, SyntaxType::Edition(_)) {
+            return;
+        }
+
+        // An implicit label is recorded as an empty `Optional` span.
+        let explicit_label = cardinality.start != cardinality.end;
+
+        match cardinality.value {
+            FieldCardinality::Required => features.field_presence = FieldPresence::LegacyRequired,
+            FieldCardinality::Optional if explicit_label || oneof => {
+                features.field_presence = FieldPresence::Explicit
+            }
+            _ => {}
+        }
+
+        for option in option_values(options) {
+            let is_packed = matches!(
+                option.name.value.as_slice(),
+                [part] if matches!(&part.value, OptionNamePart::SimpleName(name) if name == "packed")
+            );
+            if !is_packed {
+                continue;
+            }
+
+            match &option.value.value {
+                OptionValue::Identifier(value) if value == "true" => {
+                    features.repeated_field_encoding = RepeatedFieldEncoding::Packed
+                }
+                OptionValue::Identifier(value) if value == "false" => {
+                    features.repeated_field_encoding = RepeatedFieldEncoding::Expanded
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn extension(&mut self, scope: &str, extension: &ExtensionNode, features: FeatureSet) {
+        for element in &extension.elements {
+            match &element.value {
+                ExtensionElement::Field(field) => {
+                    let mut field_features = self.apply(features, option_values(&field.options));
+                    self.legacy_field_features(
+                        &mut field_features,
+                        &field.cardinality,
+                        &field.options,
+                        false,
+                    );
+                    self.insert(qualify(scope, &field.name.value), field_features);
+                }
+                ExtensionElement::Group(group) => self.group(scope, group, features, false),
+            }
+        }
+    }
+
+    fn enum_node(&mut self, scope: &str, enum_node: &EnumNode, parent: FeatureSet) {
+        let options = enum_node
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                EnumElement::EnumOption(option) => Some(option),
+                _ => None,
+            });
+        let features = self.apply(parent, options);
+        self.insert(qualify(scope, &enum_node.name.value), features);
+    }
+
+    fn service(&mut self, scope: &str, service: &ServiceNode, parent: FeatureSet) {
+        let options = service
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                ServiceElement::Option(option) => Some(option),
+                _ => None,
+            });
+        let features = self.apply(parent, options);
+        self.insert(qualify(scope, &service.name.value), features);
+    }
+}
+
+fn option_values(options: &[Node<OptionNode>]) -> impl Iterator<Item = &OptionNode> {
+    options.iter().map(|option| &option.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenize, Parser};
+
+    use super::{
+        resolve_features, EnumType, FieldPresence, MessageEncoding, RepeatedFieldEncoding,
+        ResolvedFeatures,
+    };
+
+    fn resolve(input: &str) -> ResolvedFeatures {
+        let result = Parser::new(tokenize(input)).parse("test.proto");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        resolve_features(&result.root)
+    }
+
+    #[test]
+    fn edition_features_are_inherited() {
+        let features = resolve(
+            r#"
+            edition = "2023";
+            package pkg;
+
+            option features.field_presence = IMPLICIT;
+
+            message Outer {
+                option features.enum_type = CLOSED;
+
+                int32 a = 1;
+                int32 b = 2 [features.field_presence = EXPLICIT];
+                repeated int32 c = 3 [features = { repeated_field_encoding: EXPANDED }];
+
+                message Inner {
+                    int32 d = 1;
+                }
+
+                enum Kind {
+                    KIND_UNSPECIFIED = 0;
+                }
+            }
+
+            service Api {}
+            "#,
+        );
+
+        assert!(features.errors.is_empty(), "{:?}", features.errors);
+        assert_eq!(features.file.field_presence, FieldPresence::Implicit);
+
+        let outer = features.get("pkg.Outer").unwrap();
+        assert_eq!(outer.enum_type, EnumType::Closed);
+        assert_eq!(outer.field_presence, FieldPresence::Implicit);
+
+        let a = features.get(".pkg.Outer.a").unwrap();
+        assert_eq!(a.field_presence, FieldPresence::Implicit);
+        assert_eq!(a.enum_type, EnumType::Closed);
+
+        let b = features.get("pkg.Outer.b").unwrap();
+        assert_eq!(b.field_presence, FieldPresence::Explicit);
+
+        let c = features.get("pkg.Outer.c").unwrap();
+        assert_eq!(c.repeated_field_encoding, RepeatedFieldEncoding::Expanded);
+
+        let d = features.get("pkg.Outer.Inner.d").unwrap();
+        assert_eq!(d.field_presence, FieldPresence::Implicit);
+        assert_eq!(d.enum_type, EnumType::Closed);
+
+        let kind = features.get("pkg.Outer.Kind").unwrap();
+        assert_eq!(kind.enum_type, EnumType::Closed);
+
+        assert!(features.get("pkg.Api").is_some());
+    }
+
+    #[test]
+    fn legacy_syntax_features() {
+        let features = resolve(
+            r#"
+            syntax = "proto2";
+
+            message Test {
+                required int32 a = 1;
+                repeated int32 b = 2 [packed = true];
+                optional group Result = 3 {}
+            }
+            "#,
+        );
+
+        assert_eq!(
+            features.get("Test.a").unwrap().field_presence,
+            FieldPresence::LegacyRequired
+        );
+        assert_eq!(
+            features.get("Test.b").unwrap().repeated_field_encoding,
+            RepeatedFieldEncoding::Packed
+        );
+        assert_eq!(
+            features.get("Test.result").unwrap().message_encoding,
+            MessageEncoding::Delimited
+        );
+        assert!(features.get("Test.Result").is_some());
+
+        let features = resolve(
+            r#"
+            syntax = "proto3";
+
+            message Test {
+                int32 a = 1;
+                optional int32 b = 2;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            features.get("Test.a").unwrap().field_presence,
+            FieldPresence::Implicit
+        );
+        assert_eq!(
+            features.get("Test.b").unwrap().field_presence,
+            FieldPresence::Explicit
+        );
+    }
+
+    #[test]
+    fn invalid_features() {
+        let features = resolve(
+            r#"
+            edition = "2023";
+
+            option features.field_presence = MAYBE;
+            option features.unknown = YES;
+            option features.(pb.cpp).legacy_closed_enum = true;
+            "#,
+        );
+
+        let messages: Vec<&str> = features.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid value MAYBE for feature features.field_presence",
+                "Unknown feature: features.unknown",
+            ]
+        );
+
+        let features = resolve(
+            r#"
+            syntax = "proto3";
+            option features.field_presence = EXPLICIT;
+            "#,
+        );
+
+        assert_eq!(
+            features.errors[0].message,
+            "Features are only valid under editions"
+        );
+    }
+}
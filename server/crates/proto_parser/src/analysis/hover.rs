@@ -0,0 +1,799 @@
+use crate::{
+    features::{resolve_features, RepeatedFieldEncoding},
+    message::{
+        FieldCardinality, FieldType, GroupDeclaration, Message, MessageElement, OneofElement,
+    },
+    option::{OptionNamePart, OptionNode, OptionValue},
+    service::{MethodNode, ServiceElement, ServiceNode},
+    symbols::{Symbol, SymbolKind, SymbolTable},
+    well_known_types, EnumElement, EnumNode, ExtensionElement, ExtensionNode, Node, Position, Root,
+    RootDeclaration, ScalarType,
+};
+
+/// The description of the symbol a name refers to, with the span of the
+/// name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    pub contents: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Describes the symbol `name` as markdown: its definition, kind and file,
+/// the wire type and default value of a field, and its doc comment. `root`
+/// returns the parsed file with the given name.
+pub fn describe<'a>(
+    name: &str,
+    symbol: &Symbol,
+    root: impl Fn(&str) -> Option<&'a Root>,
+    symbols: &SymbolTable,
+) -> String {
+    let file = root(&symbol.location.file);
+    let declaration = file.and_then(|file| find(file, symbol.location.start.offset));
+    let mut contents = String::new();
+
+    if let Some(declaration) = &declaration {
+        contents.push_str(&format!("```proto\n{}\n```\n\n", declaration.definition));
+    }
+    contents.push_str(&format!(
+        "{} `{name}` in `{}`",
+        kind_label(symbol.kind),
+        display_file(&symbol.location.file)
+    ));
+
+    let Some(declaration) = declaration else {
+        return contents;
+    };
+    let field = declaration
+        .field
+        .as_ref()
+        .filter(|_| matches!(symbol.kind, SymbolKind::Field | SymbolKind::Extension));
+    if let (Some(field), Some(file)) = (field, file) {
+        if let Some((wire_type, default)) = field_details(name, symbol, field, file, &root, symbols)
+        {
+            contents.push_str(&format!("\n\nWire type `{wire_type}`"));
+            if let Some(default) = default {
+                contents.push_str(&format!(", default `{default}`"));
+            }
+        }
+    }
+    if let Some(doc) = declaration.doc {
+        contents.push_str(&format!("\n\n---\n\n{}", doc_comment(doc)));
+    }
+
+    contents
+}
+
+/// The parts of a declaration a hover shows.
+struct Declaration<'a> {
+    /// The declaration written compactly, without nested types.
+    definition: String,
+    doc: Option<&'a str>,
+    field: Option<Field<'a>>,
+    /// The first value of an enum, which is its default.
+    first_value: Option<&'a str>,
+}
+
+/// A field, map field or group, or an extension.
+struct Field<'a> {
+    /// `None` if the field has no label.
+    cardinality: Option<&'a FieldCardinality>,
+    shape: Shape<'a>,
+    options: &'a [Node<OptionNode>],
+}
+
+enum Shape<'a> {
+    Type(&'a FieldType),
+    Map,
+    Group,
+}
+
+impl<'a> Declaration<'a> {
+    fn new(definition: String, doc: Option<&'a str>) -> Self {
+        Self {
+            definition,
+            doc,
+            field: None,
+            first_value: None,
+        }
+    }
+}
+
+/// Finds the declaration whose name starts at `offset`.
+fn find(root: &Root, offset: usize) -> Option<Declaration<'_>> {
+    root.nodes.iter().find_map(|node| {
+        let doc = node.leading_comment();
+        match &node.value {
+            RootDeclaration::Package(package) if package.package_name.start.offset == offset => {
+                let definition = format!("package {};", package.package_name.value.join("."));
+                Some(Declaration::new(definition, doc))
+            }
+            RootDeclaration::Message(message) => find_message(message, doc, offset),
+            RootDeclaration::Enum(enum_node) => find_enum(enum_node, doc, offset),
+            RootDeclaration::Service(service) => find_service(service, doc, offset),
+            RootDeclaration::Extension(extension) => find_extension(extension, offset),
+            _ => None,
+        }
+    })
+}
+
+fn find_message<'a>(
+    message: &'a Message,
+    doc: Option<&'a str>,
+    offset: usize,
+) -> Option<Declaration<'a>> {
+    if message.name.start.offset == offset {
+        let definition = block(
+            &format!("message {}", message.name.value),
+            message_lines(&message.elements),
+        );
+        return Some(Declaration::new(definition, doc));
+    }
+
+    find_message_elements(&message.elements, offset)
+}
+
+fn find_message_elements(
+    elements: &[Node<MessageElement>],
+    offset: usize,
+) -> Option<Declaration<'_>> {
+    elements.iter().find_map(|element| {
+        let doc = element.leading_comment();
+        match &element.value {
+            MessageElement::Field(field) if field.name.start.offset == offset => {
+                Some(Declaration {
+                    definition: field_line(
+                        label(&field.cardinality),
+                        &field_type(&field.type_name.value),
+                        &field.name.value,
+                        field.number.value,
+                        &field.options,
+                    ),
+                    doc,
+                    field: Some(Field {
+                        cardinality: label(&field.cardinality),
+                        shape: Shape::Type(&field.type_name.value),
+                        options: &field.options,
+                    }),
+                    first_value: None,
+                })
+            }
+            MessageElement::MapField(field) if field.name.start.offset == offset => {
+                let map_type = format!(
+                    "map<{}, {}>",
+                    field.key_type.value.as_str(),
+                    field_type(&field.value_type.value)
+                );
+                Some(Declaration {
+                    definition: field_line(
+                        None,
+                        &map_type,
+                        &field.name.value,
+                        field.number.value,
+                        &field.options,
+                    ),
+                    doc,
+                    field: Some(Field {
+                        cardinality: None,
+                        shape: Shape::Map,
+                        options: &field.options,
+                    }),
+                    first_value: None,
+                })
+            }
+            MessageElement::Group(group) => {
+                find_group(group, label(&group.cardinality), doc, offset)
+            }
+            MessageElement::OneOf(oneof) if oneof.name.start.offset == offset => {
+                let lines = oneof
+                    .elements
+                    .iter()
+                    .filter_map(|element| oneof_line(&element.value))
+                    .collect();
+                let definition = block(&format!("oneof {}", oneof.name.value), lines);
+                Some(Declaration::new(definition, doc))
+            }
+            MessageElement::OneOf(oneof) => oneof.elements.iter().find_map(|element| {
+                let doc = element.leading_comment();
+                match &element.value {
+                    OneofElement::OneofField(field) if field.name.start.offset == offset => {
+                        let options = field.options.as_deref().unwrap_or_default();
+                        Some(Declaration {
+                            definition: field_line(
+                                None,
+                                &field_type(&field.type_name.value),
+                                &field.name.value,
+                                field.number.value,
+                                options,
+                            ),
+                            doc,
+                            field: Some(Field {
+                                cardinality: None,
+                                shape: Shape::Type(&field.type_name.value),
+                                options,
+                            }),
+                            first_value: None,
+                        })
+                    }
+                    OneofElement::Group(group) => find_group(group, None, doc, offset),
+                    _ => None,
+                }
+            }),
+            MessageElement::Message(message) => find_message(message, doc, offset),
+            MessageElement::Enum(enum_node) => find_enum(enum_node, doc, offset),
+            MessageElement::Extension(extension) => find_extension(extension, offset),
+            _ => None,
+        }
+    })
+}
+
+/// A group declares a field and a message, both named at the group's name.
+fn find_group<'a>(
+    group: &'a GroupDeclaration,
+    cardinality: Option<&'a FieldCardinality>,
+    doc: Option<&'a str>,
+    offset: usize,
+) -> Option<Declaration<'a>> {
+    if group.name.start.offset != offset {
+        return find_message_elements(&group.elements, offset);
+    }
+
+    Some(Declaration {
+        definition: block(
+            &group_header(group, cardinality),
+            message_lines(&group.elements),
+        ),
+        doc,
+        field: Some(Field {
+            cardinality,
+            shape: Shape::Group,
+            options: &group.options,
+        }),
+        first_value: None,
+    })
+}
+
+fn find_enum<'a>(
+    enum_node: &'a EnumNode,
+    doc: Option<&'a str>,
+    offset: usize,
+) -> Option<Declaration<'a>> {
+    if enum_node.name.start.offset == offset {
+        let lines = enum_node
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                EnumElement::EnumValue {
+                    name,
+                    number,
+                    options,
+                } => Some(enum_value_line(&name.value, number.value, options)),
+                _ => None,
+            })
+            .collect();
+        let definition = block(&format!("enum {}", enum_node.name.value), lines);
+        let first_value = enum_node
+            .elements
+            .iter()
+            .find_map(|element| match &element.value {
+                EnumElement::EnumValue { name, .. } => Some(name.value.as_str()),
+                _ => None,
+            });
+        return Some(Declaration {
+            first_value,
+            ..Declaration::new(definition, doc)
+        });
+    }
+
+    enum_node
+        .elements
+        .iter()
+        .find_map(|element| match &element.value {
+            EnumElement::EnumValue {
+                name,
+                number,
+                options,
+            } if name.start.offset == offset => Some(Declaration::new(
+                enum_value_line(&name.value, number.value, options),
+                element.leading_comment(),
+            )),
+            _ => None,
+        })
+}
+
+fn find_service<'a>(
+    service: &'a ServiceNode,
+    doc: Option<&'a str>,
+    offset: usize,
+) -> Option<Declaration<'a>> {
+    let methods = service
+        .elements
+        .iter()
+        .filter_map(|element| match &element.value {
+            ServiceElement::Method(method) => Some((method, element.leading_comment())),
+            _ => None,
+        });
+
+    if service.name.start.offset == offset {
+        let lines = methods.map(|(method, _)| method_line(method)).collect();
+        let definition = block(&format!("service {}", service.name.value), lines);
+        return Some(Declaration::new(definition, doc));
+    }
+
+    methods
+        .filter(|(method, _)| method.name.start.offset == offset)
+        .map(|(method, doc)| Declaration::new(method_line(method), doc))
+        .next()
+}
+
+fn find_extension(extension: &ExtensionNode, offset: usize) -> Option<Declaration<'_>> {
+    extension.elements.iter().find_map(|element| {
+        let doc = element.leading_comment();
+        match &element.value {
+            ExtensionElement::Field(field) if field.name.start.offset == offset => {
+                let definition = field_line(
+                    label(&field.cardinality),
+                    &field_type(&field.type_name.value),
+                    &field.name.value,
+                    field.number.value,
+                    &field.options,
+                );
+                Some(Declaration {
+                    definition: block(
+                        &format!("extend {}", extension.extendee.value),
+                        vec![definition],
+                    ),
+                    doc,
+                    field: Some(Field {
+                        cardinality: label(&field.cardinality),
+                        shape: Shape::Type(&field.type_name.value),
+                        options: &field.options,
+                    }),
+                    first_value: None,
+                })
+            }
+            ExtensionElement::Group(group) => {
+                find_group(group, label(&group.cardinality), doc, offset)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// The fields of a message, with the fields of its oneofs and groups.
+fn message_lines(elements: &[Node<MessageElement>]) -> Vec<String> {
+    elements
+        .iter()
+        .filter_map(|element| match &element.value {
+            MessageElement::Field(field) => Some(field_line(
+                label(&field.cardinality),
+                &field_type(&field.type_name.value),
+                &field.name.value,
+                field.number.value,
+                &field.options,
+            )),
+            MessageElement::MapField(field) => Some(field_line(
+                None,
+                &format!(
+                    "map<{}, {}>",
+                    field.key_type.value.as_str(),
+                    field_type(&field.value_type.value)
+                ),
+                &field.name.value,
+                field.number.value,
+                &field.options,
+            )),
+            MessageElement::Group(group) => Some(block(
+                &group_header(group, label(&group.cardinality)),
+                message_lines(&group.elements),
+            )),
+            MessageElement::OneOf(oneof) => Some(block(
+                &format!("oneof {}", oneof.name.value),
+                oneof
+                    .elements
+                    .iter()
+                    .filter_map(|element| oneof_line(&element.value))
+                    .collect(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn oneof_line(element: &OneofElement) -> Option<String> {
+    match element {
+        OneofElement::OneofField(field) => Some(field_line(
+            None,
+            &field_type(&field.type_name.value),
+            &field.name.value,
+            field.number.value,
+            field.options.as_deref().unwrap_or_default(),
+        )),
+        OneofElement::Group(group) => Some(block(
+            &group_header(group, None),
+            message_lines(&group.elements),
+        )),
+        OneofElement::Option(_) => None,
+    }
+}
+
+/// `header { ... }`, with each line indented.
+fn block(header: &str, lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        return format!("{header} {{}}");
+    }
+
+    let mut block = format!("{header} {{\n");
+    for line in lines.iter().flat_map(|line| line.lines()) {
+        block.push_str(&format!("  {line}\n"));
+    }
+    block.push('}');
+    block
+}
+
+/// The label of a field, unless it was left out.
+fn label(cardinality: &Node<FieldCardinality>) -> Option<&FieldCardinality> {
+    (cardinality.start != cardinality.end).then_some(&cardinality.value)
+}
+
+fn label_text(cardinality: Option<&FieldCardinality>) -> &'static str {
+    match cardinality {
+        Some(FieldCardinality::Required) => "required ",
+        Some(FieldCardinality::Optional) => "optional ",
+        Some(FieldCardinality::Repeated) => "repeated ",
+        None => "",
+    }
+}
+
+fn field_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::ScalarType(scalar) => scalar.as_str().to_string(),
+        FieldType::TypeName(name) => name.to_string(),
+    }
+}
+
+fn field_line(
+    cardinality: Option<&FieldCardinality>,
+    field_type: &str,
+    name: &str,
+    number: u32,
+    options: &[Node<OptionNode>],
+) -> String {
+    format!(
+        "{}{field_type} {name} = {number}{};",
+        label_text(cardinality),
+        compact_options(options)
+    )
+}
+
+fn group_header(group: &GroupDeclaration, cardinality: Option<&FieldCardinality>) -> String {
+    format!(
+        "{}group {} = {}{}",
+        label_text(cardinality),
+        group.name.value,
+        group.number.value,
+        compact_options(&group.options)
+    )
+}
+
+fn enum_value_line(name: &str, number: i64, options: &[Node<OptionNode>]) -> String {
+    format!("{name} = {number}{};", compact_options(options))
+}
+
+fn method_line(method: &MethodNode) -> String {
+    let message_type = |message_type: &crate::service::MessageType| {
+        let stream = if message_type.stream { "stream " } else { "" };
+        format!("{stream}{}", message_type.type_name.value)
+    };
+    format!(
+        "rpc {}({}) returns ({});",
+        method.name.value,
+        message_type(&method.input_type.value),
+        message_type(&method.output_type.value)
+    )
+}
+
+fn compact_options(options: &[Node<OptionNode>]) -> String {
+    if options.is_empty() {
+        return String::new();
+    }
+
+    let options: Vec<_> = options
+        .iter()
+        .map(|option| {
+            format!(
+                "{} = {}",
+                option_name(&option.value),
+                option_value(&option.value.value.value)
+            )
+        })
+        .collect();
+    format!(" [{}]", options.join(", "))
+}
+
+fn option_name(option: &OptionNode) -> String {
+    let parts: Vec<_> = option
+        .name
+        .value
+        .iter()
+        .map(|part| match &part.value {
+            OptionNamePart::SimpleName(name) => name.clone(),
+            OptionNamePart::ExtensionName(name) => format!("({name})"),
+        })
+        .collect();
+    parts.join(".")
+}
+
+fn option_value(value: &OptionValue) -> String {
+    match value {
+        OptionValue::StringLiteral(literal) => literal.raw.join(" "),
+        OptionValue::UintLiteral(value)
+        | OptionValue::IntLiteral(value)
+        | OptionValue::FloatLiteral(value)
+        | OptionValue::Identifier(value) => value.clone(),
+        OptionValue::MessageLiteral(_) => "{ … }".to_string(),
+    }
+}
+
+/// The wire type of a field and its default value, or `None` if its type
+/// doesn't resolve.
+fn field_details<'a>(
+    name: &str,
+    symbol: &Symbol,
+    field: &Field,
+    file: &Root,
+    root: &impl Fn(&str) -> Option<&'a Root>,
+    symbols: &SymbolTable,
+) -> Option<(&'static str, Option<String>)> {
+    let field_type = match field.shape {
+        Shape::Map => return Some(("LEN", Some("{}".to_string()))),
+        Shape::Group => return Some(("SGROUP", None)),
+        Shape::Type(field_type) => field_type,
+    };
+
+    let (wire_type, default) = match field_type {
+        FieldType::ScalarType(scalar) => (scalar_wire_type(scalar), Some(scalar_default(scalar))),
+        FieldType::TypeName(type_name) => {
+            let (_, type_symbol) = symbols.resolve_declared(name, symbol, type_name)?;
+            match type_symbol.kind {
+                SymbolKind::Enum => {
+                    let first_value = root(&type_symbol.location.file)
+                        .and_then(|root| find(root, type_symbol.location.start.offset))
+                        .and_then(|declaration| declaration.first_value)
+                        .map(str::to_string);
+                    ("VARINT", first_value)
+                }
+                _ => ("LEN", None),
+            }
+        }
+    };
+
+    if matches!(field.cardinality, Some(FieldCardinality::Repeated)) {
+        let packed = wire_type != "LEN"
+            && resolve_features(file).get(name).is_some_and(|features| {
+                features.repeated_field_encoding == RepeatedFieldEncoding::Packed
+            });
+        let wire_type = if packed { "LEN (packed)" } else { wire_type };
+        return Some((wire_type, Some("[]".to_string())));
+    }
+
+    let explicit = field.options.iter().find_map(|option| {
+        (option_name(&option.value) == "default").then(|| option_value(&option.value.value.value))
+    });
+    Some((wire_type, explicit.or(default)))
+}
+
+fn scalar_wire_type(scalar: &ScalarType) -> &'static str {
+    match scalar {
+        ScalarType::Int32
+        | ScalarType::Int64
+        | ScalarType::Uint32
+        | ScalarType::Uint64
+        | ScalarType::Sint32
+        | ScalarType::Sint64
+        | ScalarType::Bool => "VARINT",
+        ScalarType::Fixed64 | ScalarType::Sfixed64 | ScalarType::Double => "I64",
+        ScalarType::Fixed32 | ScalarType::Sfixed32 | ScalarType::Float => "I32",
+        ScalarType::String | ScalarType::Bytes => "LEN",
+    }
+}
+
+fn scalar_default(scalar: &ScalarType) -> String {
+    match scalar {
+        ScalarType::Bool => "false",
+        ScalarType::String | ScalarType::Bytes => "\"\"",
+        _ => "0",
+    }
+    .to_string()
+}
+
+fn kind_label(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Package => "Package",
+        SymbolKind::Message => "Message",
+        SymbolKind::Enum => "Enum",
+        SymbolKind::Service => "Service",
+        SymbolKind::Field => "Field",
+        SymbolKind::Oneof => "Oneof",
+        SymbolKind::EnumValue => "Enum value",
+        SymbolKind::Method => "Method",
+        SymbolKind::Extension => "Extension",
+    }
+}
+
+/// Bundled files are shown by their import path.
+fn display_file(file: &str) -> &str {
+    file.strip_prefix(well_known_types::BUNDLED_ROOT)
+        .unwrap_or(file)
+}
+
+/// A leading comment without the space after each `//`.
+fn doc_comment(comment: &str) -> String {
+    let lines: Vec<_> = comment
+        .lines()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_util::position_at, Source};
+
+    /// Hovers `target` in the first occurrence of `context` in `file`.
+    fn hover(source: &Source, file: &str, context: &str, target: &str) -> Option<String> {
+        let text = source.text(file).unwrap();
+        let offset = text.find(context).unwrap() + context.find(target).unwrap();
+
+        let hover = source.hover(file, position_at(source, file, offset))?;
+        assert_eq!(&text[hover.start.offset..hover.end.offset], target);
+        Some(hover.contents)
+    }
+
+    fn source() -> Source {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/types.proto",
+            r#"syntax = "proto3";
+package acme.types;
+
+// The state of an order.
+enum State {
+  // Not set.
+  STATE_UNKNOWN = 0;
+  STATE_OPEN = 1;
+}
+"#,
+        );
+        source.parse(
+            "/ws/api.proto",
+            r#"syntax = "proto3";
+package acme.api;
+import "types.proto";
+
+// An order.
+//
+// Orders are never deleted.
+message Order {
+  // The id of the order.
+  uint64 id = 1;
+  acme.types.State state = 2;
+  repeated sint32 counts = 3;
+  map<string, Order> children = 4;
+  oneof contact {
+    string email = 5;
+  }
+}
+
+service Orders {
+  rpc Get(Order) returns (stream Order);
+}
+"#,
+        );
+        source
+    }
+
+    #[test]
+    fn describes_messages_with_their_fields() {
+        let source = source();
+
+        assert_eq!(
+            hover(&source, "/ws/api.proto", "message Order", "Order").unwrap(),
+            "```proto
+message Order {
+  uint64 id = 1;
+  acme.types.State state = 2;
+  repeated sint32 counts = 3;
+  map<string, Order> children = 4;
+  oneof contact {
+    string email = 5;
+  }
+}
+```
+
+Message `acme.api.Order` in `/ws/api.proto`
+
+---
+
+An order.
+
+Orders are never deleted."
+        );
+    }
+
+    #[test]
+    fn describes_fields_with_wire_type_and_default() {
+        let source = source();
+
+        assert_eq!(
+            hover(&source, "/ws/api.proto", "uint64 id", "id").unwrap(),
+            "```proto
+uint64 id = 1;
+```
+
+Field `acme.api.Order.id` in `/ws/api.proto`
+
+Wire type `VARINT`, default `0`
+
+---
+
+The id of the order."
+        );
+        let state = hover(&source, "/ws/api.proto", "State state", "state").unwrap();
+        assert!(
+            state.contains("Wire type `VARINT`, default `STATE_UNKNOWN`"),
+            "{state}"
+        );
+        let counts = hover(&source, "/ws/api.proto", "counts", "counts").unwrap();
+        assert!(
+            counts.contains("Wire type `LEN (packed)`, default `[]`"),
+            "{counts}"
+        );
+        let email = hover(&source, "/ws/api.proto", "email", "email").unwrap();
+        assert!(email.contains("Wire type `LEN`, default `\"\"`"), "{email}");
+    }
+
+    #[test]
+    fn describes_references_in_other_files() {
+        let source = source();
+
+        assert_eq!(
+            hover(
+                &source,
+                "/ws/api.proto",
+                "acme.types.State",
+                "acme.types.State"
+            )
+            .unwrap(),
+            "```proto
+enum State {
+  STATE_UNKNOWN = 0;
+  STATE_OPEN = 1;
+}
+```
+
+Enum `acme.types.State` in `/ws/types.proto`
+
+---
+
+The state of an order."
+        );
+        assert_eq!(
+            hover(&source, "/ws/types.proto", "STATE_UNKNOWN", "STATE_UNKNOWN").unwrap(),
+            "```proto
+STATE_UNKNOWN = 0;
+```
+
+Enum value `acme.types.STATE_UNKNOWN` in `/ws/types.proto`
+
+---
+
+Not set."
+        );
+        let method = hover(&source, "/ws/api.proto", "Get", "Get").unwrap();
+        assert!(
+            method.starts_with("```proto\nrpc Get(Order) returns (stream Order);\n```"),
+            "{method}"
+        );
+    }
+}
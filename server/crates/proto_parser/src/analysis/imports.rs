@@ -0,0 +1,152 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{ImportModifier, Position, Root, RootDeclaration};
+
+use super::well_known_types;
+
+/// An import statement and the file it resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// The imported path, as written.
+    pub path: String,
+    pub modifier: Option<ImportModifier>,
+    /// The imported file, or `None` if it wasn't found.
+    pub file: Option<String>,
+    /// The span of the quoted path.
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Import {
+    pub fn is_public(&self) -> bool {
+        self.modifier == Some(ImportModifier::Public)
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.modifier == Some(ImportModifier::Weak)
+    }
+}
+
+/// Finds imported files the way protoc does with `-I`: an import path is
+/// relative to one of the include paths, which are searched in order.
+#[derive(Debug, Clone, Default)]
+pub struct ImportResolver {
+    include_paths: Vec<PathBuf>,
+}
+
+impl ImportResolver {
+    pub fn new(include_paths: Vec<PathBuf>) -> Self {
+        Self { include_paths }
+    }
+
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+
+    /// Returns the file `path` refers to. Files in `loaded` don't have to
+    /// exist on disk, e.g. because they are open in the editor but haven't
+    /// been saved yet.
+    ///
+    /// The well-known types are searched after the include paths, so a
+    /// project can still bring its own copies. When no include path has the
+    /// file, a loaded file whose path ends with `path` is used, so imports
+    /// keep working before include paths are configured.
+    pub fn resolve(&self, path: &str, loaded: &HashSet<&str>) -> Option<String> {
+        if Path::new(path).is_absolute() {
+            return None;
+        }
+
+        self.include_paths
+            .iter()
+            .filter_map(|include_path| include_path.join(path).to_str().map(str::to_string))
+            .find(|file| loaded.contains(file.as_str()) || Path::new(file).is_file())
+            .or_else(|| well_known_types::resolve(path))
+            .or_else(|| {
+                loaded
+                    .iter()
+                    .filter(|file| {
+                        file.strip_suffix(path)
+                            .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+                    })
+                    .min()
+                    .map(|file| file.to_string())
+            })
+    }
+
+    /// Resolves every import statement in `root`.
+    pub fn imports(&self, root: &Root, loaded: &HashSet<&str>) -> Vec<Import> {
+        root.nodes
+            .iter()
+            .filter_map(|node| match &node.value {
+                RootDeclaration::Import(import) => Some(Import {
+                    path: import.package_name.value.clone(),
+                    modifier: import.modifier,
+                    file: self.resolve(&import.package_name.value, loaded),
+                    start: import.package_name.start,
+                    end: import.package_name.end,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs};
+
+    use crate::{tokenize, Parser};
+
+    use super::ImportResolver;
+
+    #[test]
+    fn searches_include_paths_in_order() {
+        let dir = std::env::temp_dir().join(format!("protols-imports-{}", std::process::id()));
+        let first = dir.join("first");
+        let second = dir.join("second");
+        fs::create_dir_all(first.join("a")).unwrap();
+        fs::create_dir_all(second.join("a")).unwrap();
+        fs::write(second.join("a/b.proto"), "").unwrap();
+        fs::write(second.join("c.proto"), "").unwrap();
+
+        let resolver = ImportResolver::new(vec![first.clone(), second.clone()]);
+        let path = |path: &std::path::Path| path.to_str().unwrap().to_string();
+        let none = HashSet::new();
+
+        assert_eq!(
+            resolver.resolve("a/b.proto", &none),
+            Some(path(&second.join("a/b.proto")))
+        );
+        // Files open in the editor don't need to exist on disk.
+        let unsaved = path(&first.join("c.proto"));
+        assert_eq!(
+            resolver.resolve("c.proto", &HashSet::from([unsaved.as_str()])),
+            Some(unsaved.clone())
+        );
+        assert_eq!(resolver.resolve("missing.proto", &none), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_loaded_files() {
+        let input = r#"import public "dep/b.proto"; import weak "missing.proto";"#;
+        let root = Parser::new(tokenize(input)).parse("/ws/a.proto").root;
+        let loaded = HashSet::from(["/ws/a.proto", "/ws/dep/b.proto", "/ws/xdep/b.proto"]);
+
+        let imports = ImportResolver::default().imports(&root, &loaded);
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].file.as_deref(), Some("/ws/dep/b.proto"));
+        assert!(imports[0].is_public());
+        assert_eq!(imports[1].file, None);
+        assert!(imports[1].is_weak());
+        assert_eq!(
+            &input[imports[1].start.offset..imports[1].end.offset],
+            r#""missing.proto""#
+        );
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    message::{FieldType, GroupDeclaration, Message, MessageElement, OneofElement},
+    option::{
+        MessageLiteral, MessageLiteralFieldName, MessageLiteralValue, OptionNamePart, OptionNode,
+        OptionValue,
+    },
+    service::{MethodElement, ServiceElement, ServiceNode},
+    symbols::{ResolveError, Symbol, SymbolKind, SymbolTable},
+    EnumElement, EnumNode, ExtensionElement, ExtensionNode, Node, Position, Root, RootDeclaration,
+    TypeName,
+};
+
+use super::{package_name, qualify};
+
+const FILE_OPTIONS: &str = "google.protobuf.FileOptions";
+const MESSAGE_OPTIONS: &str = "google.protobuf.MessageOptions";
+const FIELD_OPTIONS: &str = "google.protobuf.FieldOptions";
+const ONEOF_OPTIONS: &str = "google.protobuf.OneofOptions";
+const EXTENSION_RANGE_OPTIONS: &str = "google.protobuf.ExtensionRangeOptions";
+const ENUM_OPTIONS: &str = "google.protobuf.EnumOptions";
+const ENUM_VALUE_OPTIONS: &str = "google.protobuf.EnumValueOptions";
+const SERVICE_OPTIONS: &str = "google.protobuf.ServiceOptions";
+const METHOD_OPTIONS: &str = "google.protobuf.MethodOptions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// The type of a field or map value, an rpc input or output, or the
+    /// message an `extend` block extends.
+    Type,
+    /// The extension in a custom option name, `(my.option)`.
+    Extension,
+}
+
+/// A name referring to a declaration, with the scope it is resolved in.
+#[derive(Debug, Clone)]
+pub struct Reference<'a> {
+    pub kind: ReferenceKind,
+    pub name: &'a TypeName,
+    pub scope: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Reference<'_> {
+    /// Whether `position` is inside the name or right after it.
+    pub fn contains(&self, position: Position) -> bool {
+        self.start.offset <= position.offset && position.offset <= self.end.offset
+    }
+}
+
+/// An option set on a declaration.
+#[derive(Debug, Clone)]
+pub struct OptionUsage<'a> {
+    /// The message of `descriptor.proto` the option is set in, e.g.
+    /// `google.protobuf.FieldOptions`.
+    pub target: &'static str,
+    pub scope: String,
+    pub option: &'a OptionNode,
+    /// The type of the field the option is set on, which is the type of its
+    /// `default`.
+    pub field_type: Option<&'a FieldType>,
+}
+
+/// A name resolved to the symbol it refers to.
+#[derive(Debug, Clone)]
+pub struct ResolvedReference<'a> {
+    pub name: String,
+    pub symbol: &'a Symbol,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl ResolvedReference<'_> {
+    /// Whether `position` is inside the name or right after it.
+    pub fn contains(&self, position: Position) -> bool {
+        self.start.offset <= position.offset && position.offset <= self.end.offset
+    }
+}
+
+/// Returns every type and extension name used in `root`, in source order.
+pub fn references(root: &Root) -> Vec<Reference<'_>> {
+    collect(root).references
+}
+
+/// Returns every option set in `root`, in source order.
+pub fn options(root: &Root) -> Vec<OptionUsage<'_>> {
+    collect(root).options
+}
+
+/// Resolves every name in `root` that refers to a declaration: the
+/// `references`, and in options the fields of option names (`(ext).field`)
+/// and of aggregate values, and enum values. Names that don't resolve are
+/// left out.
+pub fn resolved_references<'a>(
+    root: &Root,
+    symbols: &'a SymbolTable,
+) -> Vec<ResolvedReference<'a>> {
+    let collector = collect(root);
+    let mut resolver = Resolver {
+        symbols,
+        visible: symbols.visible_files(&root.file_name),
+        declared_visible: HashMap::new(),
+        resolved: Vec::new(),
+    };
+
+    for reference in &collector.references {
+        let types_only = reference.kind == ReferenceKind::Type;
+        if let Ok((name, symbol)) = resolver.resolve(&reference.scope, reference.name, types_only) {
+            resolver.push(name, symbol, reference.start, reference.end);
+        }
+    }
+    for usage in &collector.options {
+        resolver.option(usage);
+    }
+
+    resolver
+        .resolved
+        .sort_by_key(|reference| reference.start.offset);
+    resolver.resolved
+}
+
+fn collect(root: &Root) -> Collector<'_> {
+    let package = package_name(root);
+    let mut collector = Collector {
+        references: Vec::new(),
+        options: Vec::new(),
+    };
+
+    for node in &root.nodes {
+        match &node.value {
+            RootDeclaration::Message(message) => collector.message(&package, message),
+            RootDeclaration::Enum(enum_node) => collector.enum_node(&package, enum_node),
+            RootDeclaration::Service(service) => collector.service(&package, service),
+            RootDeclaration::Extension(extension) => collector.extension(&package, extension),
+            RootDeclaration::Option(option) => collector.option(FILE_OPTIONS, &package, option),
+            _ => {}
+        }
+    }
+
+    collector
+}
+
+struct Collector<'a> {
+    references: Vec<Reference<'a>>,
+    options: Vec<OptionUsage<'a>>,
+}
+
+impl<'a> Collector<'a> {
+    fn push(
+        &mut self,
+        kind: ReferenceKind,
+        scope: &str,
+        name: &'a TypeName,
+        start: Position,
+        end: Position,
+    ) {
+        self.references.push(Reference {
+            kind,
+            name,
+            scope: scope.to_string(),
+            start,
+            end,
+        });
+    }
+
+    fn field_type(&mut self, scope: &str, field_type: &'a Node<FieldType>) {
+        if let FieldType::TypeName(name) = &field_type.value {
+            self.push(
+                ReferenceKind::Type,
+                scope,
+                name,
+                field_type.start,
+                field_type.end,
+            );
+        }
+    }
+
+    fn option(&mut self, target: &'static str, scope: &str, option: &'a OptionNode) {
+        self.field_option(target, scope, None, option);
+    }
+
+    fn field_option(
+        &mut self,
+        target: &'static str,
+        scope: &str,
+        field_type: Option<&'a FieldType>,
+        option: &'a OptionNode,
+    ) {
+        for part in &option.name.value {
+            if let OptionNamePart::ExtensionName(name) = &part.value {
+                self.push(ReferenceKind::Extension, scope, name, part.start, part.end);
+            }
+        }
+        if let OptionValue::MessageLiteral(literal) = &option.value.value {
+            self.message_literal(scope, literal);
+        }
+
+        self.options.push(OptionUsage {
+            target,
+            scope: scope.to_string(),
+            option,
+            field_type,
+        });
+    }
+
+    /// The extensions set in an aggregate value, `[pkg.ext]: ...`.
+    fn message_literal(&mut self, scope: &str, literal: &'a MessageLiteral) {
+        for field in &literal.fields {
+            let field = &field.value;
+            if let MessageLiteralFieldName::Extension(name) = &field.name.value {
+                self.push(
+                    ReferenceKind::Extension,
+                    scope,
+                    name,
+                    field.name.start,
+                    field.name.end,
+                );
+            }
+            self.message_literal_value(scope, &field.value.value);
+        }
+    }
+
+    fn message_literal_value(&mut self, scope: &str, value: &'a MessageLiteralValue) {
+        match value {
+            MessageLiteralValue::Message(literal) => self.message_literal(scope, literal),
+            MessageLiteralValue::List(values) => {
+                for value in values {
+                    self.message_literal_value(scope, &value.value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn options(&mut self, target: &'static str, scope: &str, options: &'a [Node<OptionNode>]) {
+        for option in options {
+            self.option(target, scope, &option.value);
+        }
+    }
+
+    fn field_options(
+        &mut self,
+        scope: &str,
+        field_type: &'a FieldType,
+        options: &'a [Node<OptionNode>],
+    ) {
+        for option in options {
+            self.field_option(FIELD_OPTIONS, scope, Some(field_type), &option.value);
+        }
+    }
+
+    fn message(&mut self, scope: &str, message: &'a Message) {
+        let name = qualify(scope, &message.name.value);
+        self.message_elements(&name, &message.elements);
+    }
+
+    fn message_elements(&mut self, scope: &str, elements: &'a [Node<MessageElement>]) {
+        for element in elements {
+            match &element.value {
+                MessageElement::Field(field) => {
+                    self.field_type(scope, &field.type_name);
+                    self.field_options(scope, &field.type_name.value, &field.options);
+                }
+                MessageElement::MapField(field) => {
+                    self.field_type(scope, &field.value_type);
+                    self.options(FIELD_OPTIONS, scope, &field.options);
+                }
+                MessageElement::Group(group) => self.group(scope, group),
+                MessageElement::OneOf(oneof) => {
+                    for element in &oneof.elements {
+                        match &element.value {
+                            OneofElement::Option(option) => {
+                                self.option(ONEOF_OPTIONS, scope, option)
+                            }
+                            OneofElement::OneofField(field) => {
+                                self.field_type(scope, &field.type_name);
+                                self.field_options(
+                                    scope,
+                                    &field.type_name.value,
+                                    field.options.as_deref().unwrap_or_default(),
+                                );
+                            }
+                            OneofElement::Group(group) => self.group(scope, group),
+                        }
+                    }
+                }
+                MessageElement::Option(option) => self.option(MESSAGE_OPTIONS, scope, option),
+                MessageElement::Message(message) => self.message(scope, message),
+                MessageElement::Enum(enum_node) => self.enum_node(scope, enum_node),
+                MessageElement::Extension(extension) => self.extension(scope, extension),
+                MessageElement::ExtensionRange(range) => {
+                    self.options(EXTENSION_RANGE_OPTIONS, scope, &range.options)
+                }
+                MessageElement::Reserved(_) | MessageElement::Empty => {}
+            }
+        }
+    }
+
+    fn group(&mut self, scope: &str, group: &'a GroupDeclaration) {
+        self.options(FIELD_OPTIONS, scope, &group.options);
+        self.message_elements(&qualify(scope, &group.name.value), &group.elements);
+    }
+
+    fn enum_node(&mut self, scope: &str, enum_node: &'a EnumNode) {
+        let name = qualify(scope, &enum_node.name.value);
+        for element in &enum_node.elements {
+            match &element.value {
+                EnumElement::EnumValue { options, .. } => {
+                    self.options(ENUM_VALUE_OPTIONS, &name, options)
+                }
+                EnumElement::EnumOption(option) => self.option(ENUM_OPTIONS, &name, option),
+                _ => {}
+            }
+        }
+    }
+
+    fn service(&mut self, scope: &str, service: &'a ServiceNode) {
+        let name = qualify(scope, &service.name.value);
+        for element in &service.elements {
+            match &element.value {
+                ServiceElement::Option(option) => self.option(SERVICE_OPTIONS, &name, option),
+                ServiceElement::Method(method) => {
+                    for message_type in [&method.input_type, &method.output_type] {
+                        let type_name = &message_type.value.type_name;
+                        self.push(
+                            ReferenceKind::Type,
+                            &name,
+                            &type_name.value,
+                            type_name.start,
+                            type_name.end,
+                        );
+                    }
+                    for element in &method.elements {
+                        if let MethodElement::Option(option) = &element.value {
+                            self.option(METHOD_OPTIONS, &name, option);
+                        }
+                    }
+                }
+                ServiceElement::Empty => {}
+            }
+        }
+    }
+
+    /// The extendee and the extension fields' types are resolved in the
+    /// scope of the `extend` block.
+    fn extension(&mut self, scope: &str, extension: &'a ExtensionNode) {
+        let extendee = &extension.extendee;
+        self.push(
+            ReferenceKind::Type,
+            scope,
+            &extendee.value,
+            extendee.start,
+            extendee.end,
+        );
+
+        for element in &extension.elements {
+            match &element.value {
+                ExtensionElement::Field(field) => {
+                    self.field_type(scope, &field.type_name);
+                    self.field_options(scope, &field.type_name.value, &field.options);
+                }
+                ExtensionElement::Group(group) => self.group(scope, group),
+            }
+        }
+    }
+}
+
+/// Resolves the names in options, which depend on the types of the fields
+/// the option names and aggregate values go through.
+struct Resolver<'a, 's> {
+    symbols: &'a SymbolTable,
+    /// The files visible from the file being resolved.
+    visible: HashSet<&'s str>,
+    /// The files visible from the files declaring the fields options go
+    /// through, whose types are resolved there.
+    declared_visible: HashMap<&'s str, HashSet<&'s str>>,
+    resolved: Vec<ResolvedReference<'a>>,
+}
+
+/// The type of an option or a field in an aggregate value, as far as its
+/// names go.
+enum ValueType {
+    Message(String),
+    Enum(String),
+    Other,
+}
+
+impl<'a: 's, 's> Resolver<'a, 's> {
+    fn resolve(
+        &self,
+        scope: &str,
+        name: &TypeName,
+        types_only: bool,
+    ) -> Result<(String, &'a Symbol), ResolveError> {
+        self.symbols
+            .resolve_visible(&self.visible, scope, name, types_only)
+    }
+
+    fn push(&mut self, name: String, symbol: &'a Symbol, start: Position, end: Position) {
+        self.resolved.push(ResolvedReference {
+            name,
+            symbol,
+            start,
+            end,
+        });
+    }
+
+    /// Follows the option name from the options message through each field,
+    /// then resolves the names in the value.
+    fn option(&mut self, usage: &OptionUsage) {
+        let parts = &usage.option.name.value;
+        let mut value_type = ValueType::Message(usage.target.to_string());
+
+        for part in parts {
+            value_type = match (&part.value, value_type) {
+                (OptionNamePart::SimpleName(name), _) if name == "default" && parts.len() == 1 => {
+                    match usage.field_type {
+                        Some(FieldType::TypeName(type_name)) => self
+                            .resolve(&usage.scope, type_name, true)
+                            .map_or(ValueType::Other, |(name, symbol)| symbol_type(name, symbol)),
+                        _ => ValueType::Other,
+                    }
+                }
+                (OptionNamePart::SimpleName(name), ValueType::Message(message)) => {
+                    match self.field(&message, name) {
+                        Some((name, symbol)) => {
+                            self.push(name.clone(), symbol, part.start, part.end);
+                            self.declared_type(&name, symbol)
+                        }
+                        None => return,
+                    }
+                }
+                // Already a reference of its own.
+                (OptionNamePart::ExtensionName(type_name), _) => {
+                    match self.resolve(&usage.scope, type_name, false) {
+                        Ok((name, symbol)) => self.declared_type(&name, symbol),
+                        Err(_) => return,
+                    }
+                }
+                _ => return,
+            };
+        }
+
+        let value = &usage.option.value;
+        match (&value.value, value_type) {
+            (OptionValue::Identifier(identifier), ValueType::Enum(enum_name)) => {
+                self.enum_value(&enum_name, identifier, value.start, value.end);
+            }
+            (OptionValue::MessageLiteral(literal), ValueType::Message(message)) => {
+                self.message_literal(&usage.scope, &message, literal);
+            }
+            _ => {}
+        }
+    }
+
+    fn message_literal(&mut self, scope: &str, message: &str, literal: &MessageLiteral) {
+        for field in &literal.fields {
+            let field = &field.value;
+            let name = &field.name;
+            let value_type = match &name.value {
+                MessageLiteralFieldName::Simple(field_name) => {
+                    match self.field(message, field_name) {
+                        Some((field_name, symbol)) => {
+                            self.push(field_name.clone(), symbol, name.start, name.end);
+                            self.declared_type(&field_name, symbol)
+                        }
+                        None => continue,
+                    }
+                }
+                // Collected as a reference of its own.
+                MessageLiteralFieldName::Extension(type_name) => {
+                    match self.resolve(scope, type_name, false) {
+                        Ok((name, symbol)) => self.declared_type(&name, symbol),
+                        Err(_) => continue,
+                    }
+                }
+                MessageLiteralFieldName::AnyTypeUrl { type_name, .. } => {
+                    match self.resolve(scope, type_name, true) {
+                        Ok((resolved, symbol)) => {
+                            self.push(resolved.clone(), symbol, name.start, name.end);
+                            symbol_type(resolved, symbol)
+                        }
+                        Err(_) => continue,
+                    }
+                }
+            };
+            self.message_literal_value(scope, &value_type, &field.value);
+        }
+    }
+
+    fn message_literal_value(
+        &mut self,
+        scope: &str,
+        value_type: &ValueType,
+        value: &Node<MessageLiteralValue>,
+    ) {
+        match (&value.value, value_type) {
+            (MessageLiteralValue::Identifier(identifier), ValueType::Enum(enum_name)) => {
+                self.enum_value(enum_name, identifier, value.start, value.end);
+            }
+            (MessageLiteralValue::Message(literal), ValueType::Message(message)) => {
+                self.message_literal(scope, message, literal);
+            }
+            (MessageLiteralValue::List(values), _) => {
+                for value in values {
+                    self.message_literal_value(scope, value_type, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The field `name` of `message`.
+    fn field(&self, message: &str, name: &str) -> Option<(String, &'a Symbol)> {
+        let field = qualify(message, name);
+        let symbol = self
+            .symbols
+            .get(&field)
+            .iter()
+            .find(|symbol| symbol.kind == SymbolKind::Field)?;
+        Some((field, symbol))
+    }
+
+    /// The type of the field or extension `name`, resolved in the file and
+    /// scope it is declared in.
+    fn declared_type(&mut self, name: &str, symbol: &'a Symbol) -> ValueType {
+        let Some(FieldType::TypeName(type_name)) = &symbol.value_type else {
+            return ValueType::Other;
+        };
+        let symbols = self.symbols;
+        let file = symbol.location.file.as_str();
+        let visible = self
+            .declared_visible
+            .entry(file)
+            .or_insert_with(|| symbols.visible_files(file));
+        let scope = name.rsplit_once('.').map_or("", |(scope, _)| scope);
+        symbols
+            .resolve_visible(visible, scope, type_name, true)
+            .map_or(ValueType::Other, |(name, symbol)| symbol_type(name, symbol))
+    }
+
+    /// Enum values are siblings of their enum.
+    fn enum_value(&mut self, enum_name: &str, value: &str, start: Position, end: Position) {
+        let scope = enum_name.rsplit_once('.').map_or("", |(scope, _)| scope);
+        let name = qualify(scope, value);
+        let enum_type = FieldType::TypeName(TypeName::from(format!(".{enum_name}")));
+        let symbol = self.symbols.get(&name).iter().find(|symbol| {
+            symbol.kind == SymbolKind::EnumValue && symbol.value_type.as_ref() == Some(&enum_type)
+        });
+        if let Some(symbol) = symbol {
+            self.push(name, symbol, start, end);
+        }
+    }
+}
+
+fn symbol_type(name: String, symbol: &Symbol) -> ValueType {
+    match symbol.kind {
+        SymbolKind::Message => ValueType::Message(name),
+        SymbolKind::Enum => ValueType::Enum(name),
+        _ => ValueType::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenize, Parser, Source};
+
+    use super::{references, resolved_references, ReferenceKind};
+
+    /// Returns the text and resolved name of each reference in `file` after
+    /// the first occurrence of `after`.
+    fn resolved(source: &Source, file: &str, after: &str) -> Vec<(String, String)> {
+        let text = source.text(file).unwrap();
+        let start = text.find(after).unwrap();
+        let root = &source.parse_result(file).unwrap().root;
+
+        resolved_references(root, source.symbols())
+            .into_iter()
+            .filter(|reference| reference.start.offset >= start)
+            .map(|reference| {
+                let name = &text[reference.start.offset..reference.end.offset];
+                (name.to_string(), reference.name)
+            })
+            .collect()
+    }
+
+    fn pairs<const N: usize>(expected: [(&str, &str); N]) -> Vec<(String, String)> {
+        expected
+            .map(|(text, name)| (text.to_string(), name.to_string()))
+            .to_vec()
+    }
+
+    #[test]
+    fn collects_references_with_their_scope() {
+        let input = r#"
+            package p;
+            option (file_opt) = true;
+            message A {
+                B b = 1 [(field_opt) = 1];
+                map<string, .p.C> c = 2;
+                oneof o { D d = 3; }
+                extend E { F f = 100; }
+            }
+            service S {
+                rpc Call(stream G) returns (H) { option (method_opt) = { [p.ext]: 1 }; }
+            }
+        "#;
+        let result = Parser::new(tokenize(input)).parse("a.proto");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let found: Vec<_> = references(&result.root)
+            .into_iter()
+            .map(|reference| {
+                let name = reference.name.parts.join(".");
+                let text = &input[reference.start.offset..reference.end.offset];
+                (reference.kind, reference.scope, name, text.to_string())
+            })
+            .collect();
+
+        let expected = [
+            (ReferenceKind::Extension, "p", "file_opt", "(file_opt)"),
+            (ReferenceKind::Type, "p.A", "B", "B"),
+            (ReferenceKind::Extension, "p.A", "field_opt", "(field_opt)"),
+            (ReferenceKind::Type, "p.A", "p.C", ".p.C"),
+            (ReferenceKind::Type, "p.A", "D", "D"),
+            (ReferenceKind::Type, "p.A", "E", "E"),
+            (ReferenceKind::Type, "p.A", "F", "F"),
+            (ReferenceKind::Type, "p.S", "G", "G"),
+            (ReferenceKind::Type, "p.S", "H", "H"),
+            (
+                ReferenceKind::Extension,
+                "p.S",
+                "method_opt",
+                "(method_opt)",
+            ),
+            (ReferenceKind::Extension, "p.S", "p.ext", "[p.ext]"),
+        ]
+        .map(|(kind, scope, name, text)| {
+            (kind, scope.to_string(), name.to_string(), text.to_string())
+        });
+
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn resolves_types_in_maps_rpcs_and_extends() {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/a.proto",
+            r#"syntax = "proto2";
+package p;
+message A {
+    map<string, B> bs = 1;
+    extensions 100 to 200;
+}
+message B {}
+extend A { optional B b = 100; }
+service S { rpc Call(stream A) returns (B); }
+"#,
+        );
+
+        assert_eq!(
+            resolved(&source, "/ws/a.proto", "message A"),
+            pairs([
+                ("B", "p.B"),
+                ("A", "p.A"),
+                ("B", "p.B"),
+                ("A", "p.A"),
+                ("B", "p.B"),
+            ])
+        );
+    }
+
+    #[test]
+    fn resolves_option_names_values_and_enum_values() {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/a.proto",
+            r#"syntax = "proto2";
+package p;
+import "google/protobuf/descriptor.proto";
+enum Level { LOW = 0; HIGH = 1; }
+message Rule {
+    optional int32 min = 1;
+    optional Level level = 2;
+    repeated Rule nested = 3;
+}
+extend google.protobuf.FieldOptions {
+    optional Rule rule = 50000;
+    optional Level level = 50001;
+}
+message M {
+    optional int32 a = 1 [(rule).min = 1, (p.level) = HIGH, deprecated = true];
+    optional int32 b = 2 [(rule) = { level: LOW nested { min: 2 } nested: [{ min: 3 }] }];
+    optional Level c = 3 [default = HIGH];
+}
+"#,
+        );
+
+        assert_eq!(
+            resolved(&source, "/ws/a.proto", "message M"),
+            pairs([
+                ("(rule)", "p.rule"),
+                ("min", "p.Rule.min"),
+                ("(p.level)", "p.level"),
+                ("HIGH", "p.HIGH"),
+                ("deprecated", "google.protobuf.FieldOptions.deprecated"),
+                ("(rule)", "p.rule"),
+                ("level", "p.Rule.level"),
+                ("LOW", "p.LOW"),
+                ("nested", "p.Rule.nested"),
+                ("min", "p.Rule.min"),
+                ("nested", "p.Rule.nested"),
+                ("min", "p.Rule.min"),
+                ("Level", "p.Level"),
+                ("HIGH", "p.HIGH"),
+            ])
+        );
+    }
+}
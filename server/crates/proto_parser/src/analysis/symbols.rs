@@ -0,0 +1,760 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    message::{FieldType, GroupDeclaration, Message, MessageElement, OneofElement},
+    service::{ServiceElement, ServiceNode},
+    EnumElement, EnumNode, ExtensionElement, ExtensionNode, Node, Position, Root, RootDeclaration,
+    TypeName,
+};
+
+use super::{
+    imports::Import,
+    package_name, qualify,
+    references::{references, ReferenceKind},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Package,
+    Message,
+    Enum,
+    Service,
+    Field,
+    Oneof,
+    /// Enum values are siblings of their enum, as in C++.
+    EnumValue,
+    Method,
+    Extension,
+}
+
+impl SymbolKind {
+    /// Types can be used as field, rpc and extendee types.
+    pub fn is_type(self) -> bool {
+        matches!(self, SymbolKind::Message | SymbolKind::Enum)
+    }
+
+    /// Aggregates can contain other symbols, so a compound name may continue
+    /// inside them.
+    pub fn is_aggregate(self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Package | SymbolKind::Message | SymbolKind::Enum | SymbolKind::Service
+        )
+    }
+}
+
+/// A span in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A declaration, located at its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub location: Location,
+    /// The type of a field or extension, or the enum of an enum value. Type
+    /// names are resolved in the scope the symbol is declared in.
+    pub value_type: Option<FieldType>,
+    /// The message an extension extends, resolved like `value_type`.
+    pub extendee: Option<TypeName>,
+}
+
+/// Why a name couldn't be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// No visible symbol has the name.
+    Unresolved,
+    /// The first part of a compound name matched in an inner scope that
+    /// doesn't contain the rest of it, so the symbol the name was probably
+    /// meant for, `candidate`, in an outer scope is hidden.
+    Ambiguous { resolved: String, candidate: String },
+    /// The name refers to a symbol that isn't a message or an enum.
+    NotAType { resolved: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolErrorKind {
+    UnresolvedType,
+    UnresolvedExtension,
+    AmbiguousReference,
+    NotAType,
+}
+
+impl SymbolErrorKind {
+    /// A stable identifier for the kind of error, used as the diagnostic code.
+    pub fn code(self) -> &'static str {
+        match self {
+            SymbolErrorKind::UnresolvedType => "unresolved-type",
+            SymbolErrorKind::UnresolvedExtension => "unresolved-extension",
+            SymbolErrorKind::AmbiguousReference => "ambiguous-reference",
+            SymbolErrorKind::NotAType => "not-a-type",
+        }
+    }
+}
+
+/// A reference that doesn't resolve to a valid declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolError {
+    pub kind: SymbolErrorKind,
+    pub message: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// The symbols declared in a set of files, keyed by fully qualified name
+/// without the leading dot (`pkg.Message.Nested`).
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Vec<Symbol>>,
+    /// The names each file declares, so its symbols can be replaced when it
+    /// changes.
+    declared: HashMap<String, HashSet<String>>,
+    /// The files each file imports and whether the import is public.
+    imports: HashMap<String, Vec<(String, bool)>>,
+}
+
+impl SymbolTable {
+    /// Replaces the symbols declared in the file of `root`. References are
+    /// resolved when they're looked up, so the files using them don't need
+    /// updating. Returns whether the names, kinds or types declared changed,
+    /// which may change what names in other files resolve to.
+    pub fn insert_file(&mut self, root: &Root) -> bool {
+        let before = self.declarations(&root.file_name);
+        self.remove_symbols(&root.file_name);
+
+        let mut collector = Collector {
+            table: self,
+            file: &root.file_name,
+        };
+        collector.root(root);
+        before != self.declarations(&root.file_name)
+    }
+
+    /// Forgets the symbols and imports of `file`.
+    pub fn remove_file(&mut self, file: &str) {
+        self.remove_symbols(file);
+        self.imports.remove(file);
+    }
+
+    /// Sets the resolved imports of `file`, which decide the symbols visible
+    /// in it. Returns whether they changed.
+    pub fn set_imports(&mut self, file: &str, imports: &[Import]) -> bool {
+        let files: Vec<_> = imports
+            .iter()
+            .filter_map(|import| Some((import.file.clone()?, import.is_public())))
+            .collect();
+        self.imports.insert(file.to_string(), files.clone()) != Some(files)
+    }
+
+    /// The symbols `file` declares without their locations, ordered by name
+    /// and then as declared.
+    fn declarations(&self, file: &str) -> Vec<(String, SymbolKind, Option<FieldType>)> {
+        let mut names: Vec<&String> = self.declared.get(file).into_iter().flatten().collect();
+        names.sort();
+        names
+            .into_iter()
+            .flat_map(|name| {
+                self.get(name)
+                    .iter()
+                    .filter(|symbol| symbol.location.file == file)
+                    .map(|symbol| (name.clone(), symbol.kind, symbol.value_type.clone()))
+            })
+            .collect()
+    }
+
+    fn remove_symbols(&mut self, file: &str) {
+        for name in self.declared.remove(file).unwrap_or_default() {
+            if let Some(symbols) = self.symbols.get_mut(&name) {
+                symbols.retain(|symbol| symbol.location.file != file);
+                if symbols.is_empty() {
+                    self.symbols.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Returns the symbols named `name`, in any file.
+    pub fn get(&self, name: &str) -> &[Symbol] {
+        self.symbols
+            .get(name.strip_prefix('.').unwrap_or(name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns every symbol with its name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Symbol)> {
+        self.symbols
+            .iter()
+            .flat_map(|(name, symbols)| symbols.iter().map(move |symbol| (name.as_str(), symbol)))
+    }
+
+    /// Returns the symbols `file` can use, with their names.
+    pub fn visible<'a>(&'a self, file: &'a str) -> impl Iterator<Item = (&'a str, &'a Symbol)> {
+        let visible = self.visible_files(file);
+        self.iter()
+            .filter(move |(_, symbol)| visible.contains(symbol.location.file.as_str()))
+    }
+
+    /// Resolves a type name from the declaration of the symbol `name`, like
+    /// its `value_type` or `extendee`.
+    pub fn resolve_declared(
+        &self,
+        name: &str,
+        symbol: &Symbol,
+        type_name: &TypeName,
+    ) -> Option<(String, &Symbol)> {
+        let scope = name.rsplit_once('.').map_or("", |(scope, _)| scope);
+        self.resolve(&symbol.location.file, scope, type_name, true)
+            .ok()
+    }
+
+    /// Resolves a reference in `file` the way protoc does: relative names are
+    /// looked up starting in the innermost `scope` and then in each enclosing
+    /// scope. Only the first part of a compound name (`Outer.Inner`) is
+    /// searched for, the rest must be declared inside the symbol it resolves
+    /// to. Symbols from files that aren't imported by `file` aren't visible.
+    ///
+    /// With `types_only`, simple names skip symbols that aren't types, as
+    /// protoc does for field types.
+    pub fn resolve(
+        &self,
+        file: &str,
+        scope: &str,
+        name: &TypeName,
+        types_only: bool,
+    ) -> Result<(String, &Symbol), ResolveError> {
+        self.resolve_visible(&self.visible_files(file), scope, name, types_only)
+    }
+
+    /// Resolves like `resolve`, with the files visible from the file the
+    /// name is in.
+    pub(crate) fn resolve_visible<'a>(
+        &'a self,
+        visible: &HashSet<&str>,
+        scope: &str,
+        name: &TypeName,
+        types_only: bool,
+    ) -> Result<(String, &'a Symbol), ResolveError> {
+        let lookup = |name: &str| {
+            self.get(name)
+                .iter()
+                .find(|symbol| visible.contains(symbol.location.file.as_str()))
+        };
+        let check_type = |resolved: String, symbol: &'a Symbol| {
+            if !types_only || symbol.kind.is_type() {
+                Ok((resolved, symbol))
+            } else {
+                Err(ResolveError::NotAType { resolved })
+            }
+        };
+        let full_name = name.parts.join(".");
+
+        if name.absolute {
+            let symbol = lookup(&full_name).ok_or(ResolveError::Unresolved)?;
+            return check_type(full_name, symbol);
+        }
+
+        let first = name.parts.first().ok_or(ResolveError::Unresolved)?;
+        let mut scope: Vec<&str> = scope.split('.').filter(|part| !part.is_empty()).collect();
+        // The name a compound name resolved to in an inner scope without
+        // being declared there.
+        let mut hidden_by = None;
+
+        loop {
+            let prefix = scope.join(".");
+            let qualified = qualify(&prefix, &full_name);
+
+            if let Some(symbol) = lookup(&qualify(&prefix, first)) {
+                if name.parts.len() == 1 {
+                    if !types_only || symbol.kind.is_type() {
+                        return Ok((qualified, symbol));
+                    }
+                } else if symbol.kind.is_aggregate() {
+                    match (lookup(&qualified), hidden_by.take()) {
+                        (Some(symbol), None) => return check_type(qualified, symbol),
+                        (Some(_), Some(resolved)) => {
+                            return Err(ResolveError::Ambiguous {
+                                resolved,
+                                candidate: qualified,
+                            })
+                        }
+                        (None, hidden) => hidden_by = hidden.or(Some(qualified)),
+                    }
+                }
+            }
+
+            if scope.pop().is_none() {
+                return Err(ResolveError::Unresolved);
+            }
+        }
+    }
+
+    /// Returns an error for each reference in `root` that doesn't resolve.
+    pub fn check(&self, root: &Root) -> Vec<SymbolError> {
+        let visible = self.visible_files(&root.file_name);
+
+        references(root)
+            .into_iter()
+            .filter_map(|reference| {
+                let types_only = reference.kind == ReferenceKind::Type;
+                let error = self
+                    .resolve_visible(&visible, &reference.scope, reference.name, types_only)
+                    .err()?;
+                let name = reference.name;
+
+                let (kind, message) = match error {
+                    ResolveError::Unresolved if types_only => (
+                        SymbolErrorKind::UnresolvedType,
+                        format!("Unresolved type `{name}`"),
+                    ),
+                    ResolveError::Unresolved => (
+                        SymbolErrorKind::UnresolvedExtension,
+                        format!("Unresolved extension `{name}`"),
+                    ),
+                    ResolveError::Ambiguous {
+                        resolved,
+                        candidate,
+                    } => (
+                        SymbolErrorKind::AmbiguousReference,
+                        format!(
+                            "`{name}` resolves to `{resolved}`, which is not defined. The \
+                             innermost scope is searched first, use `.{candidate}` to refer \
+                             to the outer declaration"
+                        ),
+                    ),
+                    ResolveError::NotAType { resolved } => (
+                        SymbolErrorKind::NotAType,
+                        format!("`{resolved}` is not a type"),
+                    ),
+                };
+
+                Some(SymbolError {
+                    kind,
+                    message,
+                    start: reference.start,
+                    end: reference.end,
+                })
+            })
+            .collect()
+    }
+
+    /// The files whose symbols `file` can use: itself, the files it imports
+    /// and the files those publicly import.
+    pub(crate) fn visible_files<'a>(&'a self, file: &'a str) -> HashSet<&'a str> {
+        let mut visible = HashSet::from([file]);
+        let mut pending: Vec<(&str, bool)> = vec![(file, true)];
+
+        while let Some((importer, direct)) = pending.pop() {
+            let Some(imports) = self.imports.get(importer) else {
+                continue;
+            };
+
+            for (imported, public) in imports {
+                if (direct || *public) && visible.insert(imported.as_str()) {
+                    pending.push((imported.as_str(), false));
+                }
+            }
+        }
+
+        visible
+    }
+}
+
+struct Collector<'a> {
+    table: &'a mut SymbolTable,
+    file: &'a str,
+}
+
+impl Collector<'_> {
+    fn insert<T: Clone>(&mut self, name: String, kind: SymbolKind, node: &Node<T>) -> &mut Symbol {
+        self.table
+            .declared
+            .entry(self.file.to_string())
+            .or_default()
+            .insert(name.clone());
+        let symbols = self.table.symbols.entry(name).or_default();
+        symbols.push(Symbol {
+            kind,
+            location: Location {
+                file: self.file.to_string(),
+                start: node.start,
+                end: node.end,
+            },
+            value_type: None,
+            extendee: None,
+        });
+        symbols.last_mut().unwrap()
+    }
+
+    fn root(&mut self, root: &Root) {
+        let package = package_name(root);
+
+        for node in &root.nodes {
+            match &node.value {
+                RootDeclaration::Package(package) => {
+                    let mut name = String::new();
+                    for part in &package.package_name.value {
+                        name = qualify(&name, part);
+                        self.insert(name.clone(), SymbolKind::Package, &package.package_name);
+                    }
+                }
+                RootDeclaration::Message(message) => self.message(&package, message),
+                RootDeclaration::Enum(enum_node) => self.enum_node(&package, enum_node),
+                RootDeclaration::Service(service) => self.service(&package, service),
+                RootDeclaration::Extension(extension) => self.extension(&package, extension),
+                _ => {}
+            }
+        }
+    }
+
+    fn message(&mut self, scope: &str, message: &Message) {
+        let name = qualify(scope, &message.name.value);
+        self.insert(name.clone(), SymbolKind::Message, &message.name);
+        self.message_elements(&name, &message.elements);
+    }
+
+    fn message_elements(&mut self, scope: &str, elements: &[Node<MessageElement>]) {
+        for element in elements {
+            match &element.value {
+                MessageElement::Field(field) => {
+                    self.insert(
+                        qualify(scope, &field.name.value),
+                        SymbolKind::Field,
+                        &field.name,
+                    )
+                    .value_type = Some(field.type_name.value.clone());
+                }
+                MessageElement::MapField(field) => {
+                    self.insert(
+                        qualify(scope, &field.name.value),
+                        SymbolKind::Field,
+                        &field.name,
+                    )
+                    .value_type = Some(field.value_type.value.clone());
+                }
+                MessageElement::Group(group) => {
+                    self.group(scope, group, SymbolKind::Field);
+                }
+                MessageElement::OneOf(oneof) => {
+                    self.insert(
+                        qualify(scope, &oneof.name.value),
+                        SymbolKind::Oneof,
+                        &oneof.name,
+                    );
+
+                    for element in &oneof.elements {
+                        match &element.value {
+                            OneofElement::OneofField(field) => {
+                                self.insert(
+                                    qualify(scope, &field.name.value),
+                                    SymbolKind::Field,
+                                    &field.name,
+                                )
+                                .value_type = Some(field.type_name.value.clone());
+                            }
+                            OneofElement::Group(group) => {
+                                self.group(scope, group, SymbolKind::Field);
+                            }
+                            OneofElement::Option(_) => {}
+                        }
+                    }
+                }
+                MessageElement::Message(message) => self.message(scope, message),
+                MessageElement::Enum(enum_node) => self.enum_node(scope, enum_node),
+                MessageElement::Extension(extension) => self.extension(scope, extension),
+                _ => {}
+            }
+        }
+    }
+
+    /// A group declares both a field (or extension), named after the
+    /// lowercased group name, and a nested message type. Returns the field.
+    fn group(
+        &mut self,
+        scope: &str,
+        group: &GroupDeclaration,
+        field_kind: SymbolKind,
+    ) -> &mut Symbol {
+        let field = qualify(scope, &group.name.value.to_ascii_lowercase());
+
+        let name = qualify(scope, &group.name.value);
+        self.insert(name.clone(), SymbolKind::Message, &group.name);
+        self.message_elements(&name, &group.elements);
+
+        let symbol = self.insert(field, field_kind, &group.name);
+        symbol.value_type = Some(FieldType::TypeName(TypeName::from(
+            group.name.value.clone(),
+        )));
+        symbol
+    }
+
+    fn enum_node(&mut self, scope: &str, enum_node: &EnumNode) {
+        let enum_name = qualify(scope, &enum_node.name.value);
+        self.insert(enum_name.clone(), SymbolKind::Enum, &enum_node.name);
+
+        let enum_type = TypeName {
+            absolute: true,
+            parts: enum_name.split('.').map(str::to_string).collect(),
+        };
+        for element in &enum_node.elements {
+            if let EnumElement::EnumValue { name, .. } = &element.value {
+                self.insert(qualify(scope, &name.value), SymbolKind::EnumValue, name)
+                    .value_type = Some(FieldType::TypeName(enum_type.clone()));
+            }
+        }
+    }
+
+    fn service(&mut self, scope: &str, service: &ServiceNode) {
+        let name = qualify(scope, &service.name.value);
+        self.insert(name.clone(), SymbolKind::Service, &service.name);
+
+        for element in &service.elements {
+            if let ServiceElement::Method(method) = &element.value {
+                self.insert(
+                    qualify(&name, &method.name.value),
+                    SymbolKind::Method,
+                    &method.name,
+                );
+            }
+        }
+    }
+
+    /// Extensions are declared in the scope of the `extend` block, not in
+    /// the message they extend.
+    fn extension(&mut self, scope: &str, extension: &ExtensionNode) {
+        for element in &extension.elements {
+            let symbol = match &element.value {
+                ExtensionElement::Field(field) => {
+                    let symbol = self.insert(
+                        qualify(scope, &field.name.value),
+                        SymbolKind::Extension,
+                        &field.name,
+                    );
+                    symbol.value_type = Some(field.type_name.value.clone());
+                    symbol
+                }
+                ExtensionElement::Group(group) => self.group(scope, group, SymbolKind::Extension),
+            };
+            symbol.extendee = Some(extension.extendee.value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{imports::ImportResolver, tokenize, Parser, Root, TypeName};
+
+    use super::{ResolveError, SymbolErrorKind, SymbolKind, SymbolTable};
+
+    fn table<const N: usize>(roots: [&Root; N]) -> SymbolTable {
+        let loaded: HashSet<&str> = roots.iter().map(|root| root.file_name.as_str()).collect();
+        let mut table = SymbolTable::default();
+        for root in roots {
+            let imports = ImportResolver::default().imports(root, &loaded);
+            table.set_imports(&root.file_name, &imports);
+            table.insert_file(root);
+        }
+        table
+    }
+
+    fn parse(file_name: &str, input: &str) -> Root {
+        let result = Parser::new(tokenize(input)).parse(file_name);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        result.root
+    }
+
+    fn resolve(table: &SymbolTable, file: &str, scope: &str, name: &str) -> Option<String> {
+        table
+            .resolve(file, scope, &TypeName::from(name.to_string()), true)
+            .ok()
+            .map(|(name, _)| name)
+    }
+
+    #[test]
+    fn resolves_relative_to_innermost_scope() {
+        let root = parse(
+            "/ws/a.proto",
+            r#"
+            package a.b;
+            message Outer {
+                message Inner {}
+                message Other { message Inner {} }
+            }
+            message Inner {}
+            "#,
+        );
+        let table = table([&root]);
+        let file = "/ws/a.proto";
+
+        assert_eq!(
+            resolve(&table, file, "a.b.Outer.Other", "Inner").as_deref(),
+            Some("a.b.Outer.Other.Inner")
+        );
+        assert_eq!(
+            resolve(&table, file, "a.b.Outer", "Inner").as_deref(),
+            Some("a.b.Outer.Inner")
+        );
+        assert_eq!(
+            resolve(&table, file, "a.b", "Inner").as_deref(),
+            Some("a.b.Inner")
+        );
+        assert_eq!(
+            resolve(&table, file, "a.b", "Outer.Other.Inner").as_deref(),
+            Some("a.b.Outer.Other.Inner")
+        );
+        assert_eq!(
+            resolve(&table, file, "a.b.Outer", "b.Inner").as_deref(),
+            Some("a.b.Inner")
+        );
+        assert_eq!(
+            resolve(&table, file, "", ".a.b.Outer.Inner").as_deref(),
+            Some("a.b.Outer.Inner")
+        );
+        assert_eq!(resolve(&table, file, "a.b", ".Inner"), None);
+        assert_eq!(
+            table.get("a").first().map(|symbol| symbol.kind),
+            Some(SymbolKind::Package)
+        );
+    }
+
+    #[test]
+    fn compound_names_stop_at_first_match() {
+        let root = parse(
+            "a.proto",
+            r#"
+            message Foo {
+                message Bar {}
+            }
+            message Outer {
+                message Foo {}
+                Foo.Bar bar = 1;
+            }
+            "#,
+        );
+        let table = table([&root]);
+
+        // `Foo` resolves to `Outer.Foo`, which has no `Bar`, so protoc
+        // doesn't look at the top level `Foo`.
+        assert_eq!(resolve(&table, "a.proto", "Outer", "Foo.Bar"), None);
+        assert_eq!(
+            resolve(&table, "a.proto", "Outer", ".Foo.Bar").as_deref(),
+            Some("Foo.Bar")
+        );
+    }
+
+    #[test]
+    fn only_imported_files_are_visible() {
+        let a = parse("/ws/a.proto", "package a; import \"b.proto\"; message A {}");
+        let b = parse(
+            "/ws/b.proto",
+            "package b; import public \"c.proto\"; import \"d.proto\"; message B {}",
+        );
+        let c = parse("/ws/c.proto", "package c; message C {}");
+        let d = parse("/ws/d.proto", "package d; message D {}");
+        let e = parse("/ws/e.proto", "package e; message E {}");
+        let table = table([&a, &b, &c, &d, &e]);
+        let file = "/ws/a.proto";
+
+        assert_eq!(resolve(&table, file, "a", "b.B").as_deref(), Some("b.B"));
+        assert_eq!(resolve(&table, file, "a", "c.C").as_deref(), Some("c.C"));
+        assert_eq!(resolve(&table, file, "a", "d.D"), None);
+        assert_eq!(resolve(&table, file, "a", "e.E"), None);
+    }
+
+    #[test]
+    fn extensions_are_declared_in_the_extend_scope() {
+        let root = parse(
+            "a.proto",
+            r#"
+            package p;
+            message Options { extensions 100 to max; }
+            message Scope {
+                extend Options { optional int32 ext = 100; }
+            }
+            "#,
+        );
+        let table = table([&root]);
+        let ext = TypeName::from("Scope.ext".to_string());
+
+        let (name, symbol) = table.resolve("a.proto", "p", &ext, false).unwrap();
+        assert_eq!(name, "p.Scope.ext");
+        assert_eq!(symbol.kind, SymbolKind::Extension);
+        assert_eq!(
+            table.resolve("a.proto", "p", &ext, true).unwrap_err(),
+            ResolveError::NotAType {
+                resolved: "p.Scope.ext".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn declares_fields_enum_values_and_methods() {
+        let root = parse(
+            "a.proto",
+            r#"
+            package p;
+            message M {
+                int32 a = 1;
+                oneof choice { string b = 2; }
+                optional group Result = 3 {}
+                enum E { E_UNSPECIFIED = 0; }
+            }
+            service S { rpc Call(M) returns (M); }
+            "#,
+        );
+        let table = table([&root]);
+        let kind = |name: &str| table.get(name).first().map(|symbol| symbol.kind);
+
+        assert_eq!(kind("p.M.a"), Some(SymbolKind::Field));
+        assert_eq!(kind("p.M.choice"), Some(SymbolKind::Oneof));
+        assert_eq!(kind("p.M.b"), Some(SymbolKind::Field));
+        assert_eq!(kind("p.M.result"), Some(SymbolKind::Field));
+        assert_eq!(kind("p.M.Result"), Some(SymbolKind::Message));
+        assert_eq!(kind("p.M.E_UNSPECIFIED"), Some(SymbolKind::EnumValue));
+        assert_eq!(kind("p.M.E.E_UNSPECIFIED"), None);
+        assert_eq!(kind("p.S.Call"), Some(SymbolKind::Method));
+    }
+
+    #[test]
+    fn check_reports_unresolved_and_ambiguous_references() {
+        let input = r#"
+            package p;
+            message Foo { message Bar {} }
+            message Outer {
+                message Foo {}
+                int32 value = 1;
+                Foo.Bar ambiguous = 2;
+                Missing unresolved = 3;
+                value not_a_type = 4;
+                Outer.value field = 5 [(missing) = true];
+                Foo.Baz missing_nested = 6;
+            }
+        "#;
+        let root = parse("a.proto", input);
+        let table = table([&root]);
+
+        let errors: Vec<_> = table
+            .check(&root)
+            .into_iter()
+            .map(|error| (error.kind, &input[error.start.offset..error.end.offset]))
+            .collect();
+
+        assert_eq!(
+            errors,
+            [
+                (SymbolErrorKind::AmbiguousReference, "Foo.Bar"),
+                (SymbolErrorKind::UnresolvedType, "Missing"),
+                (SymbolErrorKind::UnresolvedType, "value"),
+                (SymbolErrorKind::NotAType, "Outer.value"),
+                (SymbolErrorKind::UnresolvedExtension, "(missing)"),
+                (SymbolErrorKind::UnresolvedType, "Foo.Baz"),
+            ]
+        );
+        assert!(table.check(&root)[0].message.contains("`.p.Foo.Bar`"));
+    }
+}
@@ -0,0 +1,978 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    message::{FieldCardinality, GroupDeclaration, Message, MessageElement, OneofElement},
+    option::{OptionNamePart, OptionNode, OptionValue},
+    EnumElement, EnumNode, ExtensionElement, ExtensionNode, Node, Position, RelatedInformation,
+    Reserved, Root, RootDeclaration, SyntaxType, TagEnd, TagRange,
+};
+
+use super::{package_name, qualify};
+
+/// The largest field number, `2^29 - 1`.
+pub const MAX_FIELD_NUMBER: u32 = 536_870_911;
+
+/// Field numbers protoc reserves for the protobuf implementation.
+pub const IMPLEMENTATION_RESERVED: std::ops::RangeInclusive<u32> = 19_000..=19_999;
+
+/// The messages of `descriptor.proto` custom options extend, the only
+/// extensions proto3 allows.
+const OPTIONS_MESSAGES: &[&str] = &[
+    "FileOptions",
+    "MessageOptions",
+    "FieldOptions",
+    "OneofOptions",
+    "ExtensionRangeOptions",
+    "EnumOptions",
+    "EnumValueOptions",
+    "ServiceOptions",
+    "MethodOptions",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    DuplicateFieldNumber,
+    DuplicateFieldName,
+    ReservedFieldNumber,
+    ReservedFieldName,
+    ImplementationReservedNumber,
+    FieldNumberOutOfRange,
+    MissingSyntax,
+    MissingFieldLabel,
+    RequiredInProto3,
+    GroupInProto3,
+    DefaultInProto3,
+    ExtensionInProto3,
+    FirstEnumValueNotZero,
+    DuplicateEnumNumber,
+    DuplicateEnumValueName,
+    UnusedAllowAlias,
+    EnumValueScopeConflict,
+}
+
+impl ValidationErrorKind {
+    /// A stable identifier for the kind of error, used as the diagnostic code.
+    pub fn code(self) -> &'static str {
+        match self {
+            ValidationErrorKind::DuplicateFieldNumber => "duplicate-field-number",
+            ValidationErrorKind::DuplicateFieldName => "duplicate-field-name",
+            ValidationErrorKind::ReservedFieldNumber => "reserved-field-number",
+            ValidationErrorKind::ReservedFieldName => "reserved-field-name",
+            ValidationErrorKind::ImplementationReservedNumber => "implementation-reserved-number",
+            ValidationErrorKind::FieldNumberOutOfRange => "field-number-out-of-range",
+            ValidationErrorKind::MissingSyntax => "missing-syntax",
+            ValidationErrorKind::MissingFieldLabel => "missing-field-label",
+            ValidationErrorKind::RequiredInProto3 => "proto3-required",
+            ValidationErrorKind::GroupInProto3 => "proto3-group",
+            ValidationErrorKind::DefaultInProto3 => "proto3-default",
+            ValidationErrorKind::ExtensionInProto3 => "proto3-extension",
+            ValidationErrorKind::FirstEnumValueNotZero => "first-enum-value-not-zero",
+            ValidationErrorKind::DuplicateEnumNumber => "duplicate-enum-number",
+            ValidationErrorKind::DuplicateEnumValueName => "duplicate-enum-value-name",
+            ValidationErrorKind::UnusedAllowAlias => "unused-allow-alias",
+            ValidationErrorKind::EnumValueScopeConflict => "enum-value-scope-conflict",
+        }
+    }
+
+    /// Whether protoc accepts the file anyway.
+    pub fn is_warning(self) -> bool {
+        self == ValidationErrorKind::MissingSyntax
+    }
+}
+
+/// A declaration protoc would reject. `related` points at the declarations
+/// it conflicts with, such as the first field using the same number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub kind: ValidationErrorKind,
+    pub message: String,
+    pub start: Position,
+    pub end: Position,
+    pub related: Vec<RelatedInformation>,
+}
+
+/// Checks the fields of every message in `root`, including nested messages,
+/// groups and the fields of their oneofs, against each other and against the
+/// message's `reserved` statements, and the values of every enum.
+///
+/// Declarations are also checked against the rules of the file's `syntax`,
+/// e.g. proto3 doesn't allow `required` fields. A file without a `syntax`
+/// statement is proto2, as it is for protoc, but gets a warning.
+pub fn validate(root: &Root) -> Vec<ValidationError> {
+    let package = package_name(root);
+    let syntax = root.nodes.iter().find_map(|node| match &node.value {
+        RootDeclaration::Syntax(syntax) => Some(syntax.proto_type.clone()),
+        _ => None,
+    });
+    let mut validator = Validator {
+        syntax: syntax.clone().unwrap_or(SyntaxType::Proto2),
+        errors: Vec::new(),
+    };
+
+    if syntax.is_none() {
+        validator.errors.push(ValidationError {
+            kind: ValidationErrorKind::MissingSyntax,
+            message: "No syntax specified, defaulting to proto2. Add `syntax = \"proto2\";` or `syntax = \"proto3\";`".to_string(),
+            start: Position::default(),
+            end: Position::default(),
+            related: Vec::new(),
+        });
+    }
+
+    let mut declared = HashMap::new();
+    let mut enums = Vec::new();
+    for node in &root.nodes {
+        match &node.value {
+            RootDeclaration::Message(message) => {
+                declared.insert(message.name.value.as_str(), &message.name);
+                validator.message(&package, message);
+            }
+            RootDeclaration::Enum(enum_node) => {
+                declared.insert(enum_node.name.value.as_str(), &enum_node.name);
+                enums.push(enum_node);
+                validator.enum_node(enum_node);
+            }
+            RootDeclaration::Service(service) => {
+                declared.insert(service.name.value.as_str(), &service.name);
+            }
+            RootDeclaration::Extension(extension) => validator.extension(&package, extension),
+            _ => {}
+        }
+    }
+    validator.enum_scope(&package, declared, &enums);
+
+    validator.errors
+}
+
+/// A field of a message, or the field a group declares.
+struct Field<'a> {
+    /// The name of the field, which for a group is its lowercased name.
+    name: String,
+    name_node: &'a Node<String>,
+    number: &'a Node<u32>,
+}
+
+impl<'a> Field<'a> {
+    fn new(name: &'a Node<String>, number: &'a Node<u32>) -> Self {
+        Self {
+            name: name.value.clone(),
+            name_node: name,
+            number,
+        }
+    }
+
+    fn group(group: &'a GroupDeclaration) -> Self {
+        Self {
+            name: group.name.value.to_ascii_lowercase(),
+            name_node: &group.name,
+            number: &group.number,
+        }
+    }
+}
+
+struct Validator {
+    syntax: SyntaxType,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    fn error<T: Clone>(
+        &mut self,
+        kind: ValidationErrorKind,
+        message: String,
+        node: &Node<T>,
+        related: Vec<RelatedInformation>,
+    ) {
+        self.errors.push(ValidationError {
+            kind,
+            message,
+            start: node.start,
+            end: node.end,
+            related,
+        });
+    }
+
+    fn message(&mut self, scope: &str, message: &Message) {
+        let name = qualify(scope, &message.name.value);
+        self.message_elements(&name, &message.elements);
+    }
+
+    fn message_elements(&mut self, name: &str, elements: &[Node<MessageElement>]) {
+        let mut fields = Vec::new();
+        let mut reserved_ranges = Vec::new();
+        let mut reserved_names = Vec::new();
+        // The names of the nested types and oneofs, which enum values share
+        // a scope with, as do the fields.
+        let mut declared = HashMap::new();
+        let mut enums = Vec::new();
+
+        for element in elements {
+            match &element.value {
+                MessageElement::Field(field) => {
+                    self.field_syntax(Some(&field.cardinality), &field.type_name, &field.options);
+                    fields.push(Field::new(&field.name, &field.number));
+                }
+                MessageElement::MapField(field) => {
+                    self.field_syntax(None, &field.name, &field.options);
+                    fields.push(Field::new(&field.name, &field.number));
+                }
+                MessageElement::Group(group) => {
+                    fields.push(Field::group(group));
+                    declared.insert(group.name.value.as_str(), &group.name);
+                    self.group(name, group, true);
+                }
+                MessageElement::OneOf(oneof) => {
+                    declared.insert(oneof.name.value.as_str(), &oneof.name);
+                    for element in &oneof.elements {
+                        match &element.value {
+                            OneofElement::OneofField(field) => {
+                                let options = field.options.as_deref().unwrap_or_default();
+                                self.field_syntax(None, &field.name, options);
+                                fields.push(Field::new(&field.name, &field.number));
+                            }
+                            OneofElement::Group(group) => {
+                                fields.push(Field::group(group));
+                                declared.insert(group.name.value.as_str(), &group.name);
+                                self.group(name, group, false);
+                            }
+                            OneofElement::Option(_) => {}
+                        }
+                    }
+                }
+                MessageElement::Reserved(Reserved::TagRanges(ranges)) => {
+                    reserved_ranges.extend(ranges)
+                }
+                MessageElement::Reserved(Reserved::Names(names)) => reserved_names.extend(names),
+                MessageElement::Message(message) => {
+                    declared.insert(message.name.value.as_str(), &message.name);
+                    self.message(name, message);
+                }
+                MessageElement::Enum(enum_node) => {
+                    declared.insert(enum_node.name.value.as_str(), &enum_node.name);
+                    enums.push(enum_node);
+                    self.enum_node(enum_node);
+                }
+                MessageElement::Extension(extension) => self.extension(name, extension),
+                MessageElement::ExtensionRange(_) if self.syntax == SyntaxType::Proto3 => {
+                    self.error(
+                        ValidationErrorKind::ExtensionInProto3,
+                        "Extension ranges are not allowed in proto3".to_string(),
+                        element,
+                        Vec::new(),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        for field in &fields {
+            declared.entry(&field.name).or_insert(field.name_node);
+        }
+        self.enum_scope(name, declared, &enums);
+
+        let mut numbers: HashMap<u32, &Field> = HashMap::new();
+        let mut names: HashMap<&str, &Field> = HashMap::new();
+        for field in &fields {
+            self.field_number(field.number);
+
+            let number = field.number.value;
+            if let Some(first) = numbers.get(&number) {
+                self.error(
+                    ValidationErrorKind::DuplicateFieldNumber,
+                    format!(
+                        "Field number {number} has already been used in `{name}` by field `{}`",
+                        first.name
+                    ),
+                    field.number,
+                    vec![related("First used here", first.number)],
+                );
+            } else {
+                numbers.insert(number, field);
+            }
+
+            if let Some(first) = names.get(field.name.as_str()) {
+                self.error(
+                    ValidationErrorKind::DuplicateFieldName,
+                    format!("Field `{}` is already defined in `{name}`", field.name),
+                    field.name_node,
+                    vec![related("First defined here", first.name_node)],
+                );
+            } else {
+                names.insert(&field.name, field);
+            }
+
+            if let Some(range) = reserved_ranges
+                .iter()
+                .find(|range| contains(&range.value, number))
+            {
+                self.error(
+                    ValidationErrorKind::ReservedFieldNumber,
+                    format!("Field `{}` uses reserved field number {number}", field.name),
+                    field.number,
+                    vec![related("Reserved here", range)],
+                );
+            }
+
+            if let Some(reserved) = reserved_names
+                .iter()
+                .find(|reserved| reserved.value == field.name)
+            {
+                self.error(
+                    ValidationErrorKind::ReservedFieldName,
+                    format!("Field name `{}` is reserved in `{name}`", field.name),
+                    field.name_node,
+                    vec![related("Reserved here", reserved)],
+                );
+            }
+        }
+    }
+
+    /// Checks the field a group declares, which isn't part of a oneof if
+    /// `labeled`, and the message it declares.
+    fn group(&mut self, scope: &str, group: &GroupDeclaration, labeled: bool) {
+        if self.syntax == SyntaxType::Proto3 {
+            self.error(
+                ValidationErrorKind::GroupInProto3,
+                "Groups are not allowed in proto3".to_string(),
+                &group.name,
+                Vec::new(),
+            );
+        }
+        let cardinality = labeled.then_some(&group.cardinality);
+        self.field_syntax(cardinality, &group.name, &group.options);
+
+        self.message_elements(&qualify(scope, &group.name.value), &group.elements);
+    }
+
+    /// Extensions can't share numbers with the fields of the extended
+    /// message, which may be in another file, so only their range is checked.
+    fn extension(&mut self, scope: &str, extension: &ExtensionNode) {
+        if self.syntax == SyntaxType::Proto3 && !extends_options(scope, extension) {
+            self.error(
+                ValidationErrorKind::ExtensionInProto3,
+                "Extensions in proto3 are only allowed for defining options".to_string(),
+                &extension.extendee,
+                Vec::new(),
+            );
+        }
+
+        for element in &extension.elements {
+            match &element.value {
+                ExtensionElement::Field(field) => {
+                    self.field_syntax(Some(&field.cardinality), &field.type_name, &field.options);
+                    self.field_number(&field.number);
+                }
+                ExtensionElement::Group(group) => {
+                    self.field_number(&group.number);
+                    self.group(scope, group, true);
+                }
+            }
+        }
+    }
+
+    fn enum_node(&mut self, enum_node: &EnumNode) {
+        let enum_name = &enum_node.name.value;
+        let allow_alias = enum_node
+            .elements
+            .iter()
+            .find(|element| {
+                matches!(&element.value, EnumElement::EnumOption(option) if is_allow_alias(option))
+            });
+
+        let mut numbers: HashMap<i64, (&Node<String>, &Node<i64>)> = HashMap::new();
+        let mut names: HashMap<&str, &Node<String>> = HashMap::new();
+        let mut aliased = false;
+        for element in &enum_node.elements {
+            let EnumElement::EnumValue { name, number, .. } = &element.value else {
+                continue;
+            };
+
+            if numbers.is_empty() && number.value != 0 && self.syntax == SyntaxType::Proto3 {
+                self.error(
+                    ValidationErrorKind::FirstEnumValueNotZero,
+                    "The first enum value must be zero in proto3".to_string(),
+                    number,
+                    Vec::new(),
+                );
+            }
+
+            if let Some((first, first_number)) = numbers.get(&number.value) {
+                aliased = true;
+                if allow_alias.is_none() {
+                    self.error(
+                        ValidationErrorKind::DuplicateEnumNumber,
+                        format!(
+                            "`{}` uses the same number as `{}`. Add `option allow_alias = true;` to `{enum_name}` if this is intended",
+                            name.value, first.value
+                        ),
+                        number,
+                        vec![related("First used here", first_number)],
+                    );
+                }
+            } else {
+                numbers.insert(number.value, (name, number));
+            }
+
+            if let Some(first) = names.get(name.value.as_str()) {
+                self.error(
+                    ValidationErrorKind::DuplicateEnumValueName,
+                    format!("`{}` is already defined in `{enum_name}`", name.value),
+                    name,
+                    vec![related("First defined here", first)],
+                );
+            } else {
+                names.insert(&name.value, name);
+            }
+        }
+
+        if let (Some(option), false) = (allow_alias, aliased) {
+            self.error(
+                ValidationErrorKind::UnusedAllowAlias,
+                format!("`{enum_name}` allows aliases, but none of its values share a number"),
+                option,
+                Vec::new(),
+            );
+        }
+    }
+
+    /// Enum values are siblings of their enum, following C++ scoping rules,
+    /// so they can't share a name with the other declarations in `scope` or
+    /// with the values of another enum there.
+    fn enum_scope<'a>(
+        &mut self,
+        scope: &str,
+        mut declared: HashMap<&'a str, &'a Node<String>>,
+        enums: &[&'a EnumNode],
+    ) {
+        let scope_name = if scope.is_empty() {
+            "the file".to_string()
+        } else {
+            format!("`{scope}`")
+        };
+
+        for enum_node in enums {
+            // Duplicates within the enum are reported by `enum_node`.
+            let mut values = HashSet::new();
+            for element in &enum_node.elements {
+                let EnumElement::EnumValue { name, .. } = &element.value else {
+                    continue;
+                };
+                if !values.insert(name.value.as_str()) {
+                    continue;
+                }
+
+                if let Some(first) = declared.get(name.value.as_str()) {
+                    self.error(
+                        ValidationErrorKind::EnumValueScopeConflict,
+                        format!(
+                            "`{}` is already defined in {scope_name}. Enum values are siblings of their enum, so they must be unique within {scope_name}, not just within `{}`",
+                            name.value, enum_node.name.value
+                        ),
+                        name,
+                        vec![related("Defined here", first)],
+                    );
+                } else {
+                    declared.insert(&name.value, name);
+                }
+            }
+        }
+    }
+
+    /// Checks the label and options of a field against the rules of the
+    /// file's syntax. Map and oneof fields have no `cardinality`. A missing
+    /// label is reported on `declaration`, since its span is empty.
+    fn field_syntax<T: Clone>(
+        &mut self,
+        cardinality: Option<&Node<FieldCardinality>>,
+        declaration: &Node<T>,
+        options: &[Node<OptionNode>],
+    ) {
+        let Some(cardinality) = cardinality else {
+            return self.default_syntax(options);
+        };
+        match (&self.syntax, &cardinality.value) {
+            (SyntaxType::Proto2, _) if cardinality.start == cardinality.end => {
+                self.error(
+                    ValidationErrorKind::MissingFieldLabel,
+                    "Fields in proto2 must be `required`, `optional` or `repeated`".to_string(),
+                    declaration,
+                    Vec::new(),
+                );
+            }
+            (SyntaxType::Proto3, FieldCardinality::Required) => {
+                self.error(
+                    ValidationErrorKind::RequiredInProto3,
+                    "Required fields are not allowed in proto3".to_string(),
+                    cardinality,
+                    Vec::new(),
+                );
+            }
+            _ => {}
+        }
+        self.default_syntax(options);
+    }
+
+    /// proto3 fields can't have a `default` option.
+    fn default_syntax(&mut self, options: &[Node<OptionNode>]) {
+        if self.syntax != SyntaxType::Proto3 {
+            return;
+        }
+
+        for option in options {
+            if let [Node {
+                value: OptionNamePart::SimpleName(name),
+                ..
+            }] = option.value.name.value.as_slice()
+            {
+                if name == "default" {
+                    self.error(
+                        ValidationErrorKind::DefaultInProto3,
+                        "Explicit default values are not allowed in proto3".to_string(),
+                        option,
+                        Vec::new(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn field_number(&mut self, number: &Node<u32>) {
+        if number.value == 0 || number.value > MAX_FIELD_NUMBER {
+            self.error(
+                ValidationErrorKind::FieldNumberOutOfRange,
+                format!("Field numbers must be between 1 and {MAX_FIELD_NUMBER}"),
+                number,
+                Vec::new(),
+            );
+        } else if IMPLEMENTATION_RESERVED.contains(&number.value) {
+            self.error(
+                ValidationErrorKind::ImplementationReservedNumber,
+                format!(
+                    "Field numbers {} through {} are reserved for the protobuf implementation",
+                    IMPLEMENTATION_RESERVED.start(),
+                    IMPLEMENTATION_RESERVED.end()
+                ),
+                number,
+                Vec::new(),
+            );
+        }
+    }
+}
+
+fn related<T: Clone>(message: &str, node: &Node<T>) -> RelatedInformation {
+    RelatedInformation {
+        message: message.to_string(),
+        start: node.start,
+        end: node.end,
+    }
+}
+
+/// Whether `option` is `allow_alias = true`.
+fn is_allow_alias(option: &OptionNode) -> bool {
+    matches!(
+        (option.name.value.as_slice(), &option.value.value),
+        ([Node { value: OptionNamePart::SimpleName(name), .. }], OptionValue::Identifier(value))
+            if name == "allow_alias" && value == "true"
+    )
+}
+
+/// Whether `extension`, declared in `scope`, extends one of the options
+/// messages of `descriptor.proto`. A relative extendee is qualified with
+/// `scope` and each of its parents, the way it would be resolved, without
+/// checking which of those names are declared.
+fn extends_options(scope: &str, extension: &ExtensionNode) -> bool {
+    let extendee = &extension.extendee.value;
+    let name = extendee.parts.join(".");
+    let scopes = std::iter::successors(Some(scope), |scope| {
+        (!scope.is_empty()).then(|| scope.rsplit_once('.').map_or("", |(parent, _)| parent))
+    });
+
+    scopes
+        .filter(|scope| !extendee.absolute || scope.is_empty())
+        .any(|scope| {
+            qualify(scope, &name)
+                .strip_prefix("google.protobuf.")
+                .is_some_and(|message| OPTIONS_MESSAGES.contains(&message))
+        })
+}
+
+/// Whether `number` is in `range`. Ranges include their end, and `max` is
+/// the largest field number.
+fn contains(range: &TagRange, number: u32) -> bool {
+    let end = match range.end.as_ref().map(|end| &end.value) {
+        Some(TagEnd::Tag(end)) => *end,
+        Some(TagEnd::Max) => MAX_FIELD_NUMBER,
+        None => range.start.value,
+    };
+    (range.start.value..=end).contains(&number)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenize, Parser};
+
+    use super::{validate, ValidationError, ValidationErrorKind};
+
+    fn errors(input: &str) -> Vec<ValidationError> {
+        let result = Parser::new(tokenize(input)).parse("test.proto");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        validate(&result.root)
+    }
+
+    fn kinds(errors: &[ValidationError]) -> Vec<ValidationErrorKind> {
+        errors.iter().map(|error| error.kind).collect()
+    }
+
+    #[test]
+    fn duplicate_fields_point_at_the_first_declaration() {
+        let errors = errors(
+            r#"
+            syntax = "proto3";
+            package pkg;
+            message A {
+                int32 a = 1;
+                map<string, int32> b = 2;
+                oneof choice {
+                    string c = 1;
+                    string a = 3;
+                }
+                message B {
+                    int32 a = 1;
+                    int32 b = 1;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::DuplicateFieldNumber,
+                ValidationErrorKind::DuplicateFieldNumber,
+                ValidationErrorKind::DuplicateFieldName,
+            ]
+        );
+        // The nested message is checked before the fields of `A`.
+        assert_eq!(
+            errors[0].message,
+            "Field number 1 has already been used in `pkg.A.B` by field `a`"
+        );
+        assert_eq!(
+            errors[1].message,
+            "Field number 1 has already been used in `pkg.A` by field `a`"
+        );
+        assert_eq!(errors[1].start.line, 7);
+        assert_eq!(errors[1].related[0].message, "First used here");
+        assert_eq!(errors[1].related[0].start.line, 4);
+        assert_eq!(errors[2].message, "Field `a` is already defined in `pkg.A`");
+        assert_eq!(errors[2].start.line, 8);
+        assert_eq!(errors[2].related[0].start.line, 4);
+    }
+
+    #[test]
+    fn groups_declare_a_lowercase_field() {
+        let errors = errors(
+            r#"
+            syntax = "proto2";
+            message A {
+                optional int32 result = 1;
+                repeated group Result = 2 {
+                    optional int32 a = 1;
+                    optional int32 b = 1;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::DuplicateFieldNumber,
+                ValidationErrorKind::DuplicateFieldName,
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "Field number 1 has already been used in `A.Result` by field `a`"
+        );
+        assert_eq!(
+            errors[1].message,
+            "Field `result` is already defined in `A`"
+        );
+    }
+
+    #[test]
+    fn reserved_numbers_and_names() {
+        let errors = errors(
+            r#"
+            syntax = "proto3";
+            message A {
+                reserved 2, 5 to 7, 100 to max;
+                reserved "old";
+                int32 a = 6;
+                int32 old = 8;
+                int32 b = 200;
+                int32 c = 9;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::ReservedFieldNumber,
+                ValidationErrorKind::ReservedFieldName,
+                ValidationErrorKind::ReservedFieldNumber,
+            ]
+        );
+        assert_eq!(errors[0].message, "Field `a` uses reserved field number 6");
+        assert_eq!(errors[0].related[0].message, "Reserved here");
+        assert_eq!(errors[0].related[0].start.column, 28);
+        assert_eq!(errors[1].related[0].start.line, 4);
+        assert_eq!(errors[2].related[0].start.column, 36);
+    }
+
+    #[test]
+    fn field_number_ranges() {
+        let errors = errors(
+            r#"
+            syntax = "proto2";
+            message A {
+                optional int32 a = 19000;
+                optional int32 b = 536870912;
+                optional int32 c = 536870911;
+                optional int32 d = 18999;
+                extensions 100 to 200;
+            }
+            extend A {
+                optional int32 e = 19999;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::ImplementationReservedNumber,
+                ValidationErrorKind::FieldNumberOutOfRange,
+                ValidationErrorKind::ImplementationReservedNumber,
+            ]
+        );
+        assert_eq!(
+            errors[1].message,
+            "Field numbers must be between 1 and 536870911"
+        );
+        assert_eq!(errors[2].start.line, 10);
+    }
+
+    #[test]
+    fn proto3_rules() {
+        let errors = errors(
+            r#"
+            syntax = "proto3";
+            import "google/protobuf/descriptor.proto";
+            message A {
+                required int32 a = 1;
+                optional int32 b = 2 [default = 3];
+                repeated group C = 3 {}
+                extensions 100 to 200;
+            }
+            enum E {
+                E_ONE = 1;
+                E_ZERO = 0;
+            }
+            extend A {
+                int32 d = 100;
+            }
+            extend google.protobuf.FieldOptions {
+                int32 e = 50000;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::RequiredInProto3,
+                ValidationErrorKind::DefaultInProto3,
+                ValidationErrorKind::GroupInProto3,
+                ValidationErrorKind::ExtensionInProto3,
+                ValidationErrorKind::FirstEnumValueNotZero,
+                ValidationErrorKind::ExtensionInProto3,
+            ]
+        );
+        assert_eq!(errors[0].start.column, 16);
+        assert_eq!(errors[0].end.column, 24);
+        assert_eq!(errors[4].start.line, 10);
+        assert_eq!(errors[5].start.line, 13);
+    }
+
+    #[test]
+    fn proto3_extensions_must_name_an_options_message() {
+        let extendees = |package: &str| {
+            let errors = errors(&format!(
+                r#"
+                syntax = "proto3";
+                package {package};
+                import "google/protobuf/descriptor.proto";
+                extend FieldOptions {{ int32 a = 50000; }}
+                extend protobuf.FieldOptions {{ int32 b = 50001; }}
+                extend google.protobuf.FieldOptions {{ int32 c = 50002; }}
+                extend .google.protobuf.FieldOptions {{ int32 d = 50003; }}
+                message M {{
+                    extend FieldOptions {{ int32 e = 50004; }}
+                }}
+                "#
+            ));
+            errors
+                .iter()
+                .map(|error| error.start.line)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(extendees("foo"), [4, 5, 9]);
+        assert_eq!(extendees("google.foo"), [4, 9]);
+        assert_eq!(extendees("google.protobuf"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn proto2_fields_need_a_label() {
+        let errors = errors(
+            r#"
+            message A {
+                int32 a = 1;
+                map<string, int32> b = 2;
+                oneof c {
+                    int32 d = 3;
+                }
+                optional int32 e = 4 [default = 1];
+            }
+            extend A {
+                string f = 100;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::MissingSyntax,
+                ValidationErrorKind::MissingFieldLabel,
+                ValidationErrorKind::MissingFieldLabel,
+            ]
+        );
+        assert!(errors[0].kind.is_warning());
+        assert_eq!(errors[1].start.line, 2);
+        assert_eq!(errors[1].start.column, 16);
+        assert_eq!(errors[2].start.line, 10);
+    }
+
+    #[test]
+    fn editions_have_no_syntax_rules() {
+        let errors = errors(
+            r#"
+            edition = "2023";
+            message A {
+                int32 a = 1 [default = 2];
+            }
+            enum E {
+                E_ONE = 1;
+            }
+            "#,
+        );
+
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn enum_values() {
+        let errors = errors(
+            r#"
+            syntax = "proto3";
+            package pkg;
+            enum A {
+                A_UNKNOWN = 0;
+                A_ONE = 1;
+                A_UNO = 1;
+                A_ONE = 2;
+            }
+            enum B {
+                option allow_alias = true;
+                B_UNKNOWN = 0;
+                B_ONE = 1;
+                B_UNO = 1;
+            }
+            enum C {
+                option allow_alias = true;
+                C_UNKNOWN = 0;
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::DuplicateEnumNumber,
+                ValidationErrorKind::DuplicateEnumValueName,
+                ValidationErrorKind::UnusedAllowAlias,
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "`A_UNO` uses the same number as `A_ONE`. Add `option allow_alias = true;` to `A` if this is intended"
+        );
+        assert_eq!((errors[0].start.line, errors[0].start.column), (6, 24));
+        assert_eq!(errors[0].related[0].start.line, 5);
+        assert_eq!(errors[1].related[0].start.line, 5);
+        assert_eq!(errors[2].start.line, 16);
+    }
+
+    #[test]
+    fn enum_values_are_siblings_of_their_enum() {
+        let errors = errors(
+            r#"
+            syntax = "proto2";
+            package pkg;
+            message UNKNOWN {}
+            enum A {
+                UNKNOWN = 0;
+                ONE = 1;
+            }
+            enum B {
+                ONE = 1;
+            }
+            message M {
+                optional int32 value = 1;
+                enum C {
+                    value = 0;
+                }
+                enum D {
+                    ONE = 1;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(
+            kinds(&errors),
+            [
+                ValidationErrorKind::EnumValueScopeConflict,
+                ValidationErrorKind::EnumValueScopeConflict,
+                ValidationErrorKind::EnumValueScopeConflict,
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "`value` is already defined in `pkg.M`. Enum values are siblings of their enum, so they must be unique within `pkg.M`, not just within `C`"
+        );
+        assert_eq!(errors[0].related[0].start.line, 12);
+        assert_eq!(
+            errors[1].message.split('.').next(),
+            Some("`UNKNOWN` is already defined in `pkg`")
+        );
+        assert_eq!(errors[1].related[0].start.line, 3);
+        assert_eq!(errors[2].start.line, 9);
+        assert_eq!(errors[2].related[0].start.line, 6);
+    }
+}
@@ -0,0 +1,105 @@
+//! The well-known types that ship with protoc, bundled so that imports like
+//! `google/protobuf/timestamp.proto` resolve without any include paths.
+
+/// The virtual include root the bundled files live under. Their file names
+/// are this prefix followed by the import path, e.g.
+/// `protols:/google/protobuf/timestamp.proto`.
+pub const BUNDLED_ROOT: &str = "protols:/";
+
+/// The file the built-in options are declared in.
+pub const DESCRIPTOR: &str = "protols:/google/protobuf/descriptor.proto";
+
+macro_rules! bundle {
+    ($($name:literal),* $(,)?) => {
+        &[$((
+            concat!("google/protobuf/", $name),
+            include_str!(concat!("../../include/google/protobuf/", $name)),
+        )),*]
+    };
+}
+
+/// Import paths and sources of the bundled files.
+const FILES: &[(&str, &str)] = bundle![
+    "any.proto",
+    "api.proto",
+    "descriptor.proto",
+    "duration.proto",
+    "empty.proto",
+    "field_mask.proto",
+    "source_context.proto",
+    "struct.proto",
+    "timestamp.proto",
+    "type.proto",
+    "wrappers.proto",
+];
+
+/// Returns the name of the bundled file for an import path.
+pub fn resolve(path: &str) -> Option<String> {
+    FILES
+        .iter()
+        .any(|(import_path, _)| *import_path == path)
+        .then(|| format!("{BUNDLED_ROOT}{path}"))
+}
+
+/// Returns the source of a bundled file by its name.
+pub fn source(file: &str) -> Option<&'static str> {
+    let path = file.strip_prefix(BUNDLED_ROOT)?;
+    FILES
+        .iter()
+        .find(|(import_path, _)| *import_path == path)
+        .map(|(_, source)| *source)
+}
+
+pub fn is_bundled(file: &str) -> bool {
+    file.starts_with(BUNDLED_ROOT)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{symbols::SymbolKind, Source};
+
+    use super::{resolve, source, FILES};
+
+    #[test]
+    fn bundled_files_are_valid() {
+        let mut sources = Source::new();
+        for (path, _) in FILES {
+            let file = resolve(path).unwrap();
+            sources.parse(&file, source(&file).unwrap());
+        }
+
+        for (path, _) in FILES {
+            let file = resolve(path).unwrap();
+            let result = sources.parse_result(&file).unwrap();
+            assert!(result.errors.is_empty(), "{path}: {:?}", result.errors);
+            assert!(
+                sources.symbol_errors(&file).is_empty(),
+                "{path}: {:?}",
+                sources.symbol_errors(&file)
+            );
+            assert!(
+                sources.validation_errors(&file).is_empty(),
+                "{path}: {:?}",
+                sources.validation_errors(&file)
+            );
+        }
+    }
+
+    #[test]
+    fn resolves_well_known_types_without_setup() {
+        let mut sources = Source::new();
+        sources.parse(
+            "/ws/a.proto",
+            r#"import "google/protobuf/timestamp.proto"; message A { google.protobuf.Timestamp t = 1; }"#,
+        );
+
+        assert!(sources.symbol_errors("/ws/a.proto").is_empty());
+        let symbol = &sources.symbols().get("google.protobuf.Timestamp")[0];
+        assert_eq!(symbol.kind, SymbolKind::Message);
+        assert_eq!(
+            symbol.location.file,
+            "protols:/google/protobuf/timestamp.proto"
+        );
+        assert_eq!(resolve("google/protobuf/missing.proto"), None);
+    }
+}
@@ -1,6 +1,8 @@
 mod analysis;
 mod source;
 mod syntax;
+#[cfg(test)]
+mod test_util;
 
 pub use analysis::*;
 pub use source::*;
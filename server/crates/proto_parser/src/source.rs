@@ -1,46 +1,724 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
 
-use crate::{completion::get_suggestions, get_messages, tokenize, ParseResult, Parser, Position};
+use rayon::prelude::*;
+
+use crate::{
+    completion::{self, Completion},
+    features::{resolve_features, FeatureError},
+    hover::{describe, Hover},
+    imports::{Import, ImportResolver},
+    references::{references, resolved_references, ReferenceKind},
+    symbols::{Location, Symbol, SymbolError, SymbolTable},
+    validation::{validate, ValidationError},
+    well_known_types, IncrementalParser, LineIndex, ParseResult, Position,
+};
 
 #[derive(Debug)]
 pub struct Source {
-    trees: HashMap<String, ParseResult>,
-    completions: Vec<String>,
-    messages: HashMap<String, Position>,
+    files: HashMap<String, IncrementalParser>,
+    resolver: ImportResolver,
+    imports: HashMap<String, Vec<Import>>,
+    symbols: SymbolTable,
+    /// The names the references in each file resolve to, filled in by
+    /// `references`. A file's entry is dropped when it changes, and all of
+    /// them when the symbols or imports they were resolved with change.
+    resolved: Mutex<HashMap<String, Vec<(String, Location)>>>,
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Source {
     pub fn new() -> Self {
-        Self {
-            trees: HashMap::new(),
-            completions: Vec::new(),
-            messages: HashMap::new(),
+        let mut source = Self {
+            files: HashMap::new(),
+            resolver: ImportResolver::default(),
+            imports: HashMap::new(),
+            symbols: SymbolTable::default(),
+            resolved: Mutex::default(),
+        };
+
+        // The built-in options are the fields of the options messages in
+        // descriptor.proto, which is loaded even when nothing imports it so
+        // they can be completed.
+        let descriptor = well_known_types::DESCRIPTOR;
+        if let Some(text) = well_known_types::source(descriptor) {
+            source.parse(descriptor, text);
         }
+        source
     }
 
+    /// Parses a file, along with the files it imports that haven't been
+    /// loaded yet.
     pub fn parse(&mut self, name: &str, source: &str) {
-        let tokens = tokenize(source);
-        let tree = Parser::new(tokens).parse(name);
-
-        self.trees.insert(name.to_string(), tree);
-        self.completions = get_suggestions(self.trees.values().map(|result| &result.root));
-        let messages = get_messages(self.trees.values().map(|result| &result.root));
-        self.messages = messages
-            .map(|message| (message.node.value.name.clone(), message.node.start))
+        let is_new = !self.files.contains_key(name);
+        self.insert(name.to_string(), IncrementalParser::new(name, source));
+
+        // A new file may be what other files failed to import.
+        let mut pending = vec![name.to_string()];
+        if is_new {
+            pending.extend(self.files_with_unresolved_imports());
+        }
+        self.resolve_imports(pending);
+    }
+
+    /// Parses many files at once, e.g. a whole workspace. The files are
+    /// parsed in parallel, and imports are only resolved once all of them
+    /// have been loaded.
+    pub fn parse_all(&mut self, files: Vec<(String, String)>) {
+        self.insert_all(Self::parse_files(files));
+    }
+
+    /// Parses files in parallel without loading them, so it can run while
+    /// the `Source` is in use. `insert_all` loads the result.
+    pub fn parse_files(files: Vec<(String, String)>) -> Vec<(String, IncrementalParser)> {
+        files
+            .into_par_iter()
+            .map(|(name, text)| {
+                let parser = IncrementalParser::new(&name, &text);
+                (name, parser)
+            })
+            .collect()
+    }
+
+    /// Loads files parsed by `parse_files`, replacing the ones with the same
+    /// name, and then resolves imports once for all of them.
+    pub fn insert_all(&mut self, parsed: Vec<(String, IncrementalParser)>) {
+        let mut pending = self.files_with_unresolved_imports();
+        for (name, parser) in parsed {
+            self.insert(name.clone(), parser);
+            pending.push(name);
+        }
+        self.resolve_imports(pending);
+    }
+
+    /// Sets the directories imports are relative to and resolves the imports
+    /// of every file again.
+    pub fn set_include_paths(&mut self, include_paths: Vec<PathBuf>) {
+        self.resolver = ImportResolver::new(include_paths);
+        self.resolve_imports(self.files.keys().cloned().collect());
+    }
+
+    pub fn include_paths(&self) -> &[PathBuf] {
+        self.resolver.include_paths()
+    }
+
+    /// Replaces the byte range `range` of a parsed file with `text`, only
+    /// reparsing the declarations the edit touches. Returns `false` if the
+    /// file hasn't been parsed or the range splits a char.
+    pub fn edit(&mut self, name: &str, range: Range<usize>, text: &str) -> bool {
+        let Some(file) = self.files.get_mut(name) else {
+            return false;
+        };
+
+        if !file.edit(range, text) {
+            return false;
+        }
+        let changed = self.symbols.insert_file(&file.result().root);
+        self.invalidate(name, changed);
+        self.resolve_imports(vec![name.to_string()]);
+        true
+    }
+
+    /// Forgets a file. Returns `false` if it hasn't been parsed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        if self.files.remove(name).is_none() {
+            return false;
+        }
+
+        self.imports.remove(name);
+        self.symbols.remove_file(name);
+        self.invalidate(name, true);
+        let importers = self
+            .imports
+            .iter()
+            .filter(|(_, imports)| {
+                imports
+                    .iter()
+                    .any(|import| import.file.as_deref() == Some(name))
+            })
+            .map(|(importer, _)| importer.clone())
+            .collect();
+        self.resolve_imports(importers);
+        true
+    }
+
+    fn files_with_unresolved_imports(&self) -> Vec<String> {
+        self.imports
+            .iter()
+            .filter(|(_, imports)| imports.iter().any(|import| import.file.is_none()))
+            .map(|(file, _)| file.clone())
+            .collect()
+    }
+
+    /// Resolves the imports of the files in `pending`. Imported files that
+    /// haven't been loaded yet are parsed from disk, or from the bundled
+    /// well-known types, and their imports are resolved in turn.
+    fn resolve_imports(&mut self, mut pending: Vec<String>) {
+        while let Some(name) = pending.pop() {
+            let Some(file) = self.files.get(&name) else {
+                continue;
+            };
+
+            let loaded: HashSet<&str> = self.files.keys().map(String::as_str).collect();
+            let imports = self.resolver.imports(&file.result().root, &loaded);
+            let unloaded: Vec<String> = imports
+                .iter()
+                .filter_map(|import| import.file.clone())
+                .filter(|file| !loaded.contains(file.as_str()))
+                .collect();
+            if self.symbols.set_imports(&name, &imports) {
+                self.invalidate(&name, true);
+            }
+            self.imports.insert(name, imports);
+
+            for file in unloaded {
+                let text = match well_known_types::source(&file) {
+                    Some(text) => Ok(text.to_string()),
+                    None => std::fs::read_to_string(&file),
+                };
+                match text {
+                    Ok(text) => {
+                        let parser = IncrementalParser::new(&file, &text);
+                        self.insert(file.clone(), parser);
+                        pending.push(file);
+                    }
+                    Err(err) => log::warn!("failed to read {file}: {err}"),
+                }
+            }
+        }
+    }
+
+    /// Adds or replaces a parsed file along with its symbols. Its imports
+    /// are left to `resolve_imports`.
+    fn insert(&mut self, name: String, parser: IncrementalParser) {
+        let changed = self.symbols.insert_file(&parser.result().root);
+        self.invalidate(&name, changed);
+        self.files.insert(name, parser);
+    }
+
+    /// Drops the cached references of `file`, or of every file when
+    /// `symbols_changed`.
+    fn invalidate(&mut self, file: &str, symbols_changed: bool) {
+        let resolved = self
+            .resolved
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if symbols_changed {
+            resolved.clear();
+        } else {
+            resolved.remove(file);
+        }
+    }
+
+    /// Returns the import statements of `file` and the files they resolved to.
+    pub fn imports(&self, file: &str) -> &[Import] {
+        self.imports.get(file).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn line_index(&self, file: &str) -> Option<&LineIndex> {
+        Some(self.files.get(file)?.cst().line_index())
+    }
+
+    pub fn text(&self, file: &str) -> Option<&str> {
+        Some(self.files.get(file)?.text())
+    }
+
+    pub fn parse_result(&self, file: &str) -> Option<&ParseResult> {
+        Some(self.files.get(file)?.result())
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Returns the references in `file` that don't resolve.
+    pub fn symbol_errors(&self, file: &str) -> Vec<SymbolError> {
+        self.files
+            .get(file)
+            .map(|file| self.symbols.check(&file.result().root))
+            .unwrap_or_default()
+    }
+
+    /// Returns the declarations in `file` protoc would reject, such as fields
+    /// sharing a number.
+    pub fn validation_errors(&self, file: &str) -> Vec<ValidationError> {
+        self.files
+            .get(file)
+            .map(|file| validate(&file.result().root))
+            .unwrap_or_default()
+    }
+
+    /// Returns the `features.*` options in `file` that are invalid, or used
+    /// outside of editions.
+    pub fn feature_errors(&self, file: &str) -> Vec<FeatureError> {
+        self.files
+            .get(file)
+            .map(|file| resolve_features(&file.result().root).errors)
+            .unwrap_or_default()
+    }
+
+    /// Returns the completions at `position` in `file`.
+    pub fn completions(&self, file: &str, position: Position) -> Vec<Completion> {
+        match self.text(file) {
+            Some(text) => completion::completions(text, position.offset, file, &self.symbols),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the name of the declaration the type or option extension name
+    /// at `position` refers to.
+    pub fn goto_definition(&self, file: &str, position: Position) -> Option<Location> {
+        let root = &self.files.get(file)?.result().root;
+        let reference = references(root)
+            .into_iter()
+            .find(|reference| reference.contains(position))?;
+
+        let types_only = reference.kind == ReferenceKind::Type;
+        let (_, symbol) = self
+            .symbols
+            .resolve(file, &reference.scope, reference.name, types_only)
+            .ok()?;
+
+        Some(symbol.location.clone())
+    }
+
+    /// Describes the declaration at `position`, or the one the name at
+    /// `position` refers to.
+    pub fn hover(&self, file: &str, position: Position) -> Option<Hover> {
+        let (name, symbol, start, end) = self.symbol_at(file, position)?;
+        let root = |file: &str| self.files.get(file).map(|file| &file.result().root);
+        Some(Hover {
+            contents: describe(&name, symbol, root, &self.symbols),
+            start,
+            end,
+        })
+    }
+
+    /// Returns every place in the workspace that refers to the declaration at
+    /// `position`, or the one the name at `position` refers to, ordered by
+    /// file and position.
+    pub fn references(
+        &self,
+        file: &str,
+        position: Position,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        let Some((name, symbol, _, _)) = self.symbol_at(file, position) else {
+            return Vec::new();
+        };
+
+        let mut resolved = self.resolved.lock().unwrap_or_else(PoisonError::into_inner);
+        let missing: Vec<_> = self
+            .files
+            .iter()
+            .filter(|(file_name, _)| !resolved.contains_key(*file_name))
+            .collect();
+        let collected: Vec<_> = missing
+            .into_par_iter()
+            .map(|(file_name, file)| {
+                let references = resolved_references(&file.result().root, &self.symbols)
+                    .into_iter()
+                    .map(|reference| {
+                        let location = Location {
+                            file: file_name.clone(),
+                            start: reference.start,
+                            end: reference.end,
+                        };
+                        (reference.name, location)
+                    })
+                    .collect();
+                (file_name.clone(), references)
+            })
+            .collect();
+        resolved.extend(collected);
+
+        let mut locations: Vec<Location> = resolved
+            .values()
+            .flatten()
+            .filter(|(resolved_name, _)| *resolved_name == name)
+            .map(|(_, location)| location.clone())
             .collect();
+        if include_declaration {
+            locations.push(symbol.location.clone());
+        }
+
+        locations.sort_by(|a, b| (&a.file, a.start.offset).cmp(&(&b.file, b.start.offset)));
+        locations
     }
 
-    pub fn completions(&self, _line: usize, _column: usize) -> Vec<String> {
-        self.completions
+    /// Returns the symbol the name at `position` refers to, or else the one
+    /// declared there, with the range of the name.
+    fn symbol_at(
+        &self,
+        file: &str,
+        position: Position,
+    ) -> Option<(String, &Symbol, Position, Position)> {
+        let root = &self.files.get(file)?.result().root;
+        let reference = resolved_references(root, &self.symbols)
+            .into_iter()
+            .find(|reference| reference.contains(position));
+        if let Some(reference) = reference {
+            return Some((
+                reference.name,
+                reference.symbol,
+                reference.start,
+                reference.end,
+            ));
+        }
+
+        // A group's field and message are both declared at its name, as are
+        // the parent packages of a package. Prefer the type and the innermost
+        // package.
+        let (name, symbol) = self
+            .symbols
             .iter()
-            .map(|completion| completion.to_string())
+            .filter(|(_, symbol)| {
+                let location = &symbol.location;
+                location.file == file
+                    && location.start.offset <= position.offset
+                    && position.offset <= location.end.offset
+            })
+            .max_by_key(|(name, symbol)| (symbol.kind.is_type(), name.len()))?;
+        let location = &symbol.location;
+        Some((name.to_string(), symbol, location.start, location.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{symbols::Location, test_util::position_at, Position};
+
+    use super::Source;
+
+    /// Returns the position of the first occurrence of `text` in `file`.
+    fn position(source: &Source, file: &str, text: &str) -> Position {
+        let offset = source.text(file).unwrap().find(text).unwrap();
+        position_at(source, file, offset)
+    }
+
+    /// Returns the definition for the reference that starts at the first
+    /// occurrence of `reference` in `file`.
+    fn goto(source: &Source, file: &str, reference: &str) -> Option<(String, String)> {
+        let position = position(source, file, reference);
+        let Location { file, start, end } = source.goto_definition(file, position)?;
+        let name = source.text(&file).unwrap()[start.offset..end.offset].to_string();
+        Some((file, name))
+    }
+
+    #[test]
+    fn goto_definition_across_files() {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/types.proto",
+            r#"
+            package test.users.v1;
+            import "google/protobuf/descriptor.proto";
+            message User {
+                message Name { string first = 1; }
+                enum Role { ROLE_UNSPECIFIED = 0; }
+            }
+            extend google.protobuf.FieldOptions { optional bool sensitive = 50000; }
+            "#,
+        );
+        source.parse(
+            "/ws/service.proto",
+            r#"
+            package test.users.v1;
+            import "types.proto";
+            message GetUserRequest {
+                User.Name name = 1 [(sensitive) = true];
+                map<string, .test.users.v1.User.Role> roles = 2;
+            }
+            service Users {
+                rpc GetUser(GetUserRequest) returns (User);
+            }
+            "#,
+        );
+
+        let file = "/ws/service.proto";
+        let types = "/ws/types.proto".to_string();
+        assert_eq!(
+            goto(&source, file, "User.Name"),
+            Some((types.clone(), "Name".to_string()))
+        );
+        assert_eq!(
+            goto(&source, file, "sensitive"),
+            Some((types.clone(), "sensitive".to_string()))
+        );
+        assert_eq!(
+            goto(&source, file, ".test.users"),
+            Some((types.clone(), "Role".to_string()))
+        );
+        assert_eq!(
+            goto(&source, file, "GetUserRequest)"),
+            Some((file.to_string(), "GetUserRequest".to_string()))
+        );
+        assert_eq!(
+            goto(&source, file, "User);"),
+            Some((types.clone(), "User".to_string()))
+        );
+        assert_eq!(
+            goto(&source, &types, "google.protobuf.FieldOptions"),
+            Some((
+                "protols:/google/protobuf/descriptor.proto".to_string(),
+                "FieldOptions".to_string()
+            ))
+        );
+        assert_eq!(goto(&source, file, "string"), None);
+    }
+
+    /// Returns the file, line and text of each reference to the name at the
+    /// first occurrence of `name` in `file`.
+    fn references(
+        source: &Source,
+        file: &str,
+        name: &str,
+        include_declaration: bool,
+    ) -> Vec<(String, usize, String)> {
+        let position = position(source, file, name);
+        source
+            .references(file, position, include_declaration)
+            .into_iter()
+            .map(|Location { file, start, end }| {
+                let text = source.text(&file).unwrap()[start.offset..end.offset].to_string();
+                (file, start.line, text)
+            })
             .collect()
     }
 
-    pub fn goto_definition(&self, file: &str, position: Position) -> Option<Position> {
-        let tree = self.trees.get(file)?;
-        // tree.root.nodes
+    #[test]
+    fn references_across_files() {
+        let mut source = Source::new();
+        source.parse(
+            "/ws/types.proto",
+            r#"
+            package test.v1;
+            import "google/protobuf/descriptor.proto";
+            enum Level { LEVEL_UNSPECIFIED = 0; HIGH = 1; }
+            message Rule {
+                optional Level level = 1;
+                repeated Level levels = 2;
+                extensions 100 to 199;
+            }
+            extend google.protobuf.FieldOptions {
+                optional Rule rule = 50000;
+                optional Level priority = 50001;
+            }
+            "#,
+        );
+        source.parse(
+            "/ws/usage.proto",
+            r#"
+            package test.v1;
+            import "types.proto";
+            extend Rule { optional Level extra = 100; }
+            message Request {
+                optional Level current = 1 [default = HIGH, (priority) = HIGH];
+                map<string, Level> levels = 2 [(rule) = {
+                    level: HIGH
+                    levels: [HIGH, LEVEL_UNSPECIFIED]
+                    [test.v1.extra]: HIGH
+                }];
+                optional string name = 3 [(test.v1.rule).level = HIGH];
+            }
+            service Requests { rpc Get(Request) returns (Rule); }
+            "#,
+        );
+
+        let types = "/ws/types.proto";
+        let usage = "/ws/usage.proto";
+        let at = |file: &str, line, text: &str| (file.to_string(), line, text.to_string());
+
+        assert_eq!(
+            references(&source, types, "HIGH", true),
+            vec![
+                at(types, 3, "HIGH"),
+                at(usage, 5, "HIGH"),
+                at(usage, 5, "HIGH"),
+                at(usage, 7, "HIGH"),
+                at(usage, 8, "HIGH"),
+                at(usage, 9, "HIGH"),
+                at(usage, 11, "HIGH"),
+            ]
+        );
+        assert_eq!(
+            references(&source, types, "Level {", false),
+            vec![
+                at(types, 5, "Level"),
+                at(types, 6, "Level"),
+                at(types, 11, "Level"),
+                at(usage, 3, "Level"),
+                at(usage, 5, "Level"),
+                at(usage, 6, "Level"),
+            ]
+        );
+        assert_eq!(
+            references(&source, types, "Rule {", false),
+            vec![
+                at(types, 10, "Rule"),
+                at(usage, 3, "Rule"),
+                at(usage, 13, "Rule"),
+            ]
+        );
+        assert_eq!(
+            references(&source, types, "level =", true),
+            vec![
+                at(types, 5, "level"),
+                at(usage, 7, "level"),
+                at(usage, 11, "level"),
+            ]
+        );
+        assert_eq!(
+            references(&source, types, "rule =", false),
+            vec![at(usage, 6, "(rule)"), at(usage, 11, "(test.v1.rule)")]
+        );
+        assert_eq!(
+            references(&source, usage, "[test.v1.extra]", true),
+            vec![at(usage, 3, "extra"), at(usage, 9, "[test.v1.extra]")]
+        );
+        assert_eq!(
+            references(&source, usage, "Request)", false),
+            vec![at(usage, 13, "Request")]
+        );
+        assert_eq!(references(&source, usage, "string", true), vec![]);
+    }
+
+    #[test]
+    fn references_follow_edits() {
+        let mut source = Source::new();
+        let a = "/ws/a.proto";
+        let b = "/ws/b.proto";
+        source.parse(a, "package p;\nmessage T {}\n");
+        source.parse(
+            b,
+            "package p.q;\nimport \"a.proto\";\nmessage U { T t = 1; }\n",
+        );
+        let at = |line, text: &str| (b.to_string(), line, text.to_string());
+        let offset = |source: &Source, file: &str, text: &str| {
+            source.text(file).unwrap().find(text).unwrap()
+        };
+
+        assert_eq!(references(&source, a, "T {", false), vec![at(2, "T")]);
+
+        // Only moves the references of the edited file.
+        assert!(source.edit(b, 0..0, "// b\n"));
+        assert_eq!(references(&source, a, "T {", false), vec![at(3, "T")]);
+
+        let end = offset(&source, b, "}");
+        assert!(source.edit(b, end..end, "T u = 2; "));
+        assert_eq!(
+            references(&source, a, "T {", false),
+            vec![at(3, "T"), at(3, "T")]
+        );
+
+        // Moving `T` into the package of `b` changes what its names resolve
+        // to.
+        let package = offset(&source, a, ";");
+        assert!(source.edit(a, package..package, ".q"));
+        assert_eq!(
+            references(&source, a, "T {", false),
+            vec![at(3, "T"), at(3, "T")]
+        );
+    }
+
+    #[test]
+    fn edits_replace_the_symbols_of_the_edited_file() {
+        let mut source = Source::new();
+        source.parse("/ws/a.proto", "package p; message A {}");
+        source.parse(
+            "/ws/b.proto",
+            "package p; import \"a.proto\"; message B { A a = 1; }",
+        );
+
+        let text = source.text("/ws/a.proto").unwrap();
+        let offset = text.find("A {").unwrap();
+        assert!(source.edit("/ws/a.proto", offset..offset + 1, "Renamed"));
+
+        let symbols = source.symbols();
+        assert!(symbols.get("p.A").is_empty());
+        assert_eq!(symbols.get("p.Renamed").len(), 1);
+        assert_eq!(symbols.get("p.B").len(), 1);
+        assert_eq!(symbols.get("p").len(), 2);
+        assert_eq!(source.symbol_errors("/ws/b.proto").len(), 1);
+
+        source.parse("/ws/a.proto", "package p; message A {}");
+        assert_eq!(symbols_named(&source, "p.A"), vec!["/ws/a.proto"]);
+        assert!(source.symbol_errors("/ws/b.proto").is_empty());
+
+        assert!(source.remove("/ws/a.proto"));
+        assert!(source.symbols().get("p.A").is_empty());
+        assert_eq!(symbols_named(&source, "p"), vec!["/ws/b.proto"]);
+    }
+
+    fn symbols_named(source: &Source, name: &str) -> Vec<String> {
+        source
+            .symbols()
+            .get(name)
+            .iter()
+            .map(|symbol| symbol.location.file.clone())
+            .collect()
+    }
+
+    #[test]
+    fn parse_all_resolves_imports_between_the_files() {
+        let mut source = Source::new();
+        source.parse("/ws/a.proto", "import \"b.proto\"; message A { B b = 1; }");
+        assert_eq!(source.symbol_errors("/ws/a.proto").len(), 1);
+
+        source.parse_all(vec![
+            (
+                "/ws/b.proto".to_string(),
+                "import \"c.proto\"; message B { C c = 1; }".to_string(),
+            ),
+            ("/ws/c.proto".to_string(), "message C {}".to_string()),
+        ]);
+
+        assert!(source.symbol_errors("/ws/a.proto").is_empty());
+        assert!(source.symbol_errors("/ws/b.proto").is_empty());
+        assert_eq!(
+            goto(&source, "/ws/b.proto", "C c"),
+            Some(("/ws/c.proto".to_string(), "C".to_string()))
+        );
+    }
+
+    #[test]
+    fn loads_imports_from_include_paths() {
+        let dir = std::env::temp_dir().join(format!("protols-source-{}", std::process::id()));
+        let include = dir.join("include");
+        std::fs::create_dir_all(include.join("dep")).unwrap();
+        std::fs::write(
+            include.join("dep/a.proto"),
+            "package dep; import public \"dep/b.proto\"; message A {}",
+        )
+        .unwrap();
+        std::fs::write(include.join("dep/b.proto"), "package dep; message B {}").unwrap();
+
+        let mut source = Source::new();
+        source.set_include_paths(vec![include.clone()]);
+        let file = dir.join("main.proto").to_str().unwrap().to_string();
+        source.parse(
+            &file,
+            "import \"dep/a.proto\"; import weak \"dep/missing.proto\"; message M { dep.A a = 1; dep.B b = 2; }",
+        );
+
+        let b = include.join("dep/b.proto").to_str().unwrap().to_string();
+        assert_eq!(goto(&source, &file, "dep.B").map(|(file, _)| file), Some(b));
+        assert!(source.symbol_errors(&file).is_empty());
+
+        let imports = source.imports(&file);
+        assert_eq!(imports.len(), 2);
+        assert!(imports[0].file.is_some());
+        assert_eq!(imports[1].file, None);
 
-        todo!()
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -1,31 +1,32 @@
 mod ast;
+pub mod cst;
 mod cursor;
+mod incremental;
 mod lexer;
+mod line_index;
 mod parser;
 
-use std::ops::{Add, Sub};
+use std::ops::Add;
 
 pub use ast::*;
-pub use lexer::tokenize;
-pub use parser::{ParseError, ParseResult, Parser};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub use incremental::IncrementalParser;
+pub use lexer::{tokenize, tokenize_at, CommentKind, Keyword, Token, TokenKind};
+pub use line_index::{LineIndex, PositionEncoding};
+pub use parser::{
+    DeclarationResult, ParseError, ParseErrorKind, ParseResult, Parser, RelatedInformation,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Position {
     pub line: usize,
+    /// Column in chars from the start of the line.
     pub column: usize,
+    /// Offset in bytes from the start of the file.
+    pub offset: usize,
 }
 
-impl Sub<usize> for &Position {
-    type Output = Position;
-
-    fn sub(self, rhs: usize) -> Self::Output {
-        Position {
-            line: self.line,
-            column: self.column - rhs,
-        }
-    }
-}
-
+/// Moves a position `rhs` chars to the right on the same line. Only valid for
+/// ASCII text, where chars and bytes are the same width.
 impl Add<usize> for &Position {
     type Output = Position;
 
@@ -33,15 +34,7 @@ impl Add<usize> for &Position {
         Position {
             line: self.line,
             column: self.column + rhs,
-        }
-    }
-}
-
-impl Default for Position {
-    fn default() -> Self {
-        Self {
-            line: Default::default(),
-            column: Default::default(),
+            offset: self.offset + rhs,
         }
     }
 }
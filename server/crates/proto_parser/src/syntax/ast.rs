@@ -1,7 +1,7 @@
 use crate::Position;
 
 use self::{
-    message::{FieldDeclaration, Message},
+    message::{FieldDeclaration, GroupDeclaration, Message},
     option::OptionNode,
     service::ServiceNode,
 };
@@ -10,8 +10,11 @@ use super::lexer::{Keyword, TokenKind};
 
 pub mod message;
 pub mod option;
+mod positions;
 pub mod service;
 
+pub use positions::Positions;
+
 #[derive(Debug, Clone)]
 pub enum RootDeclaration {
     Syntax(SyntaxNode),
@@ -28,8 +31,8 @@ pub enum RootDeclaration {
 impl RootDeclaration {
     pub fn get_name(&self) -> Option<&str> {
         match self {
-            RootDeclaration::Message(message) => Some(&message.name),
-            RootDeclaration::Enum(enum_declaration) => Some(&enum_declaration.name),
+            RootDeclaration::Message(message) => Some(&message.name.value),
+            RootDeclaration::Enum(enum_declaration) => Some(&enum_declaration.name.value),
             _ => None,
         }
     }
@@ -42,21 +45,49 @@ pub struct SyntaxNode {
 
 #[derive(Debug, Clone)]
 pub struct PackageNode {
-    pub package_name: Vec<String>,
+    pub package_name: Node<Vec<String>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ImportNode {
-    pub package_name: String,
+    /// The imported path, without quotes.
+    pub package_name: Node<String>,
     pub modifier: Option<ImportModifier>,
 }
 
-#[derive(Debug, Clone)]
+/// A string literal, possibly made up of several adjacent literals
+/// (`"a" "b"`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral {
+    /// The source text of each adjacent literal, including quotes.
+    pub raw: Vec<String>,
+    /// The concatenated value with escape sequences decoded. Escapes can
+    /// produce any bytes, so this isn't necessarily UTF-8.
+    pub value: Vec<u8>,
+}
+
+impl StringLiteral {
+    /// The value as a string, for literals that must be valid UTF-8.
+    pub fn as_str(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeName {
     pub absolute: bool,
     pub parts: Vec<String>,
 }
 
+impl std::fmt::Display for TypeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.absolute {
+            f.write_str(".")?;
+        }
+        f.write_str(&self.parts.join("."))
+    }
+}
+
 impl From<Vec<String>> for TypeName {
     fn from(value: Vec<String>) -> Self {
         Self {
@@ -68,7 +99,10 @@ impl From<Vec<String>> for TypeName {
 
 impl From<String> for TypeName {
     fn from(value: String) -> Self {
-        let absolute = value.starts_with('.');
+        let (absolute, value) = match value.strip_prefix('.') {
+            Some(value) => (true, value),
+            None => (false, value.as_str()),
+        };
         let parts = value.split('.').map(|s| s.to_string()).collect();
 
         Self { parts, absolute }
@@ -95,15 +129,16 @@ pub enum TagEnd {
 
 #[derive(Debug, Clone)]
 pub struct EnumNode {
-    pub name: String,
+    pub name: Node<String>,
     pub elements: Vec<Node<EnumElement>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum EnumElement {
     EnumValue {
-        name: String,
-        number: i32,
+        name: Node<String>,
+        /// Out of range numbers are kept, and reported by the parser.
+        number: Node<i64>,
         options: Vec<Node<OptionNode>>,
     },
     EnumReserved(Reserved),
@@ -113,13 +148,14 @@ pub enum EnumElement {
 
 #[derive(Debug, Clone)]
 pub struct ExtensionNode {
-    pub extendee: Vec<String>,
+    pub extendee: Node<TypeName>,
     pub elements: Vec<Node<ExtensionElement>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum ExtensionElement {
     Field(FieldDeclaration),
+    Group(GroupDeclaration),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -138,6 +174,26 @@ pub enum MapKeyType {
     String,
 }
 
+impl MapKeyType {
+    /// The keyword the type is written as.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MapKeyType::Int32 => "int32",
+            MapKeyType::Int64 => "int64",
+            MapKeyType::Uint32 => "uint32",
+            MapKeyType::Uint64 => "uint64",
+            MapKeyType::Sint32 => "sint32",
+            MapKeyType::Sint64 => "sint64",
+            MapKeyType::Fixed32 => "fixed32",
+            MapKeyType::Fixed64 => "fixed64",
+            MapKeyType::Sfixed32 => "sfixed32",
+            MapKeyType::Sfixed64 => "sfixed64",
+            MapKeyType::Bool => "bool",
+            MapKeyType::String => "string",
+        }
+    }
+}
+
 impl TryFrom<TokenKind> for MapKeyType {
     type Error = String;
 
@@ -179,6 +235,29 @@ pub enum ScalarType {
     Bytes,
 }
 
+impl ScalarType {
+    /// The keyword the type is written as.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScalarType::Double => "double",
+            ScalarType::Float => "float",
+            ScalarType::Int32 => "int32",
+            ScalarType::Int64 => "int64",
+            ScalarType::Uint32 => "uint32",
+            ScalarType::Uint64 => "uint64",
+            ScalarType::Sint32 => "sint32",
+            ScalarType::Sint64 => "sint64",
+            ScalarType::Fixed32 => "fixed32",
+            ScalarType::Fixed64 => "fixed64",
+            ScalarType::Sfixed32 => "sfixed32",
+            ScalarType::Sfixed64 => "sfixed64",
+            ScalarType::Bool => "bool",
+            ScalarType::String => "string",
+            ScalarType::Bytes => "bytes",
+        }
+    }
+}
+
 impl TryFrom<TokenKind> for ScalarType {
     type Error = String;
 
@@ -236,9 +315,17 @@ impl TryFrom<&TokenKind> for ScalarType {
 pub enum SyntaxType {
     Proto2,
     Proto3,
+    /// A file starting with `edition = "...";` instead of a `syntax` statement.
+    Edition(Edition),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Edition {
+    Edition2023,
+    Edition2024,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ImportModifier {
     Weak,
     Public,
@@ -263,6 +350,25 @@ impl Root {
     }
 }
 
+/// Comments attached to a declaration, following the same rules protoc
+/// uses for `SourceCodeInfo.Location`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    pub leading: Option<String>,
+    pub trailing: Option<String>,
+    pub leading_detached: Vec<String>,
+}
+
+impl From<Comments> for Option<Box<Comments>> {
+    fn from(value: Comments) -> Self {
+        if value == Comments::default() {
+            None
+        } else {
+            Some(Box::new(value))
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Node<T>
 where
@@ -271,6 +377,7 @@ where
     pub value: T,
     pub start: Position,
     pub end: Position,
+    pub comments: Option<Box<Comments>>,
 }
 
 impl<T> Node<T>
@@ -278,6 +385,24 @@ where
     T: Clone,
 {
     pub fn new(value: T, start: Position, end: Position) -> Self {
-        Self { value, start, end }
+        Self {
+            value,
+            start,
+            end,
+            comments: None,
+        }
+    }
+
+    pub fn with_comments(mut self, comments: impl Into<Option<Box<Comments>>>) -> Self {
+        self.comments = comments.into();
+        self
+    }
+
+    pub fn leading_comment(&self) -> Option<&str> {
+        self.comments.as_ref()?.leading.as_deref()
+    }
+
+    pub fn trailing_comment(&self) -> Option<&str> {
+        self.comments.as_ref()?.trailing.as_deref()
     }
 }
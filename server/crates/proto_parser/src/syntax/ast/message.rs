@@ -1,12 +1,13 @@
 use crate::syntax::lexer::{Keyword, TokenKind};
 
 use super::{
-    option::OptionNode, EnumNode, ExtensionNode, MapKeyType, Node, Reserved, ScalarType, TypeName,
+    option::OptionNode, EnumNode, ExtensionNode, MapKeyType, Node, Reserved, ScalarType, TagRange,
+    TypeName,
 };
 
 #[derive(Debug, Clone)]
 pub struct Message {
-    pub name: String,
+    pub name: Node<String>,
     pub elements: Vec<Node<MessageElement>>,
 }
 
@@ -28,19 +29,39 @@ pub struct MapFieldDeclaration {
     pub options: Vec<Node<OptionNode>>,
 }
 
+/// A proto2 group: a field and a nested message type declared together, as in
+/// `repeated group Result = 1 { ... }`.
+#[derive(Debug, Clone)]
+pub struct GroupDeclaration {
+    pub cardinality: Node<FieldCardinality>,
+    pub name: Node<String>,
+    pub number: Node<u32>,
+    pub options: Vec<Node<OptionNode>>,
+    pub elements: Vec<Node<MessageElement>>,
+}
+
 #[derive(Debug, Clone)]
 pub enum MessageElement {
     Field(FieldDeclaration),
     MapField(MapFieldDeclaration),
+    Group(GroupDeclaration),
     OneOf(OneofDeclaration),
     Option(OptionNode),
     Reserved(Reserved),
     Message(Box<Message>),
     Enum(EnumNode),
     Extension(ExtensionNode),
+    ExtensionRange(ExtensionRange),
     Empty,
 }
 
+/// Field numbers reserved for extensions, as in `extensions 100 to max;`.
+#[derive(Debug, Clone)]
+pub struct ExtensionRange {
+    pub ranges: Vec<Node<TagRange>>,
+    pub options: Vec<Node<OptionNode>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct OneofDeclaration {
     pub name: Node<String>,
@@ -67,7 +88,7 @@ impl TryFrom<TokenKind> for FieldCardinality {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FieldType {
     ScalarType(ScalarType),
     TypeName(TypeName),
@@ -77,6 +98,7 @@ pub enum FieldType {
 pub enum OneofElement {
     Option(OptionNode),
     OneofField(OneofField),
+    Group(GroupDeclaration),
 }
 
 #[derive(Debug, Clone)]
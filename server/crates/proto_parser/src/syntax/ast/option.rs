@@ -1,4 +1,4 @@
-use super::{Node, TypeName};
+use super::{Node, StringLiteral, TypeName};
 
 #[derive(Debug, Clone)]
 pub struct OptionNode {
@@ -10,12 +10,12 @@ pub type OptionName = Vec<Node<OptionNamePart>>;
 
 #[derive(Debug, Clone)]
 pub enum OptionValue {
-    StringLiteral(String),
+    StringLiteral(StringLiteral),
     UintLiteral(String),
     IntLiteral(String),
     FloatLiteral(String),
     Identifier(String),
-    MessageLiteral(String),
+    MessageLiteral(MessageLiteral),
 }
 
 #[derive(Debug, Clone)]
@@ -23,3 +23,37 @@ pub enum OptionNamePart {
     SimpleName(String),
     ExtensionName(TypeName),
 }
+
+/// An aggregate option value written in protobuf text format, e.g.
+/// `{ name: "x" nested { a: 1 } list: [1, 2] }`.
+#[derive(Debug, Clone)]
+pub struct MessageLiteral {
+    pub fields: Vec<Node<MessageLiteralField>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageLiteralField {
+    pub name: Node<MessageLiteralFieldName>,
+    pub value: Node<MessageLiteralValue>,
+}
+
+#[derive(Debug, Clone)]
+pub enum MessageLiteralFieldName {
+    /// A regular field, `name: ...`.
+    Simple(String),
+    /// An extension field, `[pkg.ext]: ...`.
+    Extension(TypeName),
+    /// An expanded `google.protobuf.Any`, `[type.googleapis.com/pkg.Type] { ... }`.
+    AnyTypeUrl { domain: String, type_name: TypeName },
+}
+
+#[derive(Debug, Clone)]
+pub enum MessageLiteralValue {
+    StringLiteral(StringLiteral),
+    UintLiteral(String),
+    IntLiteral(String),
+    FloatLiteral(String),
+    Identifier(String),
+    Message(MessageLiteral),
+    List(Vec<Node<MessageLiteralValue>>),
+}
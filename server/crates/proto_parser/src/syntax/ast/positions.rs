@@ -0,0 +1,224 @@
+use crate::Position;
+
+use super::{
+    message::{
+        ExtensionRange, FieldCardinality, FieldDeclaration, FieldType, GroupDeclaration,
+        MapFieldDeclaration, Message, MessageElement, OneofDeclaration, OneofElement, OneofField,
+    },
+    option::{
+        MessageLiteral, MessageLiteralField, MessageLiteralFieldName, MessageLiteralValue,
+        OptionNamePart, OptionNode, OptionValue,
+    },
+    service::{MessageType, MethodElement, MethodNode, ServiceElement, ServiceNode},
+    EnumElement, EnumNode, ExtensionElement, ExtensionNode, ImportNode, MapKeyType, Node,
+    PackageNode, Reserved, RootDeclaration, StringLiteral, SyntaxNode, TagEnd, TagRange, TypeName,
+};
+
+/// Gives mutable access to every position in an AST node, so nodes can be
+/// moved around in a file without parsing them again.
+pub trait Positions {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position));
+}
+
+impl<T: Positions + Clone> Positions for Node<T> {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        f(&mut self.start);
+        f(&mut self.end);
+        self.value.for_each_position(f);
+    }
+}
+
+impl<T: Positions> Positions for Vec<T> {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        for item in self {
+            item.for_each_position(f);
+        }
+    }
+}
+
+impl<T: Positions> Positions for Option<T> {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        if let Some(value) = self {
+            value.for_each_position(f);
+        }
+    }
+}
+
+impl<T: Positions> Positions for Box<T> {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        self.as_mut().for_each_position(f);
+    }
+}
+
+/// Types without positions of their own.
+macro_rules! leaves {
+    ($($ty:ty),* $(,)?) => {$(
+        impl Positions for $ty {
+            fn for_each_position(&mut self, _: &mut impl FnMut(&mut Position)) {}
+        }
+    )*};
+}
+
+leaves!(
+    String,
+    u32,
+    i64,
+    SyntaxNode,
+    TypeName,
+    StringLiteral,
+    TagEnd,
+    MapKeyType,
+    FieldCardinality,
+    FieldType,
+    OptionNamePart,
+    MessageLiteralFieldName,
+);
+
+macro_rules! fields {
+    ($($ty:ty { $($field:ident),* })*) => {$(
+        impl Positions for $ty {
+            fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+                $(self.$field.for_each_position(f);)*
+            }
+        }
+    )*};
+}
+
+fields!(
+    PackageNode { package_name }
+    ImportNode { package_name }
+    TagRange { start, end }
+    EnumNode { name, elements }
+    ExtensionNode { extendee, elements }
+    Message { name, elements }
+    FieldDeclaration { cardinality, type_name, name, number, options }
+    MapFieldDeclaration { key_type, value_type, name, number, options }
+    GroupDeclaration { cardinality, name, number, options, elements }
+    ExtensionRange { ranges, options }
+    OneofDeclaration { name, elements }
+    OneofField { type_name, name, number, options }
+    ServiceNode { name, elements }
+    MethodNode { name, input_type, output_type, elements }
+    MessageType { type_name }
+    OptionNode { name, value }
+    MessageLiteral { fields }
+    MessageLiteralField { name, value }
+);
+
+impl Positions for RootDeclaration {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            RootDeclaration::Syntax(node) => node.for_each_position(f),
+            RootDeclaration::Package(node) => node.for_each_position(f),
+            RootDeclaration::Import(node) => node.for_each_position(f),
+            RootDeclaration::Message(node) => node.for_each_position(f),
+            RootDeclaration::Service(node) => node.for_each_position(f),
+            RootDeclaration::Option(node) => node.for_each_position(f),
+            RootDeclaration::Enum(node) => node.for_each_position(f),
+            RootDeclaration::Extension(node) => node.for_each_position(f),
+            RootDeclaration::Empty => {}
+        }
+    }
+}
+
+impl Positions for Reserved {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            Reserved::TagRanges(ranges) => ranges.for_each_position(f),
+            Reserved::Names(names) => names.for_each_position(f),
+        }
+    }
+}
+
+impl Positions for EnumElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            EnumElement::EnumValue {
+                name,
+                number,
+                options,
+            } => {
+                name.for_each_position(f);
+                number.for_each_position(f);
+                options.for_each_position(f);
+            }
+            EnumElement::EnumReserved(reserved) => reserved.for_each_position(f),
+            EnumElement::EnumOption(option) => option.for_each_position(f),
+            EnumElement::Empty => {}
+        }
+    }
+}
+
+impl Positions for ExtensionElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            ExtensionElement::Field(field) => field.for_each_position(f),
+            ExtensionElement::Group(group) => group.for_each_position(f),
+        }
+    }
+}
+
+impl Positions for MessageElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            MessageElement::Field(field) => field.for_each_position(f),
+            MessageElement::MapField(field) => field.for_each_position(f),
+            MessageElement::Group(group) => group.for_each_position(f),
+            MessageElement::OneOf(oneof) => oneof.for_each_position(f),
+            MessageElement::Option(option) => option.for_each_position(f),
+            MessageElement::Reserved(reserved) => reserved.for_each_position(f),
+            MessageElement::Message(message) => message.for_each_position(f),
+            MessageElement::Enum(enum_node) => enum_node.for_each_position(f),
+            MessageElement::Extension(extension) => extension.for_each_position(f),
+            MessageElement::ExtensionRange(range) => range.for_each_position(f),
+            MessageElement::Empty => {}
+        }
+    }
+}
+
+impl Positions for OneofElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            OneofElement::Option(option) => option.for_each_position(f),
+            OneofElement::OneofField(field) => field.for_each_position(f),
+            OneofElement::Group(group) => group.for_each_position(f),
+        }
+    }
+}
+
+impl Positions for ServiceElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            ServiceElement::Option(option) => option.for_each_position(f),
+            ServiceElement::Method(method) => method.for_each_position(f),
+            ServiceElement::Empty => {}
+        }
+    }
+}
+
+impl Positions for MethodElement {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            MethodElement::Option(option) => option.for_each_position(f),
+            MethodElement::Empty => {}
+        }
+    }
+}
+
+impl Positions for OptionValue {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        if let OptionValue::MessageLiteral(literal) = self {
+            literal.for_each_position(f);
+        }
+    }
+}
+
+impl Positions for MessageLiteralValue {
+    fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+        match self {
+            MessageLiteralValue::Message(literal) => literal.for_each_position(f),
+            MessageLiteralValue::List(values) => values.for_each_position(f),
+            _ => {}
+        }
+    }
+}
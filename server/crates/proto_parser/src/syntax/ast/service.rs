@@ -2,20 +2,20 @@ use super::{option::OptionNode, Node, TypeName};
 
 #[derive(Debug, Clone)]
 pub struct ServiceNode {
-    pub name: String,
+    pub name: Node<String>,
     pub elements: Vec<Node<ServiceElement>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum ServiceElement {
     Option(OptionNode),
-    Method(MethodNode),
+    Method(Box<MethodNode>),
     Empty,
 }
 
 #[derive(Debug, Clone)]
 pub struct MethodNode {
-    pub name: String,
+    pub name: Node<String>,
     pub input_type: Node<MessageType>,
     pub output_type: Node<MessageType>,
     pub elements: Vec<Node<MethodElement>>,
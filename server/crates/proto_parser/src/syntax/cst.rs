@@ -0,0 +1,507 @@
+//! A lossless concrete syntax tree.
+//!
+//! Unlike the AST, the CST keeps every char of the input, including
+//! whitespace, comments and tokens the parser could not make sense of, so the
+//! original text can always be rebuilt from it. Statements are grouped into
+//! nodes by their leading keyword and nested blocks, which is enough structure
+//! for formatting and text edits; the AST is produced from it by running the
+//! parser over the text of each top-level declaration.
+
+use rowan::{GreenNode, TextRange};
+
+use super::{
+    ast::Root,
+    cursor::EOF_CHAR,
+    lexer::tokenize_at,
+    line_index::{LineIndex, PositionEncoding},
+    parser::{DeclarationResult, ParseResult, Parser},
+    tokenize, Position,
+};
+
+mod builder;
+
+pub(crate) use builder::build;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum SyntaxKind {
+    // Tokens.
+    Whitespace,
+    NewLine,
+    Comment,
+    Identifier,
+    Keyword,
+    IntLiteral,
+    FloatLiteral,
+    String,
+    SemiColon,
+    Comma,
+    Dot,
+    Slash,
+    Colon,
+    Equals,
+    Minus,
+    Plus,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    LAngle,
+    RAngle,
+    Unknown,
+
+    // Nodes.
+    Root,
+    Syntax,
+    Package,
+    Import,
+    Option,
+    Message,
+    Enum,
+    Service,
+    Extend,
+    Field,
+    MapField,
+    Group,
+    Oneof,
+    EnumValue,
+    Reserved,
+    ExtensionRange,
+    Rpc,
+    Empty,
+    /// A statement that is not valid where it appears.
+    Error,
+}
+
+const KINDS: [SyntaxKind; SyntaxKind::Error as usize + 1] = [
+    SyntaxKind::Whitespace,
+    SyntaxKind::NewLine,
+    SyntaxKind::Comment,
+    SyntaxKind::Identifier,
+    SyntaxKind::Keyword,
+    SyntaxKind::IntLiteral,
+    SyntaxKind::FloatLiteral,
+    SyntaxKind::String,
+    SyntaxKind::SemiColon,
+    SyntaxKind::Comma,
+    SyntaxKind::Dot,
+    SyntaxKind::Slash,
+    SyntaxKind::Colon,
+    SyntaxKind::Equals,
+    SyntaxKind::Minus,
+    SyntaxKind::Plus,
+    SyntaxKind::LParen,
+    SyntaxKind::RParen,
+    SyntaxKind::LBrace,
+    SyntaxKind::RBrace,
+    SyntaxKind::LBracket,
+    SyntaxKind::RBracket,
+    SyntaxKind::LAngle,
+    SyntaxKind::RAngle,
+    SyntaxKind::Unknown,
+    SyntaxKind::Root,
+    SyntaxKind::Syntax,
+    SyntaxKind::Package,
+    SyntaxKind::Import,
+    SyntaxKind::Option,
+    SyntaxKind::Message,
+    SyntaxKind::Enum,
+    SyntaxKind::Service,
+    SyntaxKind::Extend,
+    SyntaxKind::Field,
+    SyntaxKind::MapField,
+    SyntaxKind::Group,
+    SyntaxKind::Oneof,
+    SyntaxKind::EnumValue,
+    SyntaxKind::Reserved,
+    SyntaxKind::ExtensionRange,
+    SyntaxKind::Rpc,
+    SyntaxKind::Empty,
+    SyntaxKind::Error,
+];
+
+impl SyntaxKind {
+    /// Whitespace, newlines and comments.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::NewLine | SyntaxKind::Comment
+        )
+    }
+}
+
+impl From<SyntaxKind> for rowan::SyntaxKind {
+    fn from(kind: SyntaxKind) -> Self {
+        Self(kind as u16)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtoLanguage {}
+
+impl rowan::Language for ProtoLanguage {
+    type Kind = SyntaxKind;
+
+    fn kind_from_raw(raw: rowan::SyntaxKind) -> Self::Kind {
+        KINDS[raw.0 as usize]
+    }
+
+    fn kind_to_raw(kind: Self::Kind) -> rowan::SyntaxKind {
+        kind.into()
+    }
+}
+
+pub type CstNode = rowan::SyntaxNode<ProtoLanguage>;
+pub type CstToken = rowan::SyntaxToken<ProtoLanguage>;
+pub type CstElement = rowan::SyntaxElement<ProtoLanguage>;
+
+/// The concrete syntax tree of a file along with its text.
+#[derive(Debug, Clone)]
+pub struct Cst {
+    green: GreenNode,
+    text: std::string::String,
+    line_index: LineIndex,
+}
+
+impl Cst {
+    pub fn parse(text: &str) -> Self {
+        let (green, _) = builder::build(text);
+        Self::new(green, text.to_string())
+    }
+
+    /// A tree for `text` from an already built green node.
+    pub(crate) fn new(green: GreenNode, text: std::string::String) -> Self {
+        let line_index = LineIndex::new(&text);
+
+        Self {
+            green,
+            text,
+            line_index,
+        }
+    }
+
+    /// The root node of the tree. Nodes are cheap to create but can't be sent
+    /// across threads, unlike the `Cst` itself.
+    pub fn syntax(&self) -> CstNode {
+        CstNode::new_root(self.green.clone())
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.green
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        &self.line_index
+    }
+
+    /// The top-level statements of the file.
+    pub fn declarations(&self) -> impl Iterator<Item = Declaration> {
+        self.syntax().children().map(Declaration)
+    }
+
+    /// Builds the AST of a single top-level declaration.
+    pub fn lower(&self, declaration: &Declaration) -> LoweredDeclaration {
+        let node = declaration.syntax();
+        let prev = node
+            .first_token()
+            .and_then(|token| significant(token, CstToken::prev_token));
+        let next = node
+            .last_token()
+            .and_then(|token| significant(token, CstToken::next_token));
+
+        // The parser also looks at the comments around the declaration and
+        // at the token after it to attach comments.
+        let from = prev
+            .as_ref()
+            .map(|token| usize::from(token.text_range().end()))
+            .unwrap_or_default();
+        let to = next
+            .as_ref()
+            .map(|token| usize::from(token.text_range().end()))
+            .unwrap_or(self.text.len());
+
+        let tokens = tokenize_at(&self.text[from..to], self.position(from));
+        let mut parser = Parser::new(tokens);
+        if let Some(prev) = prev {
+            let (line, _) = self
+                .line_index
+                .line_column(prev.text_range().start().into(), PositionEncoding::Utf32);
+            parser = parser.after_line(line);
+        }
+
+        let result = parser.parse_declaration();
+        let exact = result.end.offset == usize::from(node.text_range().end());
+
+        LoweredDeclaration { result, exact }
+    }
+
+    /// Builds the AST of the whole file. The result is the same as parsing
+    /// the text with `Parser::parse`.
+    pub fn to_ast(&self, file_name: &str) -> ParseResult {
+        match self.lower_all(file_name) {
+            Some((result, _)) => result,
+            None => Parser::new(tokenize(&self.text)).parse(file_name),
+        }
+    }
+
+    /// Lowers each top-level declaration on its own, also returning how many
+    /// AST nodes and errors each one produced. Returns `None` when the file
+    /// can only be parsed as a whole.
+    pub(crate) fn lower_all(&self, file_name: &str) -> Option<(ParseResult, Vec<(usize, usize)>)> {
+        // A NUL char ends the parse early, which per-declaration lowering
+        // can't reproduce. Without declarations the parser still reports
+        // errors in comments, so it's simplest to run it.
+        if self.text.contains(EOF_CHAR) {
+            return None;
+        }
+
+        let declarations: Vec<_> = self.declarations().collect();
+        if declarations.is_empty() {
+            return None;
+        }
+
+        let mut root = Root::new(file_name.to_string());
+        let mut errors = Vec::new();
+        let mut counts = Vec::with_capacity(declarations.len());
+        for declaration in &declarations {
+            let lowered = self.lower(declaration);
+            if !lowered.exact {
+                return None;
+            }
+
+            let DeclarationResult {
+                node,
+                errors: node_errors,
+                ..
+            } = lowered.result;
+            counts.push((usize::from(node.is_some()), node_errors.len()));
+            root.nodes.extend(node);
+            errors.extend(node_errors);
+        }
+
+        Some((ParseResult { root, errors }, counts))
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        let (line, column) = self.line_index.line_column(offset, PositionEncoding::Utf32);
+
+        Position {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
+/// The first non-trivia token in the direction of `step`.
+fn significant(token: CstToken, step: impl Fn(&CstToken) -> Option<CstToken>) -> Option<CstToken> {
+    let mut token = step(&token)?;
+    while token.kind().is_trivia() {
+        token = step(&token)?;
+    }
+
+    Some(token)
+}
+
+/// The AST of a single top-level declaration.
+#[derive(Debug)]
+pub struct LoweredDeclaration {
+    pub result: DeclarationResult,
+    /// Whether the parser consumed exactly the tokens of the declaration.
+    /// When error recovery skips past its end, the declaration can only be
+    /// parsed together with the rest of the file.
+    pub exact: bool,
+}
+
+/// A top-level statement of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Declaration(CstNode);
+
+impl Declaration {
+    pub fn syntax(&self) -> &CstNode {
+        &self.0
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.kind()
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        self.0.text_range()
+    }
+
+    /// The name of a message, enum or service.
+    pub fn name(&self) -> Option<CstToken> {
+        if !matches!(
+            self.kind(),
+            SyntaxKind::Message | SyntaxKind::Enum | SyntaxKind::Service
+        ) {
+            return None;
+        }
+
+        self.0
+            .children_with_tokens()
+            .filter_map(CstElement::into_token)
+            .filter(|token| !token.kind().is_trivia())
+            .nth(1)
+            .filter(|token| matches!(token.kind(), SyntaxKind::Identifier | SyntaxKind::Keyword))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::syntax::{tokenize, Parser};
+
+    use super::{Cst, CstNode, SyntaxKind};
+
+    const EXAMPLE: &str = include_str!("../../../../examples/example.proto");
+
+    fn kinds(node: &CstNode) -> Vec<SyntaxKind> {
+        node.children().map(|child| child.kind()).collect()
+    }
+
+    fn assert_same_ast(input: &str) {
+        let expected = Parser::new(tokenize(input)).parse("test.proto");
+        let actual = Cst::parse(input).to_ast("test.proto");
+
+        assert_eq!(format!("{actual:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn round_trips_text() {
+        for input in [
+            EXAMPLE,
+            "syntax = \"proto3\";\r\n\r\nmessage A {\r\n  int32 a = 1; // ok\r\n}\r\n",
+            "message Ünïcode { string 名前 = 1; } \t \u{a0}\n",
+            "message A { int32 a = } } ; \"unterminated\n/* block",
+            "\r  \0 x",
+        ] {
+            assert_eq!(Cst::parse(input).syntax().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn groups_statements() {
+        let cst = Cst::parse(
+            r#"
+            syntax = "proto3";
+            package foo.bar;
+            import "other.proto";
+            option (my_option) = { a: 1; b: [2, 3] };
+
+            // A message.
+            message A {
+                int32 a = 1 [(x) = { y: 2 }];
+                map<string, A> b = 2;
+                oneof c { string d = 3; }
+                message B { reserved 1 to 3; }
+                optional group G = 4 { int32 e = 5; }
+            }
+            enum E { option allow_alias = true; E_UNSET = 0; }
+            service S { rpc Get(A) returns (A) { option deprecated = true; } }
+            bogus;
+            }
+            "#,
+        );
+        let root = cst.syntax();
+
+        assert_eq!(
+            kinds(&root),
+            vec![
+                SyntaxKind::Syntax,
+                SyntaxKind::Package,
+                SyntaxKind::Import,
+                SyntaxKind::Option,
+                SyntaxKind::Message,
+                SyntaxKind::Enum,
+                SyntaxKind::Service,
+                SyntaxKind::Error,
+                SyntaxKind::Error,
+            ]
+        );
+
+        let message = root.children().nth(4).unwrap();
+        assert_eq!(
+            kinds(&message),
+            vec![
+                SyntaxKind::Field,
+                SyntaxKind::MapField,
+                SyntaxKind::Oneof,
+                SyntaxKind::Message,
+                SyntaxKind::Group,
+            ]
+        );
+        // The comment before the message belongs to the root.
+        assert!(message.text().to_string().starts_with("message A"));
+        assert!(message.text().to_string().ends_with('}'));
+
+        let enum_node = root.children().nth(5).unwrap();
+        assert_eq!(
+            kinds(&enum_node),
+            vec![SyntaxKind::Option, SyntaxKind::EnumValue]
+        );
+
+        let service = root.children().nth(6).unwrap();
+        let rpc = service.children().next().unwrap();
+        assert_eq!(rpc.kind(), SyntaxKind::Rpc);
+        assert_eq!(kinds(&rpc), vec![SyntaxKind::Option]);
+
+        let names: Vec<_> = cst
+            .declarations()
+            .filter_map(|declaration| declaration.name())
+            .map(|name| name.text().to_string())
+            .collect();
+        assert_eq!(names, vec!["A", "E", "S"]);
+    }
+
+    #[test]
+    fn unterminated_block_ends_at_eof() {
+        let input = "message A {\n  message B {\n    int32 a = 1;\n";
+        let cst = Cst::parse(input);
+        let root = cst.syntax();
+
+        assert_eq!(kinds(&root), vec![SyntaxKind::Message]);
+        assert_eq!(root.to_string(), input);
+    }
+
+    #[test]
+    fn to_ast_matches_parser() {
+        assert_same_ast(EXAMPLE);
+        assert_same_ast("");
+        assert_same_ast("// just a comment\n/* unterminated");
+        assert_same_ast("message A { int32 a = }\n; message B {} } enum");
+    }
+
+    #[test]
+    fn to_ast_lowers_each_declaration() {
+        let cst = Cst::parse(EXAMPLE);
+
+        assert!(cst
+            .declarations()
+            .all(|declaration| cst.lower(&declaration).exact));
+    }
+
+    proptest! {
+        #[test]
+        fn any_text_round_trips(input in ".*") {
+            prop_assert_eq!(Cst::parse(&input).syntax().to_string(), input);
+        }
+
+        #[test]
+        fn to_ast_matches_parser_with_deleted_span(start in 0..EXAMPLE.len(), len in 0..64usize) {
+            let end = (start + len).min(EXAMPLE.len());
+            if EXAMPLE.is_char_boundary(start) && EXAMPLE.is_char_boundary(end) {
+                let input = format!("{}{}", &EXAMPLE[..start], &EXAMPLE[end..]);
+                assert_same_ast(&input);
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::ops::Range;
+
+use rowan::{GreenNode, GreenNodeBuilder};
+
+use crate::syntax::lexer::{tokenize, TokenKind};
+
+use super::SyntaxKind;
+
+/// The kind of block a statement appears in, which decides how statements
+/// are classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Root,
+    Message,
+    Enum,
+    Service,
+    Rpc,
+    Extend,
+    Oneof,
+}
+
+/// Builds the tree of `text`, and whether a statement or block was cut off by
+/// the end of the text.
+pub(crate) fn build(text: &str) -> (GreenNode, bool) {
+    let mut builder = Builder {
+        text,
+        tokens: tokens(text),
+        pos: 0,
+        truncated: false,
+        builder: GreenNodeBuilder::new(),
+    };
+
+    builder.builder.start_node(SyntaxKind::Root.into());
+    builder.statements(Block::Root);
+    builder.trivia();
+    builder.builder.finish_node();
+
+    (builder.builder.finish(), builder.truncated)
+}
+
+/// Tokenizes `text`, filling the gaps the lexer skips over with whitespace
+/// tokens so that the tokens cover the whole text.
+fn tokens(text: &str) -> Vec<(SyntaxKind, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut end = 0;
+
+    for token in tokenize(text) {
+        if token.kind == TokenKind::Eof {
+            continue;
+        }
+
+        let start = token.position.offset;
+        if start > end {
+            tokens.push((SyntaxKind::Whitespace, end..start));
+        }
+
+        end = start + token.value.len();
+        tokens.push((kind(token.kind), start..end));
+    }
+
+    if end < text.len() {
+        tokens.push((SyntaxKind::Whitespace, end..text.len()));
+    }
+
+    tokens
+}
+
+fn kind(kind: TokenKind) -> SyntaxKind {
+    match kind {
+        TokenKind::IntLiteral => SyntaxKind::IntLiteral,
+        TokenKind::FloatLiteral => SyntaxKind::FloatLiteral,
+        TokenKind::String | TokenKind::UnterminatedString => SyntaxKind::String,
+        TokenKind::SemiColon => SyntaxKind::SemiColon,
+        TokenKind::Comma => SyntaxKind::Comma,
+        TokenKind::Dot => SyntaxKind::Dot,
+        TokenKind::Slash => SyntaxKind::Slash,
+        TokenKind::Colon => SyntaxKind::Colon,
+        TokenKind::Equals => SyntaxKind::Equals,
+        TokenKind::Minus => SyntaxKind::Minus,
+        TokenKind::Plus => SyntaxKind::Plus,
+        TokenKind::LParen => SyntaxKind::LParen,
+        TokenKind::RParen => SyntaxKind::RParen,
+        TokenKind::LBrace => SyntaxKind::LBrace,
+        TokenKind::RBrace => SyntaxKind::RBrace,
+        TokenKind::LBracket => SyntaxKind::LBracket,
+        TokenKind::RBracket => SyntaxKind::RBracket,
+        TokenKind::LAngle => SyntaxKind::LAngle,
+        TokenKind::RAngle => SyntaxKind::RAngle,
+        TokenKind::Identifier => SyntaxKind::Identifier,
+        TokenKind::Keyword(_) => SyntaxKind::Keyword,
+        TokenKind::Comment(_) => SyntaxKind::Comment,
+        TokenKind::NewLine => SyntaxKind::NewLine,
+        TokenKind::Unknown | TokenKind::Eof => SyntaxKind::Unknown,
+    }
+}
+
+struct Builder<'a> {
+    text: &'a str,
+    tokens: Vec<(SyntaxKind, Range<usize>)>,
+    pos: usize,
+    truncated: bool,
+    builder: GreenNodeBuilder<'static>,
+}
+
+impl Builder<'_> {
+    /// The nth significant token from the current position.
+    fn nth(&self, n: usize) -> Option<(SyntaxKind, &str)> {
+        self.tokens[self.pos..]
+            .iter()
+            .filter(|(kind, _)| !kind.is_trivia())
+            .nth(n)
+            .map(|(kind, range)| (*kind, &self.text[range.clone()]))
+    }
+
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.nth(0).map(|(kind, _)| kind)
+    }
+
+    fn trivia(&mut self) {
+        while let Some((kind, range)) = self.tokens.get(self.pos) {
+            if !kind.is_trivia() {
+                break;
+            }
+
+            self.builder
+                .token((*kind).into(), &self.text[range.clone()]);
+            self.pos += 1;
+        }
+    }
+
+    /// Adds the next significant token, and the trivia before it, to the
+    /// current node.
+    fn bump(&mut self) {
+        self.trivia();
+        if let Some((kind, range)) = self.tokens.get(self.pos) {
+            self.builder
+                .token((*kind).into(), &self.text[range.clone()]);
+            self.pos += 1;
+        }
+    }
+
+    /// Parses statements up to the `}` closing the block or the end of the
+    /// input. Trivia after the last statement belongs to the block.
+    fn statements(&mut self, block: Block) {
+        loop {
+            match self.peek() {
+                None => {
+                    self.truncated |= block != Block::Root;
+                    return;
+                }
+                Some(SyntaxKind::RBrace) if block != Block::Root => return,
+                Some(SyntaxKind::RBrace) => {
+                    self.trivia();
+                    self.builder.start_node(SyntaxKind::Error.into());
+                    self.bump();
+                    self.builder.finish_node();
+                }
+                Some(_) => {
+                    self.trivia();
+                    self.statement(block);
+                }
+            }
+        }
+    }
+
+    /// Parses a statement up to its `;` or the end of its body. Braces are
+    /// matched, so aggregate option values stay inside their statement.
+    fn statement(&mut self, block: Block) {
+        let kind = self.classify(block);
+        let body = match kind {
+            SyntaxKind::Message | SyntaxKind::Group => Some(Block::Message),
+            SyntaxKind::Enum => Some(Block::Enum),
+            SyntaxKind::Service => Some(Block::Service),
+            SyntaxKind::Rpc => Some(Block::Rpc),
+            SyntaxKind::Extend => Some(Block::Extend),
+            SyntaxKind::Oneof => Some(Block::Oneof),
+            _ => None,
+        };
+
+        self.builder.start_node(kind.into());
+
+        let mut braces = 0usize;
+        let mut nesting = 0usize;
+        loop {
+            let Some(next) = self.peek() else {
+                self.truncated = true;
+                break;
+            };
+
+            match next {
+                SyntaxKind::SemiColon if braces == 0 => {
+                    self.bump();
+                    break;
+                }
+                SyntaxKind::RBrace if braces == 0 => break,
+                SyntaxKind::LBrace if braces == 0 && nesting == 0 && body.is_some() => {
+                    self.bump();
+                    self.statements(body.unwrap());
+                    if self.peek() == Some(SyntaxKind::RBrace) {
+                        self.bump();
+                    }
+                    break;
+                }
+                SyntaxKind::LBrace => {
+                    braces += 1;
+                    self.bump();
+                }
+                SyntaxKind::RBrace => {
+                    braces -= 1;
+                    self.bump();
+                }
+                SyntaxKind::LParen | SyntaxKind::LBracket => {
+                    nesting += 1;
+                    self.bump();
+                }
+                SyntaxKind::RParen | SyntaxKind::RBracket => {
+                    nesting = nesting.saturating_sub(1);
+                    self.bump();
+                }
+                _ => self.bump(),
+            }
+        }
+
+        self.builder.finish_node();
+    }
+
+    fn classify(&self, block: Block) -> SyntaxKind {
+        let Some((first, text)) = self.nth(0) else {
+            return SyntaxKind::Empty;
+        };
+        if first == SyntaxKind::SemiColon {
+            return SyntaxKind::Empty;
+        }
+
+        let second = self.nth(1);
+        let third = self.nth(2);
+        let is_group = match (text, second, third) {
+            ("group", Some((_, second)), _) => second != "=",
+            ("optional" | "required" | "repeated", Some((_, "group")), Some((_, third))) => {
+                third != "="
+            }
+            _ => false,
+        };
+
+        match (block, text) {
+            (Block::Root, "syntax" | "edition") => SyntaxKind::Syntax,
+            (Block::Root, "package") => SyntaxKind::Package,
+            (Block::Root, "import") => SyntaxKind::Import,
+            (Block::Root, "option") => SyntaxKind::Option,
+            (Block::Root | Block::Message, "message") => SyntaxKind::Message,
+            (Block::Root | Block::Message, "enum") => SyntaxKind::Enum,
+            (Block::Root | Block::Message, "extend") => SyntaxKind::Extend,
+            (Block::Root, "service") => SyntaxKind::Service,
+            (Block::Root, _) => SyntaxKind::Error,
+            (_, "option") if block != Block::Extend => SyntaxKind::Option,
+            (Block::Message | Block::Enum, "reserved") => SyntaxKind::Reserved,
+            (Block::Message, "extensions") => SyntaxKind::ExtensionRange,
+            (Block::Message, "oneof") => SyntaxKind::Oneof,
+            (Block::Message, "map") if matches!(second, Some((SyntaxKind::LAngle, _))) => {
+                SyntaxKind::MapField
+            }
+            (Block::Enum, _) => SyntaxKind::EnumValue,
+            (Block::Service, "rpc") => SyntaxKind::Rpc,
+            (Block::Service | Block::Rpc, _) => SyntaxKind::Error,
+            _ if is_group => SyntaxKind::Group,
+            _ => SyntaxKind::Field,
+        }
+    }
+}
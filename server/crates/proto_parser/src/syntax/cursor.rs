@@ -1,9 +1,9 @@
 use std::str::Chars;
 
 pub struct Cursor<'a> {
-    initial_len: usize,
+    /// Offset of the end of the input, in bytes from the start of the file.
+    end_offset: usize,
     chars: Chars<'a>,
-    current_pos: usize,
     current_line: usize,
     current_line_char: usize,
 }
@@ -11,13 +11,14 @@ pub struct Cursor<'a> {
 pub const EOF_CHAR: char = '\0';
 
 impl<'a> Cursor<'a> {
-    pub fn new(input: &'a str) -> Cursor<'a> {
+    /// A cursor over a slice of a file that starts at the given line, column
+    /// and byte offset.
+    pub fn new(input: &'a str, line: usize, column: usize, offset: usize) -> Cursor<'a> {
         Cursor {
-            initial_len: input.len(),
+            end_offset: offset + input.len(),
             chars: input.chars(),
-            current_pos: 0,
-            current_line: 0,
-            current_line_char: 0,
+            current_line: line,
+            current_line_char: column,
         }
     }
 
@@ -35,37 +36,18 @@ impl<'a> Cursor<'a> {
         self.chars.as_str().is_empty()
     }
 
-    pub fn len_consumed(&self) -> usize {
-        self.initial_len - self.chars.as_str().len()
-    }
-
-    pub fn reset_len_consumed(&mut self) {
-        self.initial_len = self.chars.as_str().len()
-    }
-
     pub fn bump(&mut self) -> Option<char> {
         let c = self.chars.next()?;
-        self.current_pos += 1;
         self.current_line_char += 1;
 
         Some(c)
     }
 
-    pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
-        while predicate(self.first()) && !self.is_eof() {
-            self.bump();
-        }
-    }
-
     pub fn increment_line(&mut self) {
         self.current_line += 1;
         self.current_line_char = 0;
     }
 
-    pub fn current_pos(&self) -> usize {
-        self.current_pos
-    }
-
     pub fn current_line(&self) -> usize {
         self.current_line
     }
@@ -73,4 +55,9 @@ impl<'a> Cursor<'a> {
     pub fn current_line_char(&self) -> usize {
         self.current_line_char
     }
+
+    /// Byte offset of the next char.
+    pub fn current_offset(&self) -> usize {
+        self.end_offset - self.chars.as_str().len()
+    }
 }
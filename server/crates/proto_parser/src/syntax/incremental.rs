@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use super::{
+    ast::Positions,
+    cst::{self, Cst, CstNode},
+    lexer::tokenize_at,
+    line_index::PositionEncoding,
+    parser::{DeclarationResult, ParseResult},
+    Position,
+};
+
+/// A parsed file that can be updated with text edits. The AST of a top-level
+/// declaration only depends on its own text and the comments around it, so an
+/// edit only reparses the declarations whose text or neighbouring comments it
+/// touches.
+#[derive(Debug)]
+pub struct IncrementalParser {
+    file_name: String,
+    cst: Cst,
+    result: ParseResult,
+    /// The number of AST nodes and errors each top-level declaration lowered
+    /// to, or `None` when the file could only be parsed as a whole.
+    counts: Option<Vec<(usize, usize)>>,
+}
+
+impl IncrementalParser {
+    pub fn new(file_name: &str, text: &str) -> Self {
+        let cst = Cst::parse(text);
+        let (result, counts) = match cst.lower_all(file_name) {
+            Some((result, counts)) => (result, Some(counts)),
+            None => (cst.to_ast(file_name), None),
+        };
+
+        Self {
+            file_name: file_name.to_string(),
+            cst,
+            result,
+            counts,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.cst.text()
+    }
+
+    pub fn cst(&self) -> &Cst {
+        &self.cst
+    }
+
+    pub fn result(&self) -> &ParseResult {
+        &self.result
+    }
+
+    pub fn into_result(self) -> ParseResult {
+        self.result
+    }
+
+    /// Replaces the text in the byte range `range` with `text` and updates the
+    /// tree. Out of bounds ranges are clamped to the text. Returns `false`,
+    /// leaving the text unchanged, if the range splits a char.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> bool {
+        let old_text = self.cst.text();
+        let end = range.end.min(old_text.len());
+        let range = range.start.min(end)..end;
+        if !old_text.is_char_boundary(range.start) || !old_text.is_char_boundary(range.end) {
+            return false;
+        }
+
+        let mut new_text = String::with_capacity(old_text.len() + text.len() - range.len());
+        new_text.push_str(&old_text[..range.start]);
+        new_text.push_str(text);
+        new_text.push_str(&old_text[range.end..]);
+
+        if self.reparse(range, text.len(), &new_text).is_none() {
+            *self = Self::new(&self.file_name, &new_text);
+        }
+        true
+    }
+
+    /// Reparses the declarations affected by replacing `range` with
+    /// `inserted` bytes, or returns `None` when the edit may change how the
+    /// rest of the file is parsed.
+    fn reparse(&mut self, range: Range<usize>, inserted: usize, new_text: &str) -> Option<()> {
+        let counts = self.counts.as_mut()?;
+        let old_len = self.cst.text().len();
+        let root = self.cst.syntax();
+        let declarations: Vec<CstNode> = root.children().collect();
+
+        let start = |node: &CstNode| usize::from(node.text_range().start());
+        let end = |node: &CstNode| usize::from(node.text_range().end());
+
+        // The parser reads from the end of the previous declaration up to the
+        // first token of the next one.
+        let mut affected = (0..declarations.len()).filter(|&idx| {
+            let context_start = idx
+                .checked_sub(1)
+                .map_or(0, |prev| end(&declarations[prev]));
+            let context_end = declarations
+                .get(idx + 1)
+                .and_then(|next| next.first_token())
+                .map_or(old_len, |token| token.text_range().end().into());
+
+            context_start <= range.end && range.start <= context_end
+        });
+        let first = affected.clone().next()?;
+        let last = affected.next_back()?;
+
+        let region_start = first
+            .checked_sub(1)
+            .map_or(0, |prev| end(&declarations[prev]));
+        let old_region_end = declarations.get(last + 1).map_or(old_len, start);
+        let new_region_end = old_region_end + inserted - range.len();
+
+        // The tokens after the region must stay the same, so the lexer must
+        // start a token where the region ends.
+        if new_region_end < new_text.len() {
+            let region_end = new_region_end - region_start;
+            let boundary = tokenize_at(&new_text[region_start..], Position::default())
+                .map(|token| token.position.offset)
+                .find(|&offset| offset >= region_end);
+            if boundary != Some(region_end) {
+                return None;
+            }
+        }
+
+        let (region, truncated) = cst::build(&new_text[region_start..new_region_end]);
+        if truncated && new_region_end < new_text.len() {
+            return None;
+        }
+
+        let elements: Vec<_> = root.children_with_tokens().collect();
+        let element_start = elements
+            .iter()
+            .position(|element| usize::from(element.text_range().start()) >= region_start)
+            .unwrap_or(elements.len());
+        let element_end = elements
+            .iter()
+            .position(|element| usize::from(element.text_range().start()) >= old_region_end)
+            .unwrap_or(elements.len());
+
+        let green = self.cst.green().splice_children(
+            element_start..element_end,
+            region.children().map(|element| element.to_owned()),
+        );
+        let cst = Cst::new(green, new_text.to_string());
+
+        let region_declarations = region
+            .children()
+            .filter(|element| element.as_node().is_some())
+            .count();
+        let mut nodes = Vec::new();
+        let mut errors = Vec::new();
+        let mut new_counts = Vec::with_capacity(region_declarations);
+        for declaration in cst.declarations().skip(first).take(region_declarations) {
+            let lowered = cst.lower(&declaration);
+            if !lowered.exact {
+                return None;
+            }
+
+            let DeclarationResult {
+                node,
+                errors: node_errors,
+                ..
+            } = lowered.result;
+            new_counts.push((usize::from(node.is_some()), node_errors.len()));
+            nodes.extend(node);
+            errors.extend(node_errors);
+        }
+
+        // Moves the declarations after the edit to their new positions.
+        let new_end = range.start + inserted;
+        let (old_line, old_column) = self
+            .cst
+            .line_index()
+            .line_column(range.end, PositionEncoding::Utf32);
+        let (new_line, new_column) = cst
+            .line_index()
+            .line_column(new_end, PositionEncoding::Utf32);
+        let mut shift = |position: &mut Position| {
+            if position.line == old_line {
+                position.column = position.column - old_column + new_column;
+            }
+            position.line = position.line - old_line + new_line;
+            position.offset = position.offset - range.end + new_end;
+        };
+
+        let sum = |range: Range<usize>, count: fn(&(usize, usize)) -> usize| {
+            counts[range].iter().map(count).sum::<usize>()
+        };
+        let node_start = sum(0..first, |count| count.0);
+        let node_end = node_start + sum(first..last + 1, |count| count.0);
+        let error_start = sum(0..first, |count| count.1);
+        let error_end = error_start + sum(first..last + 1, |count| count.1);
+
+        let result = &mut self.result;
+        for node in &mut result.root.nodes[node_end..] {
+            node.for_each_position(&mut shift);
+        }
+        for error in &mut result.errors[error_end..] {
+            shift(&mut error.position);
+            shift(&mut error.end);
+            for related in &mut error.related {
+                shift(&mut related.start);
+                shift(&mut related.end);
+            }
+        }
+        result.root.nodes.splice(node_start..node_end, nodes);
+        result.errors.splice(error_start..error_end, errors);
+        counts.splice(first..last + 1, new_counts);
+        self.cst = cst;
+
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::syntax::{tokenize, Parser};
+
+    use super::IncrementalParser;
+
+    const EXAMPLE: &str = include_str!("../../../../examples/example.proto");
+
+    fn assert_edit(text: &str, range: std::ops::Range<usize>, replacement: &str) {
+        let mut parser = IncrementalParser::new("test.proto", text);
+        parser.edit(range.clone(), replacement);
+
+        let mut expected_text = text.to_string();
+        expected_text.replace_range(range, replacement);
+        let expected = Parser::new(tokenize(&expected_text)).parse("test.proto");
+
+        assert_eq!(parser.text(), expected_text);
+        assert_eq!(parser.cst().syntax().to_string(), expected_text);
+        assert_eq!(
+            format!("{:?}", parser.result()),
+            format!("{expected:?}"),
+            "{expected_text}"
+        );
+    }
+
+    #[test]
+    fn edit_inside_declaration() {
+        let text = "syntax = \"proto3\";\n\nmessage A {\n  int32 a = 1;\n}\n\n// B\nmessage B {\n  string b = 2; // trailing\n}\n";
+        let a = text.find("a = 1").unwrap();
+
+        assert_edit(text, a..a + 1, "renamed");
+        assert_edit(text, a..a, "\n\n");
+        assert_edit(text, a..a + 5, "");
+    }
+
+    #[test]
+    fn edit_keeps_unaffected_declarations() {
+        let green = |parser: &IncrementalParser, idx: usize| {
+            let declaration = parser.cst().declarations().nth(idx).unwrap();
+            &*declaration.syntax().green() as *const rowan::GreenNodeData
+        };
+        let mut parser = IncrementalParser::new("test.proto", EXAMPLE);
+        let before: Vec<_> = (0..4).map(|idx| green(&parser, idx)).collect();
+
+        let offset = EXAMPLE.find("User user = 1;").unwrap();
+        parser.edit(offset..offset + 4, "Users");
+        let after: Vec<_> = (0..4).map(|idx| green(&parser, idx)).collect();
+
+        assert_eq!(before[..3], after[..3]);
+        assert_ne!(before[3], after[3]);
+        assert!(parser.counts.is_some());
+    }
+
+    #[test]
+    fn edit_comments_between_declarations() {
+        let text = "message A {}\n\n// B\nmessage B {}\nmessage C {}\n";
+        let comment = text.find("// B").unwrap();
+
+        assert_edit(text, comment..comment + 4, "");
+        assert_edit(text, comment - 1..comment - 1, "// trailing A");
+        assert_edit(text, comment..comment, "/* unterminated ");
+    }
+
+    #[test]
+    fn edit_that_changes_later_declarations() {
+        let text = "message A {\n}\nmessage B {}\nenum C { X = 0; }\n";
+        let brace = text.find('}').unwrap();
+
+        // Removing the brace makes `B` and `C` nested in `A`.
+        assert_edit(text, brace..brace + 1, "");
+        assert_edit(text, 0..0, "\"");
+        assert_edit(text, text.len()..text.len(), "message D {");
+    }
+
+    #[test]
+    fn sequential_edits_reuse_declarations() {
+        let mut parser = IncrementalParser::new("test.proto", EXAMPLE);
+        let mut expected = EXAMPLE.to_string();
+
+        for (idx, insert) in ["\n", "// note\n", "x", "reserved 5;\n"].iter().enumerate() {
+            let offset = expected
+                .match_indices('{')
+                .nth(idx * 3 + 1)
+                .map(|(offset, _)| offset + 1)
+                .unwrap();
+            parser.edit(offset..offset, insert);
+            expected.insert_str(offset, insert);
+
+            let full = Parser::new(tokenize(&expected)).parse("test.proto");
+            assert_eq!(format!("{:?}", parser.result()), format!("{full:?}"));
+            assert!(parser.counts.is_some());
+        }
+    }
+
+    #[test]
+    fn edit_that_splits_a_char_is_rejected() {
+        let text = "// é\nmessage A {}\n";
+        let mut parser = IncrementalParser::new("test.proto", text);
+        let offset = text.find('é').unwrap();
+
+        assert!(!parser.edit(offset + 1..offset + 2, "e"));
+        assert!(!parser.edit(offset..offset + 1, "e"));
+        assert_eq!(parser.text(), text);
+        assert!(parser.edit(offset..offset + 2, "e"));
+        assert_eq!(parser.text(), "// e\nmessage A {}\n");
+    }
+
+    proptest! {
+        #[test]
+        fn edit_matches_full_parse(
+            start in 0..EXAMPLE.len(),
+            len in 0..32usize,
+            replacement in prop::sample::select(vec![
+                "", " ", "\n", "}", "{", ";", "message X {", "// c\n", "/*", "\"", "int32 y = 3;",
+            ]),
+        ) {
+            let end = (start + len).min(EXAMPLE.len());
+            if EXAMPLE.is_char_boundary(start) && EXAMPLE.is_char_boundary(end) {
+                assert_edit(EXAMPLE, start..end, replacement);
+            }
+        }
+    }
+}
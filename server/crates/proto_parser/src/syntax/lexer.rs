@@ -6,6 +6,7 @@ use super::cursor::EOF_CHAR;
 
 static KEYWORD: phf::Map<&'static str, Keyword> = phf_map! {
     "syntax" => Keyword::Syntax,
+    "edition" => Keyword::Edition,
     "import" => Keyword::Import,
     "weak" => Keyword::Weak,
     "public" => Keyword::Public,
@@ -68,6 +69,7 @@ static OPERATORS: phf::Map<char, TokenKind> = phf_map! {
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Keyword {
     Syntax,
+    Edition,
     Import,
     Weak,
     Public,
@@ -113,6 +115,7 @@ pub enum TokenKind {
     IntLiteral,
     FloatLiteral,
     String,
+    UnterminatedString,
     SemiColon,
     Comma,
     Dot,
@@ -131,13 +134,19 @@ pub enum TokenKind {
     RAngle,
     Identifier,
     Keyword(Keyword),
-    LineComment,
-    BlockComment,
+    Comment(CommentKind),
     NewLine,
     Unknown,
     Eof,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CommentKind {
+    Line,
+    Block,
+    UnterminatedBlock,
+}
+
 impl Keyword {
     pub fn is_scalar(&self) -> bool {
         matches!(
@@ -190,26 +199,140 @@ impl Token {
     pub fn take_values(self) -> (String, TokenKind, Position) {
         (self.value, self.kind, self.position)
     }
+
+    /// The position just past the last char of the token.
+    pub fn end(&self) -> Position {
+        if self.kind == TokenKind::Eof {
+            return self.position;
+        }
+
+        let offset = self.position.offset + self.value.len();
+
+        match self.value.rfind('\n') {
+            Some(idx) => Position {
+                line: self.position.line + self.value.matches('\n').count(),
+                column: self.value[idx + 1..].chars().count(),
+                offset,
+            },
+            None => Position {
+                line: self.position.line,
+                column: self.position.column + self.value.chars().count(),
+                offset,
+            },
+        }
+    }
 }
 
 pub fn tokenize(input: &str) -> impl Iterator<Item = Token> + '_ {
-    let mut cursor = Cursor::new(input);
+    tokenize_at(input, Position::default())
+}
+
+/// Tokenizes a slice of a file, with positions relative to the start of the
+/// file given the position of the start of the slice.
+pub fn tokenize_at(input: &str, start: Position) -> impl Iterator<Item = Token> + '_ {
+    let mut cursor = Cursor::new(input, start.line, start.column, start.offset);
 
     std::iter::from_fn(move || {
         if cursor.is_eof() {
             None
         } else {
-            cursor.reset_len_consumed();
             Some(cursor.advance_token())
         }
     })
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct EscapeError {
+    pub message: String,
+    /// Offset in bytes from the start of the literal body.
+    pub offset: usize,
+}
+
+/// Decodes the escape sequences in the body of a string literal (the raw
+/// token without its surrounding quotes) into the bytes of its value.
+pub fn unescape(body: &str) -> Result<Vec<u8>, EscapeError> {
+    let mut bytes = Vec::with_capacity(body.len());
+    let mut chars = body.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        let error = |message: &str| EscapeError {
+            message: message.to_string(),
+            offset,
+        };
+
+        match chars.next().map(|(_, c)| c) {
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('f') => bytes.push(0x0c),
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('v') => bytes.push(0x0b),
+            Some(c @ ('\\' | '\'' | '"' | '?')) => bytes.push(c as u8),
+            Some(c @ '0'..='7') => {
+                let mut code = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.next_if(|(_, c)| c.is_digit(8)) {
+                        Some((_, c)) => code = code * 8 + c.to_digit(8).unwrap(),
+                        None => break,
+                    }
+                }
+                // protoc silently truncates octal escapes above \377.
+                bytes.push(code as u8);
+            }
+            Some('x' | 'X') => {
+                let mut code = None;
+                for _ in 0..2 {
+                    match chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                        Some((_, c)) => {
+                            code = Some(code.unwrap_or(0) * 16 + c.to_digit(16).unwrap())
+                        }
+                        None => break,
+                    }
+                }
+                match code {
+                    Some(code) => bytes.push(code as u8),
+                    None => return Err(error("Expected hex digits for escape sequence")),
+                }
+            }
+            Some(c @ ('u' | 'U')) => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let mut code = 0;
+                for _ in 0..len {
+                    match chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                        Some((_, c)) => code = code * 16 + c.to_digit(16).unwrap(),
+                        None => {
+                            return Err(error("Expected hex digits for unicode escape sequence"))
+                        }
+                    }
+                }
+                match char::from_u32(code) {
+                    Some(c) => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                    None => return Err(error("Invalid unicode code point in escape sequence")),
+                }
+            }
+            _ => return Err(error("Invalid escape sequence in string literal")),
+        }
+    }
+
+    Ok(bytes)
+}
+
 impl Cursor<'_> {
     fn advance_token(&mut self) -> Token {
         let pos = Position {
             line: self.current_line(),
             column: self.current_line_char(),
+            offset: self.current_offset(),
         };
         let c = match self.bump() {
             Some(c) => c,
@@ -220,9 +343,9 @@ impl Cursor<'_> {
             '/' => {
                 let next = self.first();
                 if next == '/' {
-                    self.line_comment()
+                    self.line_comment(pos)
                 } else if next == '*' {
-                    self.block_comment()
+                    self.block_comment(pos)
                 } else {
                     Token {
                         value: c.to_string(),
@@ -234,8 +357,8 @@ impl Cursor<'_> {
             c @ '_' => self.identifier_or_keyword(c, pos),
             c if c.is_ascii_alphabetic() => self.identifier_or_keyword(c, pos),
             c if c.is_whitespace() => self.whitespace(c, pos),
-            c if c.is_digit(10) => self.numeric_literal(c, pos),
-            '.' if self.first().is_digit(10) => self.numeric_literal(c, pos),
+            c if c.is_ascii_digit() => self.numeric_literal(c, pos),
+            '.' if self.first().is_ascii_digit() => self.numeric_literal(c, pos),
             c if OPERATORS.contains_key(&c) => Token {
                 value: c.to_string(),
                 kind: *OPERATORS.get(&c).unwrap(),
@@ -248,6 +371,7 @@ impl Cursor<'_> {
                 position: Position {
                     line: self.current_line(),
                     column: self.current_line_char(),
+                    offset: self.current_offset(),
                 },
             },
             _ => Token {
@@ -290,25 +414,43 @@ impl Cursor<'_> {
                     }
                 }
             }
-            _ => self.advance_token(),
+            _ => {
+                while self.first().is_whitespace() && !matches!(self.first(), '\n' | '\r') {
+                    self.bump();
+                }
+
+                self.advance_token()
+            }
         }
     }
 
     fn string(&mut self, c: char, pos: Position) -> Token {
         let mut value = c.to_string();
-        let mut next = self.first();
 
-        while next != c {
-            // TODO: handle escape sequences
-            value.push(self.bump().unwrap());
-            next = self.first();
-        }
+        let kind = loop {
+            if self.is_eof() {
+                break TokenKind::UnterminatedString;
+            }
 
-        value.push(self.bump().unwrap());
+            match self.first() {
+                '\n' | '\r' => break TokenKind::UnterminatedString,
+                '\\' => {
+                    value.push(self.bump().unwrap());
+                    if !self.is_eof() && !matches!(self.first(), '\n' | '\r') {
+                        value.push(self.bump().unwrap());
+                    }
+                }
+                next if next == c => {
+                    value.push(self.bump().unwrap());
+                    break TokenKind::String;
+                }
+                _ => value.push(self.bump().unwrap()),
+            }
+        };
 
         Token {
             value,
-            kind: TokenKind::String,
+            kind,
             position: pos,
         }
     }
@@ -317,7 +459,7 @@ impl Cursor<'_> {
         let mut value = c.to_string();
         let mut next = self.first();
 
-        while next.is_digit(10)
+        while next.is_ascii_digit()
             || next == '.'
             || next == 'e'
             || next == 'E'
@@ -330,18 +472,15 @@ impl Cursor<'_> {
         }
 
         let mut chars = value.chars();
-        let mut kind = TokenKind::Unknown;
-        if value.contains(&['.', 'e', 'E']) {
+        let mut kind;
+        if value.contains(['.', 'e', 'E']) {
             kind = TokenKind::FloatLiteral;
             let mut has_point = false;
             let mut has_e = false;
 
             if chars.next().unwrap() == '.' {
                 has_point = true;
-                let valid = match chars.next() {
-                    Some(c) if c.is_digit(10) => true,
-                    _ => false,
-                };
+                let valid = matches!(chars.next(), Some(c) if c.is_ascii_digit());
 
                 if !valid {
                     kind = TokenKind::Unknown;
@@ -362,10 +501,11 @@ impl Cursor<'_> {
 
                     has_e = true;
                     match chars.next() {
-                        Some('+') | Some('-') if chars.next().unwrap().is_digit(10) => {
+                        Some('+') | Some('-') if matches!(chars.next(), Some(c) if c.is_ascii_digit()) =>
+                        {
                             continue;
                         }
-                        Some(c) if c.is_digit(10) => {
+                        Some(c) if c.is_ascii_digit() => {
                             continue;
                         }
                         _ => {
@@ -387,7 +527,7 @@ impl Cursor<'_> {
         } else {
             if chars.next().unwrap() == '0' {
                 match chars.next() {
-                    Some('x') | Some('X') if chars.all(|c| c.is_digit(16)) => {
+                    Some('x') | Some('X') if chars.all(|c| c.is_ascii_hexdigit()) => {
                         kind = TokenKind::IntLiteral
                     }
                     Some(c) if c.is_digit(8) && chars.all(|c| c.is_digit(8)) => {
@@ -435,12 +575,54 @@ impl Cursor<'_> {
         }
     }
 
-    fn line_comment(&mut self) -> Token {
-        todo!()
+    fn line_comment(&mut self, pos: Position) -> Token {
+        let mut value = String::from("/");
+
+        while !self.is_eof() {
+            match self.first() {
+                '\n' => break,
+                '\r' if self.second() == '\n' => break,
+                _ => value.push(self.bump().unwrap()),
+            }
+        }
+
+        Token {
+            value,
+            kind: TokenKind::Comment(CommentKind::Line),
+            position: pos,
+        }
     }
 
-    fn block_comment(&mut self) -> Token {
-        todo!()
+    fn block_comment(&mut self, pos: Position) -> Token {
+        let mut value = String::from("/");
+        value.push(self.bump().unwrap());
+
+        let kind = loop {
+            match self.bump() {
+                Some('*') if self.first() == '/' => {
+                    value.push('*');
+                    value.push(self.bump().unwrap());
+                    break CommentKind::Block;
+                }
+                Some('\r') if self.first() == '\n' => {
+                    value.push('\r');
+                    value.push(self.bump().unwrap());
+                    self.increment_line();
+                }
+                Some('\n') => {
+                    value.push('\n');
+                    self.increment_line();
+                }
+                Some(c) => value.push(c),
+                None => break CommentKind::UnterminatedBlock,
+            }
+        };
+
+        Token {
+            value,
+            kind: TokenKind::Comment(kind),
+            position: pos,
+        }
     }
 }
 
@@ -448,16 +630,20 @@ impl Cursor<'_> {
 mod tests {
     use crate::Position;
 
-    use super::{tokenize, Keyword, Token, TokenKind};
+    use super::{tokenize, unescape, CommentKind, EscapeError, Keyword, Token, TokenKind};
 
     #[test]
     fn double_quoted_string() {
         let input = r#""hello""#;
 
-        let expected_tokens = vec![Token {
+        let expected_tokens = [Token {
             value: r#""hello""#.to_string(),
             kind: TokenKind::String,
-            position: Position { line: 0, column: 0 },
+            position: Position {
+                line: 0,
+                column: 0,
+                offset: 0,
+            },
         }];
 
         let actual_tokens: Vec<Token> = tokenize(input).collect();
@@ -472,6 +658,126 @@ mod tests {
             });
     }
 
+    #[test]
+    fn strings() {
+        let inputs = vec![
+            (r#""hello""#, TokenKind::String),
+            ("'hello'", TokenKind::String),
+            (r#""it's""#, TokenKind::String),
+            (r#"'say "hi"'"#, TokenKind::String),
+            (r#""escaped \" quote""#, TokenKind::String),
+            (r#""escaped \\""#, TokenKind::String),
+            (r#""\x41\101\u00e9""#, TokenKind::String),
+            (r#""unterminated"#, TokenKind::UnterminatedString),
+            (r#""unterminated \""#, TokenKind::UnterminatedString),
+            (r#""ends in \"#, TokenKind::UnterminatedString),
+        ];
+
+        for (input, expected_kind) in inputs {
+            let expected_tokens = vec![Token {
+                value: input.to_string(),
+                kind: expected_kind,
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
+            }];
+
+            let actual_tokens: Vec<Token> = tokenize(input).collect();
+
+            assert_eq!(expected_tokens, actual_tokens, "input = {:?}", input);
+        }
+    }
+
+    #[test]
+    fn unterminated_string_stops_at_new_line() {
+        let actual_tokens: Vec<Token> = tokenize("\"abc\n;").collect();
+
+        assert_eq!(
+            actual_tokens,
+            vec![
+                Token {
+                    value: "\"abc".to_string(),
+                    kind: TokenKind::UnterminatedString,
+                    position: Position {
+                        line: 0,
+                        column: 0,
+                        offset: 0
+                    },
+                },
+                Token {
+                    value: "\n".to_string(),
+                    kind: TokenKind::NewLine,
+                    position: Position {
+                        line: 0,
+                        column: 4,
+                        offset: 4
+                    },
+                },
+                Token {
+                    value: ";".to_string(),
+                    kind: TokenKind::SemiColon,
+                    position: Position {
+                        line: 1,
+                        column: 0,
+                        offset: 5
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unescape_sequences() {
+        let inputs = vec![
+            ("plain", "plain"),
+            (r"\n\r\t", "\n\r\t"),
+            (r"\a\b\f\v", "\x07\x08\x0c\x0b"),
+            (r#"\\\'\"\?"#, "\\'\"?"),
+            (r"\x41\X4a", "AJ"),
+            (r"\x4", "\x04"),
+            (r"\101\60\0", "A0\0"),
+            (r"\1010", "A0"),
+            (r"\u00e9", "é"),
+            (r"\U0001F600", "😀"),
+        ];
+
+        for (input, expected) in inputs {
+            assert_eq!(
+                unescape(input),
+                Ok(expected.as_bytes().to_vec()),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn unescape_bytes_that_are_not_utf8() {
+        assert_eq!(unescape(r"\xff\x00"), Ok(vec![0xff, 0x00]));
+        assert_eq!(unescape(r"\377\200a"), Ok(vec![0xff, 0x80, b'a']));
+    }
+
+    #[test]
+    fn unescape_errors() {
+        let inputs = vec![
+            (r"\q", 0),
+            (r"ab\xzz", 2),
+            (r"\u12", 0),
+            (r"\U00110000", 0),
+            (r"héllo\q", 6),
+        ];
+
+        for (input, offset) in inputs {
+            assert!(
+                matches!(unescape(input), Err(EscapeError { offset: o, .. }) if o == offset),
+                "{:?}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn new_lines() {
         let inputs = vec![
@@ -481,10 +787,14 @@ mod tests {
         ];
 
         for (input, expected_kind) in inputs {
-            let expected_tokens = vec![Token {
+            let expected_tokens = [Token {
                 value: input.to_string(),
                 kind: expected_kind,
-                position: Position { line: 0, column: 0 },
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
             }];
 
             let actual_tokens: Vec<Token> = tokenize(input).collect();
@@ -500,6 +810,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn comments() {
+        let inputs = vec![
+            ("// hello", CommentKind::Line),
+            ("//", CommentKind::Line),
+            ("// nested // and /* looking */", CommentKind::Line),
+            ("/* hello */", CommentKind::Block),
+            ("/**/", CommentKind::Block),
+            ("/* multi\nline\r\ncomment */", CommentKind::Block),
+            ("/* nested /* looking */", CommentKind::Block),
+            ("/* unterminated", CommentKind::UnterminatedBlock),
+            ("/* unterminated *", CommentKind::UnterminatedBlock),
+        ];
+
+        for (input, expected_kind) in inputs {
+            let expected_tokens = vec![Token {
+                value: input.to_string(),
+                kind: TokenKind::Comment(expected_kind),
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
+            }];
+
+            let actual_tokens: Vec<Token> = tokenize(input).collect();
+
+            assert_eq!(expected_tokens, actual_tokens, "input = {:?}", input);
+        }
+    }
+
+    #[test]
+    fn positions_count_chars_and_bytes() {
+        let input = "/* é */ \"😀\" x\n/* a\nb */ y";
+        let tokens: Vec<Token> = tokenize(input).collect();
+
+        let x = tokens.iter().find(|t| t.value == "x").unwrap();
+        assert_eq!(
+            x.position,
+            Position {
+                line: 0,
+                column: 12,
+                offset: input.find('x').unwrap()
+            }
+        );
+
+        let string = tokens.iter().find(|t| t.kind == TokenKind::String).unwrap();
+        assert_eq!(
+            string.end(),
+            Position {
+                line: 0,
+                column: 11,
+                offset: input.find(" x").unwrap()
+            }
+        );
+
+        let comment = tokens
+            .iter()
+            .rfind(|t| t.kind == TokenKind::Comment(CommentKind::Block));
+        assert_eq!(
+            comment.unwrap().end(),
+            Position {
+                line: 2,
+                column: 4,
+                offset: input.find(" y").unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn comments_followed_by_tokens() {
+        let input: &[(&str, &[Token])] = &[
+            (
+                "// comment\r\n;",
+                &[
+                    Token {
+                        value: "// comment".to_string(),
+                        kind: TokenKind::Comment(CommentKind::Line),
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
+                    },
+                    Token {
+                        value: "\r\n".to_string(),
+                        kind: TokenKind::NewLine,
+                        position: Position {
+                            line: 0,
+                            column: 10,
+                            offset: 10,
+                        },
+                    },
+                    Token {
+                        value: ";".to_string(),
+                        kind: TokenKind::SemiColon,
+                        position: Position {
+                            line: 1,
+                            column: 0,
+                            offset: 12,
+                        },
+                    },
+                ],
+            ),
+            (
+                "/* a\r\n b */ ;",
+                &[
+                    Token {
+                        value: "/* a\r\n b */".to_string(),
+                        kind: TokenKind::Comment(CommentKind::Block),
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
+                    },
+                    Token {
+                        value: ";".to_string(),
+                        kind: TokenKind::SemiColon,
+                        position: Position {
+                            line: 1,
+                            column: 6,
+                            offset: 12,
+                        },
+                    },
+                ],
+            ),
+            (
+                "a / b",
+                &[
+                    Token {
+                        value: "a".to_string(),
+                        kind: TokenKind::Identifier,
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
+                    },
+                    Token {
+                        value: "/".to_string(),
+                        kind: TokenKind::Slash,
+                        position: Position {
+                            line: 0,
+                            column: 2,
+                            offset: 2,
+                        },
+                    },
+                    Token {
+                        value: "b".to_string(),
+                        kind: TokenKind::Identifier,
+                        position: Position {
+                            line: 0,
+                            column: 4,
+                            offset: 4,
+                        },
+                    },
+                ],
+            ),
+        ];
+
+        for (input, expected_tokens) in input {
+            let actual_tokens: Vec<Token> = tokenize(input).collect();
+
+            assert_eq!(
+                expected_tokens.to_vec(),
+                actual_tokens,
+                "input = {:?}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn numeric_literal() {
         let inputs = vec![
@@ -522,7 +1005,11 @@ mod tests {
             let expected_tokens = vec![Token {
                 value: input.to_string(),
                 kind: expected_kind,
-                position: Position { line: 0, column: 0 },
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
             }];
 
             let actual_tokens: Vec<Token> = tokenize(input).collect();
@@ -548,6 +1035,7 @@ mod tests {
     pub fn keywords() {
         let input = vec![
             ("syntax", Keyword::Syntax),
+            ("edition", Keyword::Edition),
             ("import", Keyword::Import),
             ("package", Keyword::Package),
             ("option", Keyword::Option),
@@ -592,7 +1080,11 @@ mod tests {
             let expected_tokens = vec![Token {
                 value: input.to_string(),
                 kind: TokenKind::Keyword(expected_kind),
-                position: Position { line: 0, column: 0 },
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
             }];
 
             let actual_tokens: Vec<Token> = tokenize(input).collect();
@@ -639,7 +1131,11 @@ mod tests {
             let expected_tokens = vec![Token {
                 value: input.to_string(),
                 kind: expected_kind,
-                position: Position { line: 0, column: 0 },
+                position: Position {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
             }];
 
             let actual_tokens: Vec<Token> = tokenize(&input.to_string()).collect();
@@ -670,17 +1166,29 @@ mod tests {
                     Token {
                         value: "syntax".to_string(),
                         kind: TokenKind::Keyword(Keyword::Syntax),
-                        position: Position { line: 0, column: 0 },
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
                     },
                     Token {
                         value: "=".to_string(),
                         kind: TokenKind::Equals,
-                        position: Position { line: 0, column: 7 },
+                        position: Position {
+                            line: 0,
+                            column: 7,
+                            offset: 7,
+                        },
                     },
                     Token {
                         value: "\"proto3\"".to_string(),
                         kind: TokenKind::String,
-                        position: Position { line: 0, column: 9 },
+                        position: Position {
+                            line: 0,
+                            column: 9,
+                            offset: 9,
+                        },
                     },
                     Token {
                         value: ";".to_string(),
@@ -688,6 +1196,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 17,
+                            offset: 17,
                         },
                     },
                 ],
@@ -698,12 +1207,20 @@ mod tests {
                     Token {
                         value: "message".to_string(),
                         kind: TokenKind::Keyword(Keyword::Message),
-                        position: Position { line: 0, column: 0 },
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
                     },
                     Token {
                         value: "Foo".to_string(),
                         kind: TokenKind::Identifier,
-                        position: Position { line: 0, column: 8 },
+                        position: Position {
+                            line: 0,
+                            column: 8,
+                            offset: 8,
+                        },
                     },
                     Token {
                         value: "{".to_string(),
@@ -711,6 +1228,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 12,
+                            offset: 12,
                         },
                     },
                 ],
@@ -721,12 +1239,20 @@ mod tests {
                     Token {
                         value: "message".to_string(),
                         kind: TokenKind::Keyword(Keyword::Message),
-                        position: Position { line: 0, column: 0 },
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
                     },
                     Token {
                         value: "Foo".to_string(),
                         kind: TokenKind::Identifier,
-                        position: Position { line: 0, column: 8 },
+                        position: Position {
+                            line: 0,
+                            column: 8,
+                            offset: 8,
+                        },
                     },
                     Token {
                         value: "{".to_string(),
@@ -734,6 +1260,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 12,
+                            offset: 12,
                         },
                     },
                     Token {
@@ -742,6 +1269,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 14,
+                            offset: 14,
                         },
                     },
                     Token {
@@ -750,6 +1278,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 23,
+                            offset: 23,
                         },
                     },
                     Token {
@@ -758,6 +1287,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 29,
+                            offset: 29,
                         },
                     },
                     Token {
@@ -766,6 +1296,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 33,
+                            offset: 33,
                         },
                     },
                     Token {
@@ -774,6 +1305,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 35,
+                            offset: 35,
                         },
                     },
                     Token {
@@ -782,6 +1314,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 36,
+                            offset: 36,
                         },
                     },
                     Token {
@@ -790,6 +1323,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 38,
+                            offset: 38,
                         },
                     },
                 ],
@@ -800,12 +1334,20 @@ mod tests {
                     Token {
                         value: "message".to_string(),
                         kind: TokenKind::Keyword(Keyword::Message),
-                        position: Position { line: 0, column: 0 },
+                        position: Position {
+                            line: 0,
+                            column: 0,
+                            offset: 0,
+                        },
                     },
                     Token {
                         value: "Foo".to_string(),
                         kind: TokenKind::Identifier,
-                        position: Position { line: 0, column: 8 },
+                        position: Position {
+                            line: 0,
+                            column: 8,
+                            offset: 8,
+                        },
                     },
                     Token {
                         value: "{".to_string(),
@@ -813,6 +1355,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 12,
+                            offset: 12,
                         },
                     },
                     Token {
@@ -821,6 +1364,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 14,
+                            offset: 14,
                         },
                     },
                     Token {
@@ -829,6 +1373,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 23,
+                            offset: 23,
                         },
                     },
                     Token {
@@ -837,6 +1382,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 29,
+                            offset: 29,
                         },
                     },
                     Token {
@@ -845,6 +1391,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 33,
+                            offset: 33,
                         },
                     },
                     Token {
@@ -853,6 +1400,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 35,
+                            offset: 35,
                         },
                     },
                     Token {
@@ -861,6 +1409,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 36,
+                            offset: 36,
                         },
                     },
                     Token {
@@ -869,6 +1418,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 38,
+                            offset: 38,
                         },
                     },
                     Token {
@@ -877,6 +1427,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 47,
+                            offset: 47,
                         },
                     },
                     Token {
@@ -885,6 +1436,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 53,
+                            offset: 53,
                         },
                     },
                     Token {
@@ -893,6 +1445,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 57,
+                            offset: 57,
                         },
                     },
                     Token {
@@ -901,6 +1454,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 59,
+                            offset: 59,
                         },
                     },
                     Token {
@@ -909,6 +1463,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 60,
+                            offset: 60,
                         },
                     },
                     Token {
@@ -917,6 +1472,7 @@ mod tests {
                         position: Position {
                             line: 0,
                             column: 62,
+                            offset: 62,
                         },
                     },
                 ],
@@ -924,7 +1480,7 @@ mod tests {
         ];
 
         for (input, expected_tokens) in input {
-            let actual_tokens: Vec<Token> = tokenize(&input.to_string()).collect();
+            let actual_tokens: Vec<Token> = tokenize(input).collect();
 
             assert_eq!(
                 expected_tokens.len(),
@@ -0,0 +1,223 @@
+use super::Position;
+
+/// The unit columns are counted in, as negotiated with an LSP client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    /// Bytes of UTF-8.
+    Utf8,
+    /// UTF-16 code units, the LSP default.
+    #[default]
+    Utf16,
+    /// Unicode code points, which is what `Position::column` counts.
+    Utf32,
+}
+
+impl PositionEncoding {
+    fn len(self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+    /// Byte offset from the start of the line.
+    start: usize,
+    c: char,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    start: usize,
+    /// End of the line's content, before any `\n` or `\r\n`.
+    end: usize,
+    /// Non-ASCII chars on the line, in order.
+    wide_chars: Vec<WideChar>,
+}
+
+/// Converts between byte offsets and line/column pairs in any
+/// `PositionEncoding`.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    lines: Vec<Line>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut start = 0;
+
+        for line in text.split_inclusive('\n') {
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            let content = content.strip_suffix('\r').unwrap_or(content);
+
+            let wide_chars = content
+                .char_indices()
+                .filter(|(_, c)| !c.is_ascii())
+                .map(|(start, c)| WideChar { start, c })
+                .collect();
+
+            lines.push(Line {
+                start,
+                end: start + content.len(),
+                wide_chars,
+            });
+            start += line.len();
+        }
+
+        if text.is_empty() || text.ends_with('\n') {
+            lines.push(Line {
+                start,
+                end: start,
+                wide_chars: Vec::new(),
+            });
+        }
+
+        Self { lines }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The line and column of a byte offset. Offsets past the end of a line's
+    /// content are clamped to it.
+    pub fn line_column(&self, offset: usize, encoding: PositionEncoding) -> (usize, usize) {
+        let line_idx = self
+            .lines
+            .partition_point(|line| line.start <= offset)
+            .saturating_sub(1);
+        let line = &self.lines[line_idx];
+        let byte_column = offset.min(line.end).saturating_sub(line.start);
+
+        let column = line
+            .wide_chars
+            .iter()
+            .take_while(|wide| wide.start < byte_column)
+            .fold(byte_column, |column, wide| {
+                column - wide.c.len_utf8() + encoding.len(wide.c)
+            });
+
+        (line_idx, column)
+    }
+
+    /// The line and column of `position` in the given encoding.
+    pub fn encode(&self, position: Position, encoding: PositionEncoding) -> (usize, usize) {
+        self.line_column(position.offset, encoding)
+    }
+
+    /// The byte offset of a line and column. Columns past the end of the line
+    /// are clamped to it and lines past the end of the text to its end.
+    pub fn offset(&self, line: usize, column: usize, encoding: PositionEncoding) -> usize {
+        let Some(line) = self.lines.get(line) else {
+            return self.lines.last().map(|line| line.end).unwrap_or_default();
+        };
+
+        // Walks the line keeping `column_so_far` (in `encoding`) and
+        // `byte_column` in step. Everything between wide chars is ASCII.
+        let mut column_so_far = 0;
+        let mut byte_column = 0;
+        for wide in &line.wide_chars {
+            let ascii = wide.start - byte_column;
+            if column_so_far + ascii >= column {
+                break;
+            }
+            column_so_far += ascii;
+            byte_column = wide.start;
+
+            let len = encoding.len(wide.c);
+            if column_so_far + len > column {
+                // The column points inside the char, e.g. between the two
+                // halves of a UTF-16 surrogate pair.
+                return line.start + byte_column;
+            }
+            column_so_far += len;
+            byte_column += wide.c.len_utf8();
+        }
+
+        (line.start + byte_column + (column - column_so_far)).min(line.end)
+    }
+
+    /// Converts a line and column in the given encoding to a `Position`.
+    pub fn position(&self, line: usize, column: usize, encoding: PositionEncoding) -> Position {
+        let offset = self.offset(line, column, encoding);
+        let (line, column) = self.line_column(offset, PositionEncoding::Utf32);
+
+        Position {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineIndex, PositionEncoding};
+
+    const TEXT: &str = "a = 1;\r\n// héllo 😀 x\nz";
+
+    #[test]
+    fn line_column() {
+        let index = LineIndex::new(TEXT);
+        let x = TEXT.find('x').unwrap();
+
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_column(0, PositionEncoding::Utf16), (0, 0));
+        assert_eq!(index.line_column(x, PositionEncoding::Utf8), (1, 15));
+        assert_eq!(index.line_column(x, PositionEncoding::Utf16), (1, 12));
+        assert_eq!(index.line_column(x, PositionEncoding::Utf32), (1, 11));
+        assert_eq!(
+            index.line_column(TEXT.len(), PositionEncoding::Utf16),
+            (2, 1)
+        );
+        // The `\r` of a `\r\n` is not part of the line.
+        assert_eq!(index.line_column(7, PositionEncoding::Utf16), (0, 6));
+    }
+
+    #[test]
+    fn offset_round_trips() {
+        let index = LineIndex::new(TEXT);
+
+        for encoding in [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ] {
+            for (offset, _) in TEXT
+                .char_indices()
+                .filter(|(_, c)| !matches!(c, '\r' | '\n'))
+            {
+                let (line, column) = index.line_column(offset, encoding);
+                assert_eq!(
+                    index.offset(line, column, encoding),
+                    offset,
+                    "{encoding:?} {offset}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn offset_clamps_to_line_end() {
+        let index = LineIndex::new(TEXT);
+
+        assert_eq!(index.offset(0, 100, PositionEncoding::Utf16), 6);
+        assert_eq!(index.offset(10, 0, PositionEncoding::Utf16), TEXT.len());
+    }
+
+    #[test]
+    fn position_counts_chars() {
+        let index = LineIndex::new(TEXT);
+        let x = TEXT.find('x').unwrap();
+
+        let position = index.position(1, 12, PositionEncoding::Utf16);
+        assert_eq!(
+            (position.line, position.column, position.offset),
+            (1, 11, x)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{iter::Peekable, str::FromStr};
+use std::iter::Peekable;
 
 use crate::{
     syntax::ast::{
@@ -9,18 +9,21 @@ use crate::{
     Position,
 };
 
+use self::comments::split_comments;
+
 use super::{
     ast::{
         message::{
-            FieldCardinality, FieldDeclaration, FieldType, MapFieldDeclaration, MessageElement,
-            OneofDeclaration, OneofElement,
+            ExtensionRange, FieldCardinality, FieldDeclaration, FieldType, GroupDeclaration,
+            MapFieldDeclaration, MessageElement, OneofDeclaration, OneofElement,
         },
         option::{OptionName, OptionNode, OptionValue},
         service::{MessageType, MethodElement, MethodNode, ServiceElement, ServiceNode},
-        EnumElement, ExtensionElement, ImportModifier, ImportNode, MapKeyType, Node, PackageNode,
-        Reserved, Root, RootDeclaration, ScalarType, SyntaxNode, SyntaxType, TagEnd, TagRange,
+        Comments, Edition, EnumElement, ExtensionElement, ImportModifier, ImportNode, MapKeyType,
+        Node, PackageNode, Reserved, Root, RootDeclaration, ScalarType, StringLiteral, SyntaxNode,
+        SyntaxType, TagEnd, TagRange,
     },
-    lexer::{Keyword, Token, TokenKind},
+    lexer::{unescape, CommentKind, Keyword, Token, TokenKind},
 };
 
 #[derive(Debug)]
@@ -29,24 +32,109 @@ pub struct ParseResult {
     pub errors: Vec<ParseError>,
 }
 
+/// The result of `Parser::parse_declaration`.
+#[derive(Debug)]
+pub struct DeclarationResult {
+    pub node: Option<Node<RootDeclaration>>,
+    pub errors: Vec<ParseError>,
+    /// End of the last significant token consumed.
+    pub end: Position,
+}
+
+/// What a `ParseError` is about. Each kind has a stable code that clients can
+/// rely on, unlike the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedToken,
+    UnexpectedEof,
+    UnclosedBlock,
+    UnterminatedComment,
+    UnterminatedString,
+    InvalidEscape,
+    InvalidSyntax,
+    NumberOutOfRange,
+    InvalidName,
+}
+
+impl ParseErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ParseErrorKind::UnexpectedToken => "unexpected-token",
+            ParseErrorKind::UnexpectedEof => "unexpected-eof",
+            ParseErrorKind::UnclosedBlock => "unclosed-block",
+            ParseErrorKind::UnterminatedComment => "unterminated-comment",
+            ParseErrorKind::UnterminatedString => "unterminated-string",
+            ParseErrorKind::InvalidEscape => "invalid-escape",
+            ParseErrorKind::InvalidSyntax => "invalid-syntax",
+            ParseErrorKind::NumberOutOfRange => "number-out-of-range",
+            ParseErrorKind::InvalidName => "invalid-name",
+        }
+    }
+}
+
+/// Another location that helps explain an error, e.g. where an unclosed
+/// block was opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedInformation {
+    pub message: String,
+    pub start: Position,
+    pub end: Position,
+}
+
 #[derive(Debug)]
 pub struct ParseError {
+    pub kind: ParseErrorKind,
     pub message: String,
     pub position: Position,
+    /// End of the text the error is about. The same as `position` when the
+    /// error is about something missing.
+    pub end: Position,
+    pub related: Vec<RelatedInformation>,
 }
 
 impl ParseError {
     pub fn new(message: String, position: Position) -> Self {
-        Self { message, position }
+        Self {
+            kind: ParseErrorKind::UnexpectedToken,
+            message,
+            position,
+            end: position,
+            related: Vec::new(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: ParseErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_end(mut self, end: Position) -> Self {
+        self.end = end;
+        self
+    }
+
+    pub fn with_related(mut self, message: &str, start: Position, end: Position) -> Self {
+        self.related.push(RelatedInformation {
+            message: message.to_string(),
+            start,
+            end,
+        });
+        self
     }
 }
 
+mod comments;
+mod text_format;
+
 type Result<T> = std::result::Result<T, ParseError>;
 
 pub struct Parser<I: Iterator<Item = Token>> {
     line: usize,
     tokens: Peekable<I>,
     errors: Vec<ParseError>,
+    trivia: Vec<Token>,
+    prev_line: Option<usize>,
+    prev_end: Position,
 }
 
 impl<I: Iterator<Item = Token>> Parser<I> {
@@ -55,6 +143,36 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             line: 0,
             tokens: tokens.peekable(),
             errors: Vec::new(),
+            trivia: Vec::new(),
+            prev_line: None,
+            prev_end: Position::default(),
+        }
+    }
+
+    /// Continues a parse after a significant token on `line`, so comments on
+    /// that line are treated as trailing comments of the previous declaration.
+    pub fn after_line(mut self, line: usize) -> Self {
+        self.prev_line = Some(line);
+        self
+    }
+
+    /// Parses a single root declaration, recovering from errors the same way
+    /// `parse` does.
+    pub fn parse_declaration(mut self) -> DeclarationResult {
+        let node = match self.root_node() {
+            Ok(node) => Some(node),
+            Err(err) => {
+                self.errors.push(err);
+                self.sync();
+                None
+            }
+        };
+        self.peek_kind();
+
+        DeclarationResult {
+            node,
+            errors: self.errors,
+            end: self.prev_end,
         }
     }
 
@@ -78,10 +196,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     fn is_at_end(&mut self) -> bool {
-        match self.peek_kind() {
-            Some(TokenKind::Eof) | None => true,
-            _ => false,
-        }
+        matches!(self.peek_kind(), Some(TokenKind::Eof) | None)
     }
 
     fn expect(&mut self, token_kind: TokenKind) -> Result<Token> {
@@ -97,8 +212,20 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
         Err(ParseError::new(
             format!("Expected token: {:?}. Got: {:?}", token_kind, next),
-            self.tokens.peek().unwrap().position,
-        ))
+            self.peek_position(),
+        )
+        .with_end(self.peek_end()))
+    }
+
+    /// Expects the token closing a block or literal opened by `open`.
+    fn expect_closing(&mut self, open: &Token, token_kind: TokenKind) -> Result<Token> {
+        self.expect(token_kind).map_err(|err| {
+            err.with_kind(ParseErrorKind::UnclosedBlock).with_related(
+                "Block opened here",
+                open.position,
+                open.end(),
+            )
+        })
     }
 
     fn peek_kind(&mut self) -> Option<TokenKind> {
@@ -108,7 +235,24 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 ..
             }) => {
                 self.line += 1;
-                self.advance();
+                let token = self.tokens.next().unwrap();
+                self.trivia.push(token);
+                self.peek_kind()
+            }
+            Some(Token {
+                kind: TokenKind::Comment(kind),
+                ..
+            }) => {
+                let kind = *kind;
+                let token = self.tokens.next().unwrap();
+                if kind == CommentKind::UnterminatedBlock {
+                    self.errors.push(
+                        ParseError::new("Unterminated block comment".to_string(), token.position)
+                            .with_kind(ParseErrorKind::UnterminatedComment)
+                            .with_end(token.end()),
+                    );
+                }
+                self.trivia.push(token);
                 self.peek_kind()
             }
             Some(token) => Some(token.kind),
@@ -130,12 +274,117 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     fn advance(&mut self) -> Option<Token> {
-        self.tokens.next()
+        let token = self.tokens.next()?;
+
+        if !matches!(token.kind, TokenKind::NewLine | TokenKind::Comment(_)) {
+            self.prev_line = Some(token.position.line);
+            self.prev_end = token.end();
+            self.trivia.clear();
+        }
+
+        Some(token)
+    }
+
+    /// Position of the next token, or the end of the last token once the
+    /// input is exhausted.
+    fn peek_position(&mut self) -> Position {
+        self.peek_kind();
+        match self.tokens.peek() {
+            Some(token) if token.kind != TokenKind::Eof => token.position,
+            _ => self.prev_end,
+        }
+    }
+
+    /// End of the next token, or the end of the last token once the input is
+    /// exhausted.
+    fn peek_end(&mut self) -> Position {
+        self.peek_kind();
+        match self.tokens.peek() {
+            Some(token) if token.kind != TokenKind::Eof => token.end(),
+            _ => self.prev_end,
+        }
+    }
+
+    /// Whether the parser is at the closing brace of a block or at the end of
+    /// the input.
+    fn at_block_end(&mut self) -> bool {
+        matches!(
+            self.peek_kind(),
+            Some(TokenKind::RBrace | TokenKind::Eof) | None
+        )
+    }
+
+    /// Skips the rest of a malformed statement inside a block: up to and
+    /// including the next `;` or nested `{ ... }` block, stopping before the
+    /// `}` that closes the enclosing block.
+    fn sync_element(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek_kind() {
+                Some(TokenKind::Eof) | None => return,
+                Some(TokenKind::RBrace) if depth == 0 => return,
+                Some(TokenKind::RBrace) => {
+                    self.advance();
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                Some(TokenKind::LBrace) => {
+                    self.advance();
+                    depth += 1;
+                }
+                Some(TokenKind::SemiColon) if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Some(_) => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn unexpected_token(&mut self, expected: &str) -> ParseError {
+        let position = self.peek_position();
+        match self.peek_kind() {
+            Some(TokenKind::Eof) | None => ParseError::new(
+                format!("Unexpected end of file. Expected {expected}"),
+                position,
+            )
+            .with_kind(ParseErrorKind::UnexpectedEof),
+            Some(kind) => ParseError::new(
+                format!("Unexpected token: {kind:?}. Expected {expected}"),
+                position,
+            )
+            .with_end(self.peek_end()),
+        }
+    }
+
+    /// Comments before the next token that belong to the declaration
+    /// starting at that token.
+    fn leading_comments(&mut self) -> Comments {
+        self.peek_kind();
+        let split = split_comments(&self.trivia, self.prev_line, self.tokens.peek());
+
+        Comments {
+            leading: split.leading,
+            trailing: None,
+            leading_detached: split.detached,
+        }
+    }
+
+    /// Comment after the last consumed token that belongs to the
+    /// declaration ending at that token.
+    fn trailing_comment(&mut self) -> Option<String> {
+        self.peek_kind();
+        split_comments(&self.trivia, self.prev_line, self.tokens.peek()).trailing
     }
 
     fn root_node(&mut self) -> Result<Node<RootDeclaration>> {
         let result = match self.peek_kind() {
-            Some(TokenKind::Keyword(Keyword::Syntax)) => {
+            Some(TokenKind::Keyword(Keyword::Syntax | Keyword::Edition)) => {
                 let node = self.syntax_node()?;
                 Node::new(RootDeclaration::Syntax(node.value), node.start, node.end)
             }
@@ -154,25 +403,30 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             Some(TokenKind::Keyword(Keyword::Message)) => {
                 let node = self.message_node()?;
                 Node::new(RootDeclaration::Message(node.value), node.start, node.end)
+                    .with_comments(node.comments)
             }
             Some(TokenKind::Keyword(Keyword::Enum)) => {
                 let node = self.enum_node()?;
                 Node::new(RootDeclaration::Enum(node.value), node.start, node.end)
+                    .with_comments(node.comments)
             }
             Some(TokenKind::Keyword(Keyword::Service)) => {
                 let node = self.service_node()?;
                 Node::new(RootDeclaration::Service(node.value), node.start, node.end)
+                    .with_comments(node.comments)
             }
             Some(TokenKind::Keyword(Keyword::Extend)) => {
                 let node = self.extend_node()?;
                 Node::new(RootDeclaration::Extension(node.value), node.start, node.end)
+                    .with_comments(node.comments)
             }
             Some(_) => {
                 let token = self.advance().unwrap();
                 let err = ParseError::new(
                     format!("Unexpected token: {:?}. Expected root node", token.kind),
                     token.position,
-                );
+                )
+                .with_end(token.end());
                 return Err(err);
             }
             None => Node::new(
@@ -186,31 +440,42 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     fn syntax_node(&mut self) -> Result<Node<SyntaxNode>> {
-        let start = self.advance().unwrap().position;
+        let keyword = self.advance().unwrap();
+        let start = keyword.position;
+        let is_edition = keyword.kind == TokenKind::Keyword(Keyword::Edition);
 
         self.expect(TokenKind::Equals)?;
 
-        let token_kind = if let Some(TokenKind::String) = self.peek_kind() {
-            let token = self.advance().unwrap();
-            match token.value.as_str() {
-                "\"proto2\"" => SyntaxType::Proto2,
-                "\"proto3\"" => SyntaxType::Proto3,
-                _ => {
-                    let err = ParseError::new(
-                        format!("Invalid syntax version: {:?}", token.kind),
-                        token.position,
-                    );
-                    return Err(err);
+        let token_kind =
+            if let Some(TokenKind::String | TokenKind::UnterminatedString) = self.peek_kind() {
+                let literal = self.utf8_string()?;
+                match (is_edition, literal.value.as_str()) {
+                    (false, "proto2") => SyntaxType::Proto2,
+                    (false, "proto3") => SyntaxType::Proto3,
+                    (true, "2023") => SyntaxType::Edition(Edition::Edition2023),
+                    (true, "2024") => SyntaxType::Edition(Edition::Edition2024),
+                    (true, _) => {
+                        let err = ParseError::new(
+                            format!("Unsupported edition: {:?}", literal.value),
+                            literal.start,
+                        )
+                        .with_kind(ParseErrorKind::InvalidSyntax)
+                        .with_end(literal.end);
+                        return Err(err);
+                    }
+                    _ => {
+                        let err = ParseError::new(
+                            format!("Invalid syntax version: {:?}", literal.value),
+                            literal.start,
+                        )
+                        .with_kind(ParseErrorKind::InvalidSyntax)
+                        .with_end(literal.end);
+                        return Err(err);
+                    }
                 }
-            }
-        } else {
-            let token = self.tokens.peek().unwrap();
-            let err = ParseError::new(
-                format!("Expected string after '=', got {:?}", token.kind),
-                self.tokens.peek().unwrap().position,
-            );
-            return Err(err);
-        };
+            } else {
+                return Err(self.unexpected_token("string after '='"));
+            };
 
         let end_token = self.expect(TokenKind::SemiColon)?;
 
@@ -231,12 +496,8 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
         let end_token = self.expect(TokenKind::SemiColon)?;
 
-        self.advance().unwrap();
-
         let package_node = Node::new(
-            PackageNode {
-                package_name: name.value,
-            },
+            PackageNode { package_name: name },
             start,
             end_token.position,
         );
@@ -258,14 +519,14 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             _ => None,
         };
 
-        let file_name = self.string()?;
+        let file_name = self.utf8_string()?;
 
         let end_token = self.expect(TokenKind::SemiColon)?;
 
         let import_node = Node::new(
             ImportNode {
                 modifier,
-                package_name: file_name.value,
+                package_name: file_name,
             },
             start,
             end_token.position,
@@ -307,132 +568,206 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     fn message_node(&mut self) -> Result<Node<Message>> {
+        let mut comments = self.leading_comments();
         let start = self.advance().unwrap().position;
 
-        let identifier = self.expect(TokenKind::Identifier)?;
+        let identifier = self.identifier()?;
 
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?;
+        comments.trailing = self.trailing_comment();
 
-        let mut elements = Vec::new();
-        while !matches!(self.peek_kind(), Some(TokenKind::RBrace) | None) {
-            let element = self.message_element()?;
-            elements.push(element);
-        }
+        let elements = self.message_elements();
 
-        let end_token = self.expect(TokenKind::RBrace)?;
+        let end_token = self.expect_closing(&open, TokenKind::RBrace)?;
 
         let message_node = Node::new(
             Message {
-                name: identifier.value,
+                name: identifier,
                 elements,
             },
             start,
             end_token.position,
-        );
+        )
+        .with_comments(comments);
 
         Ok(message_node)
     }
 
     fn enum_node(&mut self) -> Result<Node<EnumNode>> {
+        let mut comments = self.leading_comments();
         let start = self.advance().unwrap().position;
 
-        let identifier_token = self.expect(TokenKind::Identifier)?;
+        let identifier_token = self.identifier()?;
 
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?;
+        comments.trailing = self.trailing_comment();
 
         let elements = self.enum_elements()?;
 
-        let end_token = self.expect(TokenKind::RBrace)?;
+        let end_token = self.expect_closing(&open, TokenKind::RBrace)?;
 
         let enum_node = Node::new(
             EnumNode {
-                name: identifier_token.value,
+                name: identifier_token,
                 elements,
             },
             start,
             end_token.position,
-        );
+        )
+        .with_comments(comments);
 
         Ok(enum_node)
     }
 
     fn service_node(&mut self) -> Result<Node<ServiceNode>> {
+        let mut comments = self.leading_comments();
         let start = self.advance().unwrap().position;
 
-        let identifier_token = self.expect(TokenKind::Identifier)?;
+        let identifier_token = self.identifier()?;
 
-        self.expect(TokenKind::LBrace).unwrap();
+        let open = self.expect(TokenKind::LBrace)?;
+        comments.trailing = self.trailing_comment();
 
         let elements = self.service_elements()?;
 
-        let end_token = self.expect(TokenKind::RBrace)?;
+        let end_token = self.expect_closing(&open, TokenKind::RBrace)?;
 
         let service_node = Node::new(
             ServiceNode {
-                name: identifier_token.value,
+                name: identifier_token,
                 elements,
             },
             start,
             end_token.position,
-        );
+        )
+        .with_comments(comments);
 
         Ok(service_node)
     }
 
     fn extend_node(&mut self) -> Result<Node<ExtensionNode>> {
-        let identifier_token = self.qualified_identifier()?;
+        let mut comments = self.leading_comments();
+        let start = self.expect(TokenKind::Keyword(Keyword::Extend))?.position;
 
-        self.expect(TokenKind::LBrace)?;
+        let extendee = self.type_name()?;
 
-        let elements = self.extension_elements()?;
+        let open = self.expect(TokenKind::LBrace)?;
+        comments.trailing = self.trailing_comment();
 
-        let end_token = self.expect(TokenKind::RBrace)?;
+        let elements = self.extension_elements();
+
+        let end_token = self.expect_closing(&open, TokenKind::RBrace)?;
 
         let extend_node = Node::new(
-            ExtensionNode {
-                extendee: identifier_token.value,
-                elements,
-            },
-            identifier_token.start,
+            ExtensionNode { extendee, elements },
+            start,
             end_token.position,
-        );
+        )
+        .with_comments(comments);
 
         Ok(extend_node)
     }
 
-    fn string(&mut self) -> Result<Node<String>> {
-        let mut token = self.expect(TokenKind::String)?;
+    fn string(&mut self) -> Result<Node<StringLiteral>> {
+        let token = match self.peek_kind() {
+            Some(TokenKind::UnterminatedString) => self.advance().unwrap(),
+            _ => self.expect(TokenKind::String)?,
+        };
 
-        let mut string = String::from_str(token.value.as_str()).unwrap();
         let start = token.position;
-        let mut end = &token.position + token.value.len();
-        while let Some(TokenKind::String) = self.peek_kind() {
-            token = self.advance().unwrap();
-            string.push_str(token.value.as_str());
-            end = &token.position + token.value.len();
+        let mut end = token.end();
+        let mut literal = StringLiteral {
+            raw: Vec::new(),
+            value: Vec::new(),
+        };
+        self.push_string_token(&mut literal, token);
+
+        while let Some(TokenKind::String | TokenKind::UnterminatedString) = self.peek_kind() {
+            let token = self.advance().unwrap();
+            end = token.end();
+            self.push_string_token(&mut literal, token);
         }
 
-        let string = format!("\"{}\"", string.replace("\"", ""));
+        Ok(Node::new(literal, start, end))
+    }
 
-        let node = Node::new(string, start, end);
+    /// A string literal whose value has to be text, like an import path.
+    fn utf8_string(&mut self) -> Result<Node<String>> {
+        let literal = self.string()?;
+        let value = match literal.value.as_str() {
+            Ok(value) => value.to_string(),
+            Err(_) => {
+                self.errors.push(
+                    ParseError::new("String is not valid UTF-8".to_string(), literal.start)
+                        .with_kind(ParseErrorKind::InvalidEscape)
+                        .with_end(literal.end),
+                );
+                String::from_utf8_lossy(&literal.value.value).into_owned()
+            }
+        };
+        Ok(Node::new(value, literal.start, literal.end))
+    }
 
-        Ok(node)
+    fn push_string_token(&mut self, literal: &mut StringLiteral, token: Token) {
+        let body = if token.kind == TokenKind::UnterminatedString {
+            self.errors.push(
+                ParseError::new("Unterminated string literal".to_string(), token.position)
+                    .with_kind(ParseErrorKind::UnterminatedString)
+                    .with_end(token.end()),
+            );
+            &token.value[1..]
+        } else {
+            &token.value[1..token.value.len() - 1]
+        };
+
+        match unescape(body) {
+            Ok(value) => literal.value.extend(value),
+            Err(err) => {
+                // Strings don't span lines, so only the column needs the
+                // width of the text before the escape in chars.
+                let escape = &body[err.offset..];
+                let escape = &escape[..escape
+                    .char_indices()
+                    .nth(2)
+                    .map_or(escape.len(), |(idx, _)| idx)];
+                let position = Position {
+                    line: token.position.line,
+                    column: token.position.column + 1 + body[..err.offset].chars().count(),
+                    offset: token.position.offset + 1 + err.offset,
+                };
+                let end = Position {
+                    column: position.column + escape.chars().count(),
+                    offset: position.offset + escape.len(),
+                    ..position
+                };
+                self.errors.push(
+                    ParseError::new(err.message, position)
+                        .with_kind(ParseErrorKind::InvalidEscape)
+                        .with_end(end),
+                );
+                literal.value.extend_from_slice(body.as_bytes());
+            }
+        }
+
+        literal.raw.push(token.value);
     }
 
     fn qualified_identifier(&mut self) -> Result<Node<Vec<String>>> {
         let identifier = self.expect(TokenKind::Identifier)?;
         let start = identifier.position;
 
+        let mut end = &identifier.position + identifier.value.len();
+
         let mut identifiers = vec![identifier.value];
 
         while let Some(TokenKind::Dot) = self.peek_kind() {
             self.advance().unwrap();
             let identifier = self.expect(TokenKind::Identifier)?;
 
+            end = &identifier.position + identifier.value.len();
             identifiers.push(identifier.value);
         }
 
-        let end = self.tokens.peek().unwrap().position;
         let node = Node::new(identifiers, start, end);
         Ok(node)
     }
@@ -453,27 +788,23 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                     name.push(part);
                 }
                 Some(TokenKind::LParen) => {
-                    let identifier = self.qualified_identifier()?;
+                    let start = self.advance().unwrap().position;
+                    let type_name = self.type_name()?;
+                    let end = &self.expect(TokenKind::RParen)?.position + 1;
 
-                    let part = Node::new(
-                        OptionNamePart::ExtensionName(TypeName::from(identifier.value)),
-                        identifier.start,
-                        identifier.end,
-                    );
+                    let part =
+                        Node::new(OptionNamePart::ExtensionName(type_name.value), start, end);
 
                     name.push(part);
                 }
-                _ => {
-                    break;
-                }
+                _ => return Err(self.unexpected_token("option name")),
             }
-        }
 
-        if name.is_empty() {
-            return Err(ParseError::new(
-                format!("Expected option name",),
-                self.tokens.peek().unwrap().position,
-            ));
+            if let Some(TokenKind::Dot) = self.peek_kind() {
+                self.advance();
+            } else {
+                break;
+            }
         }
 
         let start = name.first().unwrap().start;
@@ -484,10 +815,13 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
     fn option_value(&mut self) -> Result<Node<OptionValue>> {
         match self.peek_kind() {
-            Some(TokenKind::String) => {
-                let token = self.advance().unwrap();
-                let end = &token.position + token.value.len();
-                let value = Node::new(OptionValue::StringLiteral(token.value), token.position, end);
+            Some(TokenKind::String | TokenKind::UnterminatedString) => {
+                let literal = self.string()?;
+                let value = Node::new(
+                    OptionValue::StringLiteral(literal.value),
+                    literal.start,
+                    literal.end,
+                );
                 Ok(value)
             }
             Some(TokenKind::Minus) => {
@@ -513,12 +847,10 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                         );
                         Ok(value)
                     }
-                    _ => {
-                        return Err(ParseError::new(
-                            format!("Expected int or float literal"),
-                            start,
-                        ))
-                    }
+                    _ => Err(
+                        ParseError::new("Expected int or float literal".to_string(), start)
+                            .with_end(self.peek_end()),
+                    ),
                 }
             }
             Some(TokenKind::Plus) => {
@@ -545,12 +877,10 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                         );
                         Ok(value)
                     }
-                    _ => {
-                        return Err(ParseError::new(
-                            format!("Expected int or float literal"),
-                            start,
-                        ))
-                    }
+                    _ => Err(
+                        ParseError::new("Expected int or float literal".to_string(), start)
+                            .with_end(self.peek_end()),
+                    ),
                 }
             }
             Some(TokenKind::IntLiteral) => {
@@ -571,13 +901,35 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 let value = Node::new(OptionValue::Identifier(token.value), token.position, end);
                 Ok(value)
             }
-            _ => {
-                return Err(ParseError::new(
-                    format!("Expected option value",),
-                    self.tokens.peek().unwrap().position,
-                ));
+            Some(TokenKind::LBrace) => {
+                let literal = self.message_literal()?;
+                let value = Node::new(
+                    OptionValue::MessageLiteral(literal.value),
+                    literal.start,
+                    literal.end,
+                );
+                Ok(value)
+            }
+            _ => Err(
+                ParseError::new("Expected option value".to_string(), self.peek_position())
+                    .with_end(self.peek_end()),
+            ),
+        }
+    }
+
+    fn message_elements(&mut self) -> Vec<Node<MessageElement>> {
+        let mut elements = Vec::new();
+        while !self.at_block_end() {
+            match self.message_element() {
+                Ok(element) => elements.push(element),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                }
             }
         }
+
+        elements
     }
 
     fn message_element(&mut self) -> Result<Node<MessageElement>> {
@@ -586,19 +938,17 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 TokenKind::Keyword(Keyword::Required)
                 | TokenKind::Keyword(Keyword::Optional)
                 | TokenKind::Keyword(Keyword::Repeated)
+                | TokenKind::Keyword(Keyword::Group)
                 | TokenKind::Dot
                 | TokenKind::Identifier,
-            ) => {
-                let decl = self.field_decl()?;
-                Node::new(MessageElement::Field(decl.value), decl.start, decl.end)
-            }
+            ) => self.field_or_group(MessageElement::Field, MessageElement::Group)?,
             Some(TokenKind::Keyword(t)) if t.is_scalar() => {
-                let decl = self.field_decl()?;
-                Node::new(MessageElement::Field(decl.value), decl.start, decl.end)
+                self.field_or_group(MessageElement::Field, MessageElement::Group)?
             }
             Some(TokenKind::Keyword(Keyword::Enum)) => {
                 let decl = self.enum_node()?;
                 Node::new(MessageElement::Enum(decl.value), decl.start, decl.end)
+                    .with_comments(decl.comments)
             }
             Some(TokenKind::Keyword(Keyword::Message)) => {
                 let decl = self.message_node()?;
@@ -607,6 +957,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                     decl.start,
                     decl.end,
                 )
+                .with_comments(decl.comments)
             }
             Some(TokenKind::Keyword(Keyword::Oneof)) => {
                 let decl = self.oneof_node()?;
@@ -615,10 +966,20 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             Some(TokenKind::Keyword(Keyword::Map)) => {
                 let decl = self.map_field_decl()?;
                 Node::new(MessageElement::MapField(decl.value), decl.start, decl.end)
+                    .with_comments(decl.comments)
             }
             Some(TokenKind::Keyword(Keyword::Extensions)) => {
+                let decl = self.extension_range_node()?;
+                Node::new(
+                    MessageElement::ExtensionRange(decl.value),
+                    decl.start,
+                    decl.end,
+                )
+            }
+            Some(TokenKind::Keyword(Keyword::Extend)) => {
                 let decl = self.extend_node()?;
                 Node::new(MessageElement::Extension(decl.value), decl.start, decl.end)
+                    .with_comments(decl.comments)
             }
             Some(TokenKind::Keyword(Keyword::Reserved)) => {
                 let decl = self.reserved_node()?;
@@ -628,15 +989,40 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 let decl = self.option_node()?;
                 Node::new(MessageElement::Option(decl.value), decl.start, decl.end)
             }
-            _ => todo!("unknown message element"),
+            Some(TokenKind::SemiColon) => {
+                let token = self.advance().unwrap();
+                Node::new(MessageElement::Empty, token.position, token.position)
+            }
+            _ => return Err(self.unexpected_token("message element")),
         };
 
         Ok(element)
     }
 
-    fn field_decl(&mut self) -> Result<Node<FieldDeclaration>> {
-        let mut cardinality = self.field_cardinality()?;
+    /// Parses a field, or a group when the field type is the `group` keyword,
+    /// wrapping the result with `field` or `group` respectively.
+    fn field_or_group<T: Clone>(
+        &mut self,
+        field: impl FnOnce(FieldDeclaration) -> T,
+        group: impl FnOnce(GroupDeclaration) -> T,
+    ) -> Result<Node<T>> {
+        let comments = self.leading_comments();
+        let cardinality = self.field_cardinality()?;
+
+        if let Some(TokenKind::Keyword(Keyword::Group)) = self.peek_kind() {
+            let decl = self.group_decl(comments, cardinality)?;
+            Ok(Node::new(group(decl.value), decl.start, decl.end).with_comments(decl.comments))
+        } else {
+            let decl = self.field_decl(comments, cardinality)?;
+            Ok(Node::new(field(decl.value), decl.start, decl.end).with_comments(decl.comments))
+        }
+    }
 
+    fn field_decl(
+        &mut self,
+        mut comments: Comments,
+        mut cardinality: Node<FieldCardinality>,
+    ) -> Result<Node<FieldDeclaration>> {
         let field_type = self.field_type()?;
 
         if cardinality.start.column == 0 {
@@ -646,17 +1032,11 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
         let start = cardinality.start;
 
-        let field_name = self.field_name()?;
+        let field_name = self.identifier()?;
 
         self.expect(TokenKind::Equals)?;
 
-        let field_number = self.expect(TokenKind::IntLiteral)?;
-        let end = &field_number.position + field_number.value.len();
-        let field_number = Node::new(
-            field_number.value.parse::<u32>().unwrap(),
-            field_number.position,
-            end,
-        );
+        let field_number = self.field_number()?;
 
         let options = if let Some(TokenKind::LBracket) = self.peek_kind() {
             self.compact_options()?
@@ -665,6 +1045,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         };
 
         let end = self.expect(TokenKind::SemiColon)?.position;
+        comments.trailing = self.trailing_comment();
 
         let decl = FieldDeclaration {
             cardinality,
@@ -674,7 +1055,61 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             options,
         };
 
-        Ok(Node::new(decl, start, end))
+        Ok(Node::new(decl, start, end).with_comments(comments))
+    }
+
+    fn group_decl(
+        &mut self,
+        mut comments: Comments,
+        mut cardinality: Node<FieldCardinality>,
+    ) -> Result<Node<GroupDeclaration>> {
+        let keyword = self.expect(TokenKind::Keyword(Keyword::Group))?;
+
+        if cardinality.start.column == 0 {
+            cardinality.start = keyword.position;
+            cardinality.end = keyword.position;
+        }
+
+        let start = cardinality.start;
+
+        let name = self.identifier()?;
+        if !name.value.starts_with(|c: char| c.is_ascii_uppercase()) {
+            self.errors.push(
+                ParseError::new(
+                    "Group names must start with a capital letter".to_string(),
+                    name.start,
+                )
+                .with_kind(ParseErrorKind::InvalidName)
+                .with_end(name.end),
+            );
+        }
+
+        self.expect(TokenKind::Equals)?;
+
+        let number = self.field_number()?;
+
+        let options = if let Some(TokenKind::LBracket) = self.peek_kind() {
+            self.compact_options()?
+        } else {
+            vec![]
+        };
+
+        let open = self.expect(TokenKind::LBrace)?;
+        comments.trailing = self.trailing_comment();
+
+        let elements = self.message_elements();
+
+        let end = self.expect_closing(&open, TokenKind::RBrace)?.position;
+
+        let decl = GroupDeclaration {
+            cardinality,
+            name,
+            number,
+            options,
+            elements,
+        };
+
+        Ok(Node::new(decl, start, end).with_comments(comments))
     }
 
     fn field_cardinality(&mut self) -> Result<Node<FieldCardinality>> {
@@ -694,26 +1129,27 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 let end = &token_parts.2 + token_parts.0.len();
                 Node::new(FieldCardinality::Repeated, token_parts.2, end)
             }
-            Some(TokenKind::Keyword(t)) if t.is_scalar() => Node::new(
+            Some(TokenKind::Keyword(t)) if t.is_scalar() || t == Keyword::Group => Node::new(
                 FieldCardinality::Optional,
-                Position { line: 0, column: 0 },
-                Position { line: 0, column: 0 },
+                Position::default(),
+                Position::default(),
             ),
             Some(TokenKind::Dot) => Node::new(
                 FieldCardinality::Optional,
-                Position { line: 0, column: 0 },
-                Position { line: 0, column: 0 },
+                Position::default(),
+                Position::default(),
             ),
             Some(TokenKind::Identifier) => Node::new(
                 FieldCardinality::Optional,
-                Position { line: 0, column: 0 },
-                Position { line: 0, column: 0 },
+                Position::default(),
+                Position::default(),
             ),
             _ => {
                 return Err(ParseError::new(
-                    format!("Expected field cardinality"),
-                    self.tokens.peek().unwrap().position,
-                ));
+                    "Expected field cardinality".to_string(),
+                    self.peek_position(),
+                )
+                .with_end(self.peek_end()));
             }
         };
 
@@ -735,9 +1171,10 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             }
             _ => {
                 return Err(ParseError::new(
-                    format!("Expected field type"),
-                    self.tokens.peek().unwrap().position,
-                ));
+                    "Expected field type".to_string(),
+                    self.peek_position(),
+                )
+                .with_end(self.peek_end()));
             }
         };
 
@@ -747,29 +1184,73 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     fn field_number(&mut self) -> Result<Node<u32>> {
         let (value, _, position) = self.expect(TokenKind::IntLiteral)?.take_values();
         let end = &position + value.len();
-        let field_number = Node::new(value.parse::<u32>().unwrap(), position, end);
+        let number = parse_int_literal(&value)
+            .and_then(|number| u32::try_from(number).ok())
+            .ok_or_else(|| {
+                ParseError::new(format!("Field number out of range: {value}"), position)
+                    .with_kind(ParseErrorKind::NumberOutOfRange)
+                    .with_end(end)
+            })?;
+        let field_number = Node::new(number, position, end);
 
         Ok(field_number)
     }
 
-    fn field_name(&mut self) -> Result<Node<String>> {
-        let (value, _, position) = self.expect(TokenKind::Identifier)?.take_values();
+    /// An enum value's number. A number outside the `int32` range is
+    /// reported but kept, so the value is still declared.
+    fn enum_value_number(&mut self) -> Result<Node<i64>> {
+        let minus = match self.peek_kind() {
+            Some(TokenKind::Minus) => Some(self.advance().unwrap().position),
+            _ => None,
+        };
+        let value = self.expect(TokenKind::IntLiteral)?;
+        let start = minus.unwrap_or(value.position);
+        let end = value.end();
+        let number = parse_int_literal(&value.value)
+            .and_then(|number| i64::try_from(number).ok())
+            .map(|number| if minus.is_some() { -number } else { number })
+            .ok_or_else(|| {
+                ParseError::new(format!("Enum value out of range: {}", value.value), start)
+                    .with_kind(ParseErrorKind::NumberOutOfRange)
+                    .with_end(end)
+            })?;
+
+        if i32::try_from(number).is_err() {
+            self.errors.push(
+                ParseError::new(format!("Enum value out of range: {}", value.value), start)
+                    .with_kind(ParseErrorKind::NumberOutOfRange)
+                    .with_end(end),
+            );
+        }
+
+        Ok(Node::new(number, start, end))
+    }
+
+    /// A declared name. Keywords are only reserved where a statement starts,
+    /// so like protoc this accepts e.g. a field named `syntax`.
+    fn identifier(&mut self) -> Result<Node<String>> {
+        let token = match self.peek_kind() {
+            Some(TokenKind::Keyword(_)) => self.advance().unwrap(),
+            _ => self.expect(TokenKind::Identifier)?,
+        };
+        let (value, _, position) = token.take_values();
         let end = &position + value.len();
         let start = position;
         Ok(Node::new(value, start, end))
     }
 
     fn enum_elements(&mut self) -> Result<Vec<Node<EnumElement>>> {
-        _ = self.expect(TokenKind::LBrace)?;
-
         let mut elements = Vec::new();
-        while !matches!(self.peek_kind(), Some(TokenKind::RBrace)) {
-            let element = self.enum_element()?;
-            elements.push(element);
+        while !self.at_block_end() {
+            match self.enum_element() {
+                Ok(element) => elements.push(element),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                }
+            }
         }
 
-        _ = self.expect(TokenKind::RBrace)?;
-
         Ok(elements)
     }
 
@@ -784,32 +1265,28 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 ))
             }
             Some(TokenKind::Identifier) => {
-                let value_name = self.advance().unwrap();
+                let mut comments = self.leading_comments();
+                let value_name = self.identifier()?;
 
                 _ = self.expect(TokenKind::Equals)?;
 
-                let modifier = if let Some(TokenKind::Minus) = self.peek_kind() {
-                    self.advance().unwrap();
-                    -1
-                } else {
-                    1
-                };
-                let value = self.expect(TokenKind::IntLiteral)?;
+                let value_number = self.enum_value_number()?;
                 let options = if let Some(TokenKind::LBracket) = self.peek_kind() {
                     self.compact_options()?
                 } else {
                     Vec::new()
                 };
                 let end = self.expect(TokenKind::SemiColon)?;
+                comments.trailing = self.trailing_comment();
 
-                let value_number = modifier * value.value.parse::<i32>().unwrap();
+                let start = value_name.start;
                 let element = EnumElement::EnumValue {
-                    name: value_name.value,
+                    name: value_name,
                     number: value_number,
                     options,
                 };
 
-                Ok(Node::new(element, value_name.position, end.position))
+                Ok(Node::new(element, start, end.position).with_comments(comments))
             }
             Some(TokenKind::Keyword(Keyword::Reserved)) => {
                 let reserved = self.reserved_node()?;
@@ -827,16 +1304,21 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                     token.position,
                 ))
             }
-            _ => todo!("unknown enum element"),
+            _ => Err(self.unexpected_token("enum element")),
         }
     }
 
     fn service_elements(&mut self) -> Result<Vec<Node<ServiceElement>>> {
         let mut elements = Vec::new();
 
-        while !matches!(self.peek_kind(), Some(TokenKind::RBrace)) {
-            let element = self.service_element()?;
-            elements.push(element);
+        while !self.at_block_end() {
+            match self.service_element() {
+                Ok(element) => elements.push(element),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                }
+            }
         }
 
         Ok(elements)
@@ -861,83 +1343,82 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 ))
             }
             Some(TokenKind::Keyword(Keyword::Rpc)) => {
+                let mut comments = self.leading_comments();
                 let start = self.advance().unwrap().position;
-                let rpc_name = self.expect(TokenKind::Identifier)?;
+                let rpc_name = self.identifier()?;
 
                 let input_type = self.rpc_message_type()?;
                 _ = self.expect(TokenKind::Keyword(Keyword::Returns))?;
                 let output_type = self.rpc_message_type()?;
 
                 let mut method_element = MethodNode {
-                    name: rpc_name.value,
+                    name: rpc_name,
                     input_type,
                     output_type,
                     elements: Vec::new(),
                 };
 
                 let end = if let Some(TokenKind::LBrace) = self.peek_kind() {
-                    self.advance().unwrap();
-                    loop {
-                        let element = match self.peek_kind() {
-                            Some(TokenKind::Keyword(Keyword::Option)) => {
-                                let option = self.option_node()?;
-                                Node::new(
-                                    MethodElement::Option(option.value),
-                                    option.start,
-                                    option.end,
-                                )
-                            }
-                            Some(TokenKind::SemiColon) => {
-                                let token = self.advance().unwrap();
-                                Node::new(MethodElement::Empty, token.position, token.position)
-                            }
-                            Some(TokenKind::RBrace) => {
-                                break;
-                            }
-                            Some(kind) => {
-                                let error = ParseError::new(
-                                    format!("unexpected token kind {:?}", kind),
-                                    self.tokens.peek().unwrap().position,
-                                );
-                                return Err(error);
+                    let open = self.advance().unwrap();
+                    comments.trailing = self.trailing_comment();
+                    while !self.at_block_end() {
+                        match self.method_element() {
+                            Ok(element) => method_element.elements.push(element),
+                            Err(err) => {
+                                self.errors.push(err);
+                                self.sync_element();
                             }
-                            _ => todo!("unknown rpc element"),
-                        };
-                        method_element.elements.push(element);
+                        }
                     }
 
-                    self.expect(TokenKind::RBrace)?.position
+                    self.expect_closing(&open, TokenKind::RBrace)?.position
                 } else {
-                    self.expect(TokenKind::SemiColon)?.position
+                    let end = self.expect(TokenKind::SemiColon)?.position;
+                    comments.trailing = self.trailing_comment();
+                    end
                 };
 
-                Ok(Node::new(
-                    ServiceElement::Method(method_element),
-                    start,
-                    end,
-                ))
-            }
-            Some(kind) => {
-                let error = ParseError::new(
-                    format!("unexpected token kind {:?}", kind),
-                    self.tokens.peek().unwrap().position,
-                );
-                Err(error)
+                Ok(
+                    Node::new(ServiceElement::Method(Box::new(method_element)), start, end)
+                        .with_comments(comments),
+                )
             }
-            _ => todo!("unknown service element"),
+            _ => Err(self.unexpected_token("service element")),
         }
     }
 
-    fn rpc_message_type(&mut self) -> Result<Node<MessageType>> {
-        let start = self.expect(TokenKind::LParen)?.position;
-        let stream = if let Some(TokenKind::Keyword(Keyword::Stream)) = self.peek_kind() {
-            self.advance().unwrap();
-            true
-        } else {
-            false
-        };
-        let name = self.type_name()?;
-        let end = self.expect(TokenKind::RParen)?.position;
+    fn method_element(&mut self) -> Result<Node<MethodElement>> {
+        match self.peek_kind() {
+            Some(TokenKind::Keyword(Keyword::Option)) => {
+                let option = self.option_node()?;
+                Ok(Node::new(
+                    MethodElement::Option(option.value),
+                    option.start,
+                    option.end,
+                ))
+            }
+            Some(TokenKind::SemiColon) => {
+                let token = self.advance().unwrap();
+                Ok(Node::new(
+                    MethodElement::Empty,
+                    token.position,
+                    token.position,
+                ))
+            }
+            _ => Err(self.unexpected_token("rpc element")),
+        }
+    }
+
+    fn rpc_message_type(&mut self) -> Result<Node<MessageType>> {
+        let start = self.expect(TokenKind::LParen)?.position;
+        let stream = if let Some(TokenKind::Keyword(Keyword::Stream)) = self.peek_kind() {
+            self.advance().unwrap();
+            true
+        } else {
+            false
+        };
+        let name = self.type_name()?;
+        let end = self.expect(TokenKind::RParen)?.position;
 
         let message_type = MessageType {
             stream,
@@ -947,28 +1428,56 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         Ok(Node::new(message_type, start, end))
     }
 
-    fn extension_elements(&mut self) -> Result<Vec<Node<ExtensionElement>>> {
-        Err(ParseError::new(
-            "extension elements are not supported yet".into(),
-            self.tokens.peek().unwrap().position,
-        ))
+    fn extension_elements(&mut self) -> Vec<Node<ExtensionElement>> {
+        let mut elements = Vec::new();
+        while !self.at_block_end() {
+            match self.extension_element() {
+                Ok(Some(element)) => elements.push(element),
+                Ok(None) => {}
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                }
+            }
+        }
+
+        elements
+    }
+
+    fn extension_element(&mut self) -> Result<Option<Node<ExtensionElement>>> {
+        match self.peek_kind() {
+            Some(
+                TokenKind::Keyword(Keyword::Required)
+                | TokenKind::Keyword(Keyword::Optional)
+                | TokenKind::Keyword(Keyword::Repeated)
+                | TokenKind::Keyword(Keyword::Group)
+                | TokenKind::Dot
+                | TokenKind::Identifier,
+            ) => self
+                .field_or_group(ExtensionElement::Field, ExtensionElement::Group)
+                .map(Some),
+            Some(TokenKind::Keyword(t)) if t.is_scalar() => self
+                .field_or_group(ExtensionElement::Field, ExtensionElement::Group)
+                .map(Some),
+            Some(TokenKind::SemiColon) => {
+                self.advance();
+                Ok(None)
+            }
+            _ => Err(self.unexpected_token("extension field")),
+        }
     }
 
     fn type_name(&mut self) -> Result<Node<TypeName>> {
-        let absolute = if let Some(TokenKind::Dot) = self.peek_kind() {
-            self.advance().unwrap();
-            true
+        let dot = if let Some(TokenKind::Dot) = self.peek_kind() {
+            self.advance()
         } else {
-            false
+            None
         };
+        let absolute = dot.is_some();
 
         let parts = self.qualified_identifier()?;
 
-        let start = if absolute {
-            &parts.start - 1
-        } else {
-            parts.start
-        };
+        let start = dot.map(|token| token.position).unwrap_or(parts.start);
 
         let end = parts.end;
 
@@ -980,6 +1489,24 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         Ok(Node::new(name, start, end))
     }
 
+    fn extension_range_node(&mut self) -> Result<Node<ExtensionRange>> {
+        let start = self
+            .expect(TokenKind::Keyword(Keyword::Extensions))?
+            .position;
+
+        let ranges = self.tag_range()?;
+
+        let options = if let Some(TokenKind::LBracket) = self.peek_kind() {
+            self.compact_options()?
+        } else {
+            vec![]
+        };
+
+        let end = self.expect(TokenKind::SemiColon)?.position;
+
+        Ok(Node::new(ExtensionRange { ranges, options }, start, end))
+    }
+
     fn reserved_node(&mut self) -> Result<Node<Reserved>> {
         let start = self.expect(TokenKind::Keyword(Keyword::Reserved))?.position;
 
@@ -989,21 +1516,21 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 let end = range.last().unwrap().end;
                 Ok(Node::new(Reserved::TagRanges(range), start, end))
             }
-            Some(TokenKind::String) => {
+            Some(TokenKind::String | TokenKind::UnterminatedString) => {
                 let names = self.reserved_names()?;
                 let end = names.last().unwrap().end;
                 Ok(Node::new(Reserved::Names(names), start, end))
             }
-            _ => {
-                return Err(ParseError::new(
-                    format!("Expected reserved range or names"),
-                    self.tokens.peek().unwrap().position,
-                ));
-            }
+            _ => Err(ParseError::new(
+                "Expected reserved range or names".to_string(),
+                self.peek_position(),
+            )
+            .with_end(self.peek_end())),
         }
     }
 
     fn map_field_decl(&mut self) -> Result<Node<MapFieldDeclaration>> {
+        let mut comments = self.leading_comments();
         let start = self.expect(TokenKind::Keyword(Keyword::Map))?.position;
         _ = self.expect(TokenKind::LAngle)?;
         let key_type = self.map_key_type()?;
@@ -1011,7 +1538,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         let value_type = self.field_type()?;
         _ = self.expect(TokenKind::RAngle)?;
 
-        let name = self.field_name()?;
+        let name = self.identifier()?;
         _ = self.expect(TokenKind::Equals)?;
         let number = self.field_number()?;
 
@@ -1022,6 +1549,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         };
 
         let end = self.expect(TokenKind::SemiColon)?.position;
+        comments.trailing = self.trailing_comment();
 
         let field = MapFieldDeclaration {
             key_type,
@@ -1031,7 +1559,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             options,
         };
 
-        Ok(Node::new(field, start, end))
+        Ok(Node::new(field, start, end).with_comments(comments))
     }
 
     fn map_key_type(&mut self) -> Result<Node<MapKeyType>> {
@@ -1043,17 +1571,15 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                     token
                         .kind
                         .try_into()
-                        .or_else(|e| Err(ParseError::new(e, token.position)))?,
+                        .map_err(|e| ParseError::new(e, token.position).with_end(end))?,
                     token.position,
                     end,
                 ))
             }
-            _ => {
-                return Err(ParseError::new(
-                    format!("Expected map key type"),
-                    self.tokens.peek().unwrap().position,
-                ));
-            }
+            _ => Err(
+                ParseError::new("Expected map key type".to_string(), self.peek_position())
+                    .with_end(self.peek_end()),
+            ),
         }
     }
 
@@ -1064,16 +1590,21 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         let name_start = name.position;
         let name = Node::new(name.value, name_start, name_end);
 
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?;
 
         let mut elements = Vec::new();
 
-        while !matches!(self.peek_kind(), Some(TokenKind::RBrace)) {
-            let element = self.oneof_element()?;
-            elements.push(element);
+        while !self.at_block_end() {
+            match self.oneof_element() {
+                Ok(element) => elements.push(element),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                }
+            }
         }
 
-        let end = self.expect(TokenKind::RBrace)?;
+        let end = self.expect_closing(&open, TokenKind::RBrace)?;
 
         let decl = OneofDeclaration { name, elements };
 
@@ -1106,13 +1637,21 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                     field.end,
                 ))
             }
+            Some(TokenKind::Keyword(Keyword::Group)) => {
+                let comments = self.leading_comments();
+                let cardinality = self.field_cardinality()?;
+                let group = self.group_decl(comments, cardinality)?;
+                Ok(
+                    Node::new(OneofElement::Group(group.value), group.start, group.end)
+                        .with_comments(group.comments),
+                )
+            }
             _ => {
-                let err = ParseError::new(
-                    format!("Expected oneof element"),
-                    self.tokens.peek().unwrap().position,
-                );
+                let err =
+                    ParseError::new("Expected oneof element".to_string(), self.peek_position())
+                        .with_end(self.peek_end());
 
-                return Err(err);
+                Err(err)
             }
         }
     }
@@ -1144,21 +1683,17 @@ impl<I: Iterator<Item = Token>> Parser<I> {
             }
             _ => {
                 return Err(ParseError::new(
-                    format!("Expected oneof field type"),
-                    self.tokens.peek().unwrap().position,
-                ));
+                    "Expected oneof field type".to_string(),
+                    self.peek_position(),
+                )
+                .with_end(self.peek_end()));
             }
         };
 
-        let name = self.field_name()?;
+        let name = self.identifier()?;
         self.expect(TokenKind::Equals)?;
-        let number = self.expect(TokenKind::IntLiteral)?;
-        let number = Node::new(
-            number.value.parse::<u32>().unwrap(),
-            number.position,
-            &number.position + number.value.len(),
-        );
-        let options = if let Some(TokenKind::RBrace) = self.peek_kind() {
+        let number = self.field_number()?;
+        let options = if let Some(TokenKind::LBracket) = self.peek_kind() {
             Some(self.compact_options()?)
         } else {
             None
@@ -1182,7 +1717,10 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
         let mut options = Vec::new();
 
-        while !matches!(self.peek_kind(), Some(TokenKind::RBracket)) {
+        while !matches!(
+            self.peek_kind(),
+            Some(TokenKind::RBracket | TokenKind::Eof) | None
+        ) {
             let option = self.option_node()?;
             options.push(option);
 
@@ -1200,33 +1738,21 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         let mut ranges = Vec::new();
 
         loop {
-            let start = self.expect(TokenKind::IntLiteral)?;
+            let start = self.field_number()?;
             let end = if let Some(TokenKind::Keyword(Keyword::To)) = self.peek_kind() {
                 self.advance().unwrap();
 
-                let end = match self.advance() {
-                    Some(Token {
-                        kind: TokenKind::IntLiteral,
-                        position,
-                        value,
-                    }) => Node::new(
-                        TagEnd::Tag(value.parse::<u32>().unwrap()),
-                        position,
-                        &position + value.len(),
-                    ),
-                    Some(Token {
-                        kind: TokenKind::Identifier,
-                        value,
-                        position,
-                    }) if &value == "max" => {
-                        Node::new(TagEnd::Max, position, &position + value.len())
+                let end = match self.peek_kind() {
+                    Some(TokenKind::IntLiteral) => {
+                        let number = self.field_number()?;
+                        Node::new(TagEnd::Tag(number.value), number.start, number.end)
                     }
-                    _ => {
-                        return Err(ParseError::new(
-                            format!("Expected tag end"),
-                            self.tokens.peek().unwrap().position,
-                        ));
+                    Some(TokenKind::Keyword(Keyword::Max)) => {
+                        let token = self.advance().unwrap();
+                        let end = &token.position + token.value.len();
+                        Node::new(TagEnd::Max, token.position, end)
                     }
+                    _ => return Err(self.unexpected_token("tag end")),
                 };
 
                 Some(end)
@@ -1234,14 +1760,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 None
             };
 
-            let range = TagRange {
-                start: Node::new(
-                    start.value.parse::<u32>().unwrap(),
-                    start.position,
-                    &start.position + start.value.len(),
-                ),
-                end,
-            };
+            let range = TagRange { start, end };
             let start = range.start.start;
             let end = range.end.as_ref().map(|n| n.end).unwrap_or(range.start.end);
 
@@ -1261,9 +1780,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         let mut names = Vec::new();
 
         loop {
-            let str_node = self.string()?;
-
-            names.push(str_node);
+            names.push(self.utf8_string()?);
 
             if let Some(TokenKind::Comma) = self.peek_kind() {
                 self.advance().unwrap();
@@ -1276,6 +1793,20 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 }
 
+/// Parses a decimal, octal (`017`) or hexadecimal (`0x1f`) integer literal.
+fn parse_int_literal(value: &str) -> Option<u64> {
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else if value.len() > 1 && value.starts_with('0') {
+        u64::from_str_radix(&value[1..], 8).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::syntax::{
@@ -1284,12 +1815,21 @@ mod tests {
                 FieldCardinality, FieldDeclaration, FieldType, MapFieldDeclaration, MessageElement,
                 OneofDeclaration, OneofElement, OneofField,
             },
-            MapKeyType, Node, RootDeclaration, ScalarType, TypeName,
+            option::{
+                MessageLiteralFieldName, MessageLiteralValue, OptionNamePart, OptionNode,
+                OptionValue,
+            },
+            service::ServiceElement,
+            Edition, EnumElement, ExtensionElement, MapKeyType, Node, RootDeclaration, ScalarType,
+            SyntaxNode, SyntaxType, TagEnd, TagRange, TypeName,
         },
         lexer::tokenize,
+        Position,
     };
 
-    use super::Parser;
+    use proptest::prelude::*;
+
+    use super::{ParseErrorKind, ParseResult, Parser};
 
     #[test]
     fn parse_message() {
@@ -1328,12 +1868,12 @@ mod tests {
             "Expected RootNode::MessageDeclaration. Got: {:?}",
             message
         );
-        let message_node = match message.value {
+        let message_node = match &message.value {
             RootDeclaration::Message(m) => m,
             rn => panic!("Expected RootNode::MessageDeclaration. Got: {:?}", rn),
         };
 
-        assert_eq!(message_node.name, "Test");
+        assert_eq!(message_node.name.value, "Test");
         assert_eq!(message_node.elements.len(), 8);
 
         let field = &message_node.elements[0];
@@ -1573,4 +2113,766 @@ mod tests {
 
         assert!(result.errors.is_empty(), "{:?}", result.errors);
     }
+
+    #[test]
+    fn parse_message_with_comments() {
+        let input = r#"
+            // Leading comment
+            syntax = "proto3"; // trailing comment
+
+            /* Block comment
+             * spanning lines */
+            message Test {
+                int32 a = 1; /* inline */ string b = 2;
+                // message Nope {}
+            }
+        "#;
+
+        let tokens = tokenize(input);
+        let parser = Parser::new(tokens);
+
+        let result = parser.parse("");
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.root.nodes.len(), 2);
+
+        let message = match &result.root.nodes[1].value {
+            RootDeclaration::Message(m) => m,
+            rn => panic!("Expected RootNode::MessageDeclaration. Got: {:?}", rn),
+        };
+
+        assert_eq!(message.elements.len(), 2);
+    }
+
+    #[test]
+    fn parse_unterminated_block_comment() {
+        let input = r#"
+            syntax = "proto3";
+            /* never closed
+        "#;
+
+        let tokens = tokenize(input);
+        let parser = Parser::new(tokens);
+
+        let result = parser.parse("");
+
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert_eq!(result.errors[0].position.line, 2);
+    }
+
+    #[test]
+    fn parse_attaches_comments() {
+        let input = r#"syntax = "proto3";
+
+// Detached comment.
+
+// Leading comment for Test.
+message Test { // Trailing comment for Test.
+    // Leading comment for a.
+    int32 a = 1; // Trailing comment for a.
+
+    /* Leading comment
+     * for b. */
+    string b = 2;
+}
+
+// Leading comment for Kind.
+enum Kind {
+    // Leading comment for UNKNOWN.
+    UNKNOWN = 0; // Trailing comment for UNKNOWN.
+}
+
+// Leading comment for TestService.
+service TestService {
+    // Leading comment for Get.
+    rpc Get (Test) returns (Test); // Trailing comment for Get.
+}
+"#;
+
+        let tokens = tokenize(input);
+        let parser = Parser::new(tokens);
+
+        let result = parser.parse("");
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.root.nodes.len(), 4);
+
+        let message = &result.root.nodes[1];
+        assert_eq!(
+            message.leading_comment(),
+            Some(" Leading comment for Test.\n")
+        );
+        assert_eq!(
+            message.trailing_comment(),
+            Some(" Trailing comment for Test.\n")
+        );
+        assert_eq!(
+            message.comments.as_ref().unwrap().leading_detached,
+            vec![" Detached comment.\n".to_string()]
+        );
+
+        let message = match &message.value {
+            RootDeclaration::Message(m) => m,
+            rn => panic!("Expected RootNode::MessageDeclaration. Got: {:?}", rn),
+        };
+        let field_a = &message.elements[0];
+        assert_eq!(field_a.leading_comment(), Some(" Leading comment for a.\n"));
+        assert_eq!(
+            field_a.trailing_comment(),
+            Some(" Trailing comment for a.\n")
+        );
+        let field_b = &message.elements[1];
+        assert_eq!(
+            field_b.leading_comment(),
+            Some(" Leading comment\n for b. ")
+        );
+        assert_eq!(field_b.trailing_comment(), None);
+
+        let enum_node = &result.root.nodes[2];
+        assert_eq!(
+            enum_node.leading_comment(),
+            Some(" Leading comment for Kind.\n")
+        );
+        let enum_value = match &enum_node.value {
+            RootDeclaration::Enum(e) => &e.elements[0],
+            rn => panic!("Expected RootNode::Enum. Got: {:?}", rn),
+        };
+        assert_eq!(
+            enum_value.leading_comment(),
+            Some(" Leading comment for UNKNOWN.\n")
+        );
+        assert_eq!(
+            enum_value.trailing_comment(),
+            Some(" Trailing comment for UNKNOWN.\n")
+        );
+
+        let service = &result.root.nodes[3];
+        assert_eq!(
+            service.leading_comment(),
+            Some(" Leading comment for TestService.\n")
+        );
+        let method = match &service.value {
+            RootDeclaration::Service(s) => &s.elements[0],
+            rn => panic!("Expected RootNode::Service. Got: {:?}", rn),
+        };
+        assert_eq!(
+            method.leading_comment(),
+            Some(" Leading comment for Get.\n")
+        );
+        assert_eq!(
+            method.trailing_comment(),
+            Some(" Trailing comment for Get.\n")
+        );
+    }
+
+    #[test]
+    fn parse_string_literals() {
+        let input = r#"
+            syntax = 'proto3';
+            import "google/" 'protobuf/any.proto';
+            option java_package = "com.example\x2e" "foo\u00e9";
+        "#;
+
+        let tokens = tokenize(input);
+        let parser = Parser::new(tokens);
+
+        let result = parser.parse("");
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.root.nodes.len(), 3);
+
+        match &result.root.nodes[1].value {
+            RootDeclaration::Import(import) => {
+                assert_eq!(import.package_name.value, "google/protobuf/any.proto")
+            }
+            rn => panic!("Expected RootNode::Import. Got: {:?}", rn),
+        }
+
+        match &result.root.nodes[2].value {
+            RootDeclaration::Option(OptionNode {
+                value:
+                    Node {
+                        value: OptionValue::StringLiteral(literal),
+                        ..
+                    },
+                ..
+            }) => {
+                assert_eq!(literal.as_str(), Ok("com.example.fooé"));
+                assert_eq!(literal.raw, vec![r#""com.example\x2e""#, r#""foo\u00e9""#]);
+            }
+            rn => panic!("Expected RootNode::Option. Got: {:?}", rn),
+        }
+    }
+
+    #[test]
+    fn parse_string_literal_errors() {
+        let input = r#"
+            option bar = "\q";
+            import "foo.proto;
+        "#;
+
+        let tokens = tokenize(input);
+        let parser = Parser::new(tokens);
+
+        let result = parser.parse("");
+
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert!(
+            messages.contains(&"Unterminated string literal"),
+            "{:?}",
+            messages
+        );
+        assert!(
+            messages.contains(&"Invalid escape sequence in string literal"),
+            "{:?}",
+            messages
+        );
+    }
+
+    #[test]
+    fn parse_invalid_escape_after_non_ascii() {
+        let input = r#"option bar = "héllo\q";"#;
+
+        let result = Parser::new(tokenize(input)).parse("");
+
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        let error = &result.errors[0];
+        assert_eq!(&input[error.position.offset..error.end.offset], r"\q");
+        assert_eq!((error.position.column, error.end.column), (19, 21));
+    }
+
+    #[test]
+    fn parse_string_literal_bytes() {
+        let input = r#"
+            import "\xff.proto";
+            option bar = "\xff\x00";
+        "#;
+
+        let result = Parser::new(tokenize(input)).parse("");
+
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["String is not valid UTF-8"]);
+        match &result.root.nodes[1].value {
+            RootDeclaration::Option(OptionNode {
+                value:
+                    Node {
+                        value: OptionValue::StringLiteral(literal),
+                        ..
+                    },
+                ..
+            }) => {
+                assert_eq!(literal.value, vec![0xff, 0x00]);
+                assert!(literal.as_str().is_err());
+            }
+            rn => panic!("Expected RootNode::Option. Got: {:?}", rn),
+        }
+    }
+
+    const EXAMPLE: &str = include_str!("../../../../examples/example.proto");
+
+    fn parse_str(input: &str) -> ParseResult {
+        Parser::new(tokenize(input)).parse("")
+    }
+
+    #[test]
+    fn parse_example() {
+        let result = parse_str(EXAMPLE);
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn parse_truncated_example_never_panics() {
+        for (idx, _) in EXAMPLE.char_indices() {
+            parse_str(&EXAMPLE[..idx]);
+        }
+    }
+
+    #[test]
+    fn parse_recovers_inside_blocks() {
+        let input = r#"
+            message Test {
+                int32 a = ;
+                string b = 2;
+                bogus;
+            }
+
+            enum Kind {
+                A = ;
+                B = 1;
+            }
+
+            service Service {
+                rpc Get (Test) returns (Test) { bogus; option a = 1; }
+                rpc Put (Test) returns (Test);
+            }
+        "#;
+
+        let result = parse_str(input);
+
+        assert_eq!(result.errors.len(), 4, "{:?}", result.errors);
+        assert_eq!(result.root.nodes.len(), 3);
+
+        match &result.root.nodes[0].value {
+            RootDeclaration::Message(m) => assert_eq!(m.elements.len(), 1),
+            rn => panic!("Expected RootNode::MessageDeclaration. Got: {:?}", rn),
+        }
+        match &result.root.nodes[1].value {
+            RootDeclaration::Enum(e) => assert_eq!(e.elements.len(), 1),
+            rn => panic!("Expected RootNode::Enum. Got: {:?}", rn),
+        }
+        match &result.root.nodes[2].value {
+            RootDeclaration::Service(s) => assert_eq!(s.elements.len(), 2),
+            rn => panic!("Expected RootNode::Service. Got: {:?}", rn),
+        }
+    }
+
+    #[test]
+    fn parse_error_ranges_and_kinds() {
+        let input = "message Test {\n  int32 a = 99999999999;\n  string b = bogus;\n";
+
+        let result = parse_str(input);
+        let errors: Vec<_> = result
+            .errors
+            .iter()
+            .map(|err| {
+                (
+                    err.kind,
+                    &input[err.position.offset..err.end.offset],
+                    err.related.len(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                (ParseErrorKind::NumberOutOfRange, "99999999999", 0),
+                (ParseErrorKind::UnexpectedToken, "bogus", 0),
+                (ParseErrorKind::UnclosedBlock, "", 1),
+            ]
+        );
+
+        let related = &result.errors[2].related[0];
+        assert_eq!(related.message, "Block opened here");
+        assert_eq!(&input[related.start.offset..related.end.offset], "{");
+    }
+
+    #[test]
+    fn parse_out_of_range_numbers() {
+        let input = r#"
+            message Test {
+                int32 a = 4294967296;
+                int32 b = 0x10;
+            }
+
+            enum Kind {
+                A = 2147483648;
+                B = -2147483648;
+                C = -0x80000001;
+            }
+        "#;
+
+        let result = parse_str(input);
+
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Field number out of range: 4294967296",
+                "Enum value out of range: 2147483648",
+                "Enum value out of range: 0x80000001",
+            ]
+        );
+
+        // Out of range enum values are still declared.
+        match &result.root.nodes[1].value {
+            RootDeclaration::Enum(e) => {
+                let numbers: Vec<_> = e
+                    .elements
+                    .iter()
+                    .filter_map(|element| match &element.value {
+                        EnumElement::EnumValue { number, .. } => Some(number.value),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(numbers, [2147483648, -2147483648, -2147483649]);
+            }
+            rn => panic!("Expected RootNode::Enum. Got: {:?}", rn),
+        }
+    }
+
+    #[test]
+    fn parse_message_literal_option() {
+        let input = r#"
+            option (my.opt).sub = {
+                name: "x"
+                nested { a: 1 }
+                list: [1, -2, 3.5],
+                msgs [<b: true>, {c: inf}];
+                [pkg.ext]: FOO
+                [type.googleapis.com/pkg.T] { }
+            };
+        "#;
+
+        let result = parse_str(input);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let RootDeclaration::Option(option) = &result.root.nodes[0].value else {
+            panic!("expected option, got {:?}", result.root.nodes[0]);
+        };
+
+        let name: Vec<_> = option.name.value.iter().map(|part| &part.value).collect();
+        assert!(matches!(
+            name.as_slice(),
+            [OptionNamePart::ExtensionName(ext), OptionNamePart::SimpleName(sub)]
+                if ext.parts == ["my", "opt"] && sub == "sub"
+        ));
+
+        let OptionValue::MessageLiteral(literal) = &option.value.value else {
+            panic!("expected message literal, got {:?}", option.value);
+        };
+        assert_eq!(literal.fields.len(), 6);
+
+        let name_field = &literal.fields[0];
+        assert!(matches!(
+            &name_field.value.name.value,
+            MessageLiteralFieldName::Simple(name) if name == "name"
+        ));
+        assert!(matches!(
+            &name_field.value.value.value,
+            MessageLiteralValue::StringLiteral(value) if value.value == b"x"
+        ));
+        assert_eq!(
+            name_field.value.value.start,
+            Position {
+                line: 2,
+                column: 22,
+                offset: input.find("\"x\"").unwrap(),
+            }
+        );
+        assert_eq!(
+            name_field.value.value.end,
+            Position {
+                line: 2,
+                column: 25,
+                offset: input.find("\"x\"").unwrap() + 3,
+            }
+        );
+
+        let MessageLiteralValue::Message(nested) = &literal.fields[1].value.value.value else {
+            panic!("expected nested message, got {:?}", literal.fields[1]);
+        };
+        assert!(matches!(
+            &nested.fields[0].value.value.value,
+            MessageLiteralValue::UintLiteral(value) if value == "1"
+        ));
+
+        let MessageLiteralValue::List(list) = &literal.fields[2].value.value.value else {
+            panic!("expected list, got {:?}", literal.fields[2]);
+        };
+        assert!(matches!(
+            list.iter().map(|value| &value.value).collect::<Vec<_>>().as_slice(),
+            [
+                MessageLiteralValue::UintLiteral(a),
+                MessageLiteralValue::IntLiteral(b),
+                MessageLiteralValue::FloatLiteral(c),
+            ] if a == "1" && b == "-2" && c == "3.5"
+        ));
+
+        let MessageLiteralValue::List(messages) = &literal.fields[3].value.value.value else {
+            panic!("expected list, got {:?}", literal.fields[3]);
+        };
+        assert!(messages
+            .iter()
+            .all(|value| matches!(value.value, MessageLiteralValue::Message(_))));
+
+        assert!(matches!(
+            &literal.fields[4].value.name.value,
+            MessageLiteralFieldName::Extension(ext) if ext.parts == ["pkg", "ext"]
+        ));
+        assert!(matches!(
+            &literal.fields[5].value.name.value,
+            MessageLiteralFieldName::AnyTypeUrl { domain, type_name }
+                if domain == "type.googleapis.com" && type_name.parts == ["pkg", "T"]
+        ));
+    }
+
+    #[test]
+    fn parse_message_literal_errors() {
+        let input = r#"
+            option (a) = { b 1 };
+            option (c) = { d: };
+            message Test {}
+        "#;
+
+        let result = parse_str(input);
+
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Expected ':' between field name and scalar value",
+                "Unexpected token: RBrace. Expected field value",
+            ]
+        );
+        assert!(matches!(
+            result.root.nodes.last().map(|node| &node.value),
+            Some(RootDeclaration::Message(_))
+        ));
+    }
+
+    #[test]
+    fn parse_groups() {
+        let input = r#"
+            syntax = "proto2";
+
+            message SearchResponse {
+                repeated group Result = 1 [deprecated = true] {
+                    required string url = 2;
+                    optional group Inner = 3 {}
+                }
+                oneof choice {
+                    group Choice = 4 {
+                        optional int32 value = 5;
+                    }
+                }
+            }
+
+            extend SearchResponse {
+                optional group Extra = 100 {
+                    optional string note = 101;
+                }
+                optional int32 count = 102;
+            }
+        "#;
+
+        let result = parse_str(input);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let RootDeclaration::Message(message) = &result.root.nodes[1].value else {
+            panic!("expected message, got {:?}", result.root.nodes[1]);
+        };
+
+        let MessageElement::Group(group) = &message.elements[0].value else {
+            panic!("expected group, got {:?}", message.elements[0]);
+        };
+        assert!(matches!(
+            group.cardinality.value,
+            FieldCardinality::Repeated
+        ));
+        assert_eq!(group.name.value, "Result");
+        assert_eq!(group.number.value, 1);
+        assert_eq!(group.options.len(), 1);
+        assert_eq!(group.elements.len(), 2);
+        assert!(matches!(
+            &group.elements[1].value,
+            MessageElement::Group(inner) if inner.name.value == "Inner" && inner.elements.is_empty()
+        ));
+
+        let MessageElement::OneOf(oneof) = &message.elements[1].value else {
+            panic!("expected oneof, got {:?}", message.elements[1]);
+        };
+        assert!(matches!(
+            &oneof.elements[0].value,
+            OneofElement::Group(group) if group.name.value == "Choice" && group.number.value == 4
+        ));
+
+        let RootDeclaration::Extension(extension) = &result.root.nodes[2].value else {
+            panic!("expected extension, got {:?}", result.root.nodes[2]);
+        };
+        assert_eq!(extension.extendee.value.parts, vec!["SearchResponse"]);
+        assert!(matches!(
+            extension.elements.iter().map(|e| &e.value).collect::<Vec<_>>().as_slice(),
+            [ExtensionElement::Group(group), ExtensionElement::Field(field)]
+                if group.name.value == "Extra" && field.name.value == "count"
+        ));
+    }
+
+    #[test]
+    fn parse_group_name_must_be_capitalized() {
+        let result = parse_str("message Test { optional group result = 1 {} }");
+
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["Group names must start with a capital letter"]
+        );
+    }
+
+    #[test]
+    fn parse_extension_ranges_and_nested_extend() {
+        let input = r#"
+            syntax = "proto2";
+
+            message Foo {
+                extensions 100 to 199;
+                extensions 5, 10 to 20;
+                extensions 1000 to max [
+                    declaration = { number: 1000, full_name: ".pkg.bar", type: "int32" },
+                    verification = DECLARATION
+                ];
+
+                extend Foo {
+                    optional int32 bar = 1000;
+                }
+            }
+        "#;
+
+        let result = parse_str(input);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let RootDeclaration::Message(message) = &result.root.nodes[1].value else {
+            panic!("expected message, got {:?}", result.root.nodes[1]);
+        };
+
+        let ranges: Vec<_> = message
+            .elements
+            .iter()
+            .filter_map(|element| match &element.value {
+                MessageElement::ExtensionRange(range) => Some(range),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ranges.len(), 3);
+
+        assert!(matches!(
+            &ranges[0].ranges[0].value,
+            TagRange { start, end: Some(end) }
+                if start.value == 100 && matches!(end.value, TagEnd::Tag(199))
+        ));
+        assert_eq!(ranges[1].ranges.len(), 2);
+        assert!(ranges[1].ranges[0].value.end.is_none());
+        assert!(matches!(
+            ranges[2].ranges[0].value.end.as_ref().map(|end| &end.value),
+            Some(TagEnd::Max)
+        ));
+        assert_eq!(ranges[2].options.len(), 2);
+        assert!(matches!(
+            ranges[2].options[0].value.value.value,
+            OptionValue::MessageLiteral(_)
+        ));
+
+        assert!(message.elements.iter().any(|element| matches!(
+            &element.value,
+            MessageElement::Extension(extension)
+                if extension.extendee.value.parts == ["Foo"] && extension.elements.len() == 1
+        )));
+    }
+
+    #[test]
+    fn parse_edition() {
+        let result = parse_str(r#"edition = "2023"; option features.field_presence = IMPLICIT;"#);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(matches!(
+            &result.root.nodes[0].value,
+            RootDeclaration::Syntax(SyntaxNode {
+                proto_type: SyntaxType::Edition(Edition::Edition2023)
+            })
+        ));
+
+        let result = parse_str(r#"edition = "proto3"; syntax = "2023";"#);
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unsupported edition: \"proto3\"",
+                "Invalid syntax version: \"2023\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_identifier_spans() {
+        let input = r#"
+            package foo.bar;
+            import "other.proto";
+            message Outer {}
+            enum Kind { KIND_ONE = 0; }
+            service Api { rpc Get(Outer) returns (Outer); }
+            extend .foo.bar.Outer {}
+        "#;
+
+        let result = parse_str(input);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let text = |start: Position, end: Position| &input[start.offset..end.offset];
+        let mut spans = Vec::new();
+
+        for node in &result.root.nodes {
+            match &node.value {
+                RootDeclaration::Package(package) => {
+                    spans.push(text(package.package_name.start, package.package_name.end))
+                }
+                RootDeclaration::Import(import) => {
+                    spans.push(text(import.package_name.start, import.package_name.end))
+                }
+                RootDeclaration::Message(message) => {
+                    spans.push(text(message.name.start, message.name.end))
+                }
+                RootDeclaration::Enum(enum_node) => {
+                    spans.push(text(enum_node.name.start, enum_node.name.end));
+                    for element in &enum_node.elements {
+                        if let EnumElement::EnumValue { name, .. } = &element.value {
+                            spans.push(text(name.start, name.end));
+                        }
+                    }
+                }
+                RootDeclaration::Service(service) => {
+                    spans.push(text(service.name.start, service.name.end));
+                    for element in &service.elements {
+                        if let ServiceElement::Method(method) = &element.value {
+                            spans.push(text(method.name.start, method.name.end));
+                        }
+                    }
+                }
+                RootDeclaration::Extension(extension) => {
+                    spans.push(text(extension.extendee.start, extension.extendee.end))
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(
+            spans,
+            vec![
+                "foo.bar",
+                "\"other.proto\"",
+                "Outer",
+                "Kind",
+                "KIND_ONE",
+                "Api",
+                "Get",
+                ".foo.bar.Outer",
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn parse_example_with_deleted_span_never_panics(start in 0..EXAMPLE.len(), len in 0..64usize) {
+            let end = (start + len).min(EXAMPLE.len());
+            if EXAMPLE.is_char_boundary(start) && EXAMPLE.is_char_boundary(end) {
+                let input = format!("{}{}", &EXAMPLE[..start], &EXAMPLE[end..]);
+                parse_str(&input);
+            }
+        }
+
+        #[test]
+        fn parse_token_soup_never_panics(
+            parts in prop::collection::vec(
+                prop::sample::select(vec![
+                    "syntax", "package", "import", "option", "message", "enum", "service",
+                    "rpc", "returns", "stream", "oneof", "map", "reserved", "extend",
+                    "extensions", "to", "max", "repeated", "optional", "int32", "string",
+                    "Foo", ".", "=", ";", ",", "{", "}", "[", "]", "(", ")", "<", ">", "-",
+                    "1", "0x1f", "1.5", "\"str\"", "\"unterminated", "// comment\n",
+                    "/* block", "\n",
+                ]),
+                0..64,
+            )
+        ) {
+            parse_str(&parts.join(" "));
+        }
+    }
 }
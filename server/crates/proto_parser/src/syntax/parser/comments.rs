@@ -0,0 +1,226 @@
+use crate::syntax::lexer::{CommentKind, Token, TokenKind};
+
+/// The comments found between two significant tokens, split the same way
+/// protoc splits them when building `SourceCodeInfo`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CommentSplit {
+    pub trailing: Option<String>,
+    pub detached: Vec<String>,
+    pub leading: Option<String>,
+}
+
+#[derive(Default)]
+struct Collector {
+    buffer: Option<String>,
+    buffer_is_block: bool,
+    can_attach_to_prev: bool,
+    split: CommentSplit,
+}
+
+impl Collector {
+    fn push_line(&mut self, text: String) {
+        if self.buffer_is_block {
+            self.flush();
+        }
+
+        self.buffer.get_or_insert_with(String::new).push_str(&text);
+    }
+
+    fn push_block(&mut self, text: String) {
+        self.flush();
+        self.buffer = Some(text);
+        self.buffer_is_block = true;
+    }
+
+    fn flush(&mut self) {
+        if let Some(comment) = self.buffer.take() {
+            if self.can_attach_to_prev {
+                self.split.trailing = Some(comment);
+                self.can_attach_to_prev = false;
+            } else {
+                self.split.detached.push(comment);
+            }
+        }
+
+        self.buffer_is_block = false;
+    }
+}
+
+/// Splits the trivia between the previous significant token (ending on
+/// `prev_line`) and `next` into a trailing comment for the previous
+/// declaration and detached and leading comments for the next one.
+pub fn split_comments(
+    trivia: &[Token],
+    prev_line: Option<usize>,
+    next: Option<&Token>,
+) -> CommentSplit {
+    let mut collector = Collector::default();
+    let mut tokens = trivia.iter().peekable();
+
+    if prev_line.is_some() {
+        collector.can_attach_to_prev = true;
+
+        match tokens.peek().map(|token| token.kind) {
+            Some(TokenKind::Comment(CommentKind::Line)) => {
+                let token = tokens.next().unwrap();
+                collector.push_line(line_comment_text(&token.value));
+                tokens.next_if(|token| token.kind == TokenKind::NewLine);
+                collector.flush();
+            }
+            Some(TokenKind::Comment(_)) => {
+                let token = tokens.next().unwrap();
+                collector.push_block(block_comment_text(&token.value));
+                if tokens
+                    .next_if(|token| token.kind == TokenKind::NewLine)
+                    .is_none()
+                {
+                    // The next token is on the same line, so there is no way
+                    // of knowing which token the comment belongs to.
+                    return CommentSplit::default();
+                }
+                collector.flush();
+            }
+            Some(TokenKind::NewLine) => {
+                tokens.next();
+            }
+            _ => return CommentSplit::default(),
+        }
+    }
+
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            TokenKind::Comment(CommentKind::Line) => {
+                collector.push_line(line_comment_text(&token.value));
+                tokens.next_if(|token| token.kind == TokenKind::NewLine);
+            }
+            TokenKind::Comment(_) => {
+                collector.push_block(block_comment_text(&token.value));
+                tokens.next_if(|token| token.kind == TokenKind::NewLine);
+            }
+            _ => {
+                // A blank line ends the current comment block and detaches
+                // everything after it from the previous declaration.
+                collector.flush();
+                collector.can_attach_to_prev = false;
+            }
+        }
+    }
+
+    let end_of_scope = match next {
+        Some(token) => matches!(
+            token.kind,
+            TokenKind::RBrace | TokenKind::RBracket | TokenKind::RParen | TokenKind::Eof
+        ),
+        None => true,
+    };
+
+    if end_of_scope {
+        collector.flush();
+    }
+
+    collector.split.leading = collector.buffer.take();
+    collector.split
+}
+
+fn line_comment_text(value: &str) -> String {
+    format!("{}\n", value.strip_prefix("//").unwrap_or(value))
+}
+
+fn block_comment_text(value: &str) -> String {
+    let value = value.strip_prefix("/*").unwrap_or(value);
+    let value = value.strip_suffix("*/").unwrap_or(value);
+
+    value
+        .split('\n')
+        .enumerate()
+        .map(|(idx, line)| {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if idx == 0 {
+                return line;
+            }
+
+            let trimmed = line.trim_start();
+            trimmed.strip_prefix('*').unwrap_or(trimmed)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::lexer::{tokenize, Token, TokenKind};
+
+    use super::{split_comments, CommentSplit};
+
+    fn split(input: &str, has_prev: bool) -> CommentSplit {
+        let tokens: Vec<Token> = tokenize(input).collect();
+        let end = tokens
+            .iter()
+            .position(|token| !matches!(token.kind, TokenKind::NewLine | TokenKind::Comment(_)))
+            .unwrap_or(tokens.len());
+
+        split_comments(&tokens[..end], has_prev.then_some(0), tokens.get(end))
+    }
+
+    #[test]
+    fn trailing_on_same_line() {
+        let actual = split(" // trailing\n  // leading\n  x", true);
+
+        assert_eq!(actual.trailing.as_deref(), Some(" trailing\n"));
+        assert!(actual.detached.is_empty());
+        assert_eq!(actual.leading.as_deref(), Some(" leading\n"));
+    }
+
+    #[test]
+    fn trailing_on_next_line_followed_by_blank_line() {
+        let actual = split("\n  // one\n  // two\n\n  x", true);
+
+        assert_eq!(actual.trailing.as_deref(), Some(" one\n two\n"));
+        assert!(actual.detached.is_empty());
+        assert_eq!(actual.leading, None);
+    }
+
+    #[test]
+    fn detached_comments() {
+        let actual = split(
+            "\n\n  // first\n\n  /* second */\n\n  // leading\n  x",
+            true,
+        );
+
+        assert_eq!(actual.trailing, None);
+        assert_eq!(actual.detached, vec![" first\n", " second "]);
+        assert_eq!(actual.leading.as_deref(), Some(" leading\n"));
+    }
+
+    #[test]
+    fn line_comment_strips_only_the_comment_start() {
+        let actual = split("/// Doc\n//// banner\n x", false);
+
+        assert_eq!(actual.leading.as_deref(), Some("/ Doc\n// banner\n"));
+    }
+
+    #[test]
+    fn block_comment_strips_asterisks() {
+        let actual = split("/* Block comment\n   * over lines. */\n x", false);
+
+        assert_eq!(
+            actual.leading.as_deref(),
+            Some(" Block comment\n over lines. ")
+        );
+    }
+
+    #[test]
+    fn block_comment_before_token_on_same_line() {
+        let actual = split(" /* ambiguous */ x", true);
+
+        assert_eq!(actual, CommentSplit::default());
+    }
+
+    #[test]
+    fn comment_before_end_of_scope_attaches_to_previous() {
+        let actual = split("\n  // dangling\n}", true);
+
+        assert_eq!(actual.trailing.as_deref(), Some(" dangling\n"));
+        assert_eq!(actual.leading, None);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::syntax::{
+    ast::{
+        option::{
+            MessageLiteral, MessageLiteralField, MessageLiteralFieldName, MessageLiteralValue,
+        },
+        Node, TypeName,
+    },
+    lexer::{Token, TokenKind},
+};
+
+use super::{ParseError, Parser, Result};
+
+impl<I> Parser<I>
+where
+    I: Iterator<Item = Token>,
+{
+    /// Parses an aggregate option value written in protobuf text format,
+    /// delimited either by `{ ... }` or `< ... >`.
+    pub(super) fn message_literal(&mut self) -> Result<Node<MessageLiteral>> {
+        let (open, close) = match self.peek_kind() {
+            Some(TokenKind::LBrace) => (TokenKind::LBrace, TokenKind::RBrace),
+            Some(TokenKind::LAngle) => (TokenKind::LAngle, TokenKind::RAngle),
+            _ => return Err(self.unexpected_token("message literal")),
+        };
+
+        let open = self.expect(open)?;
+        let start = open.position;
+        let mut fields = Vec::new();
+
+        loop {
+            match self.peek_kind() {
+                Some(kind) if kind == close => break,
+                _ if self.at_block_end() => break,
+                _ => {}
+            }
+
+            match self.message_literal_field() {
+                Ok(field) => fields.push(field),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.sync_element();
+                    continue;
+                }
+            }
+
+            if let Some(TokenKind::Comma | TokenKind::SemiColon) = self.peek_kind() {
+                self.advance();
+            }
+        }
+
+        let end = &self.expect_closing(&open, close)?.position + 1;
+
+        Ok(Node::new(MessageLiteral { fields }, start, end))
+    }
+
+    fn message_literal_field(&mut self) -> Result<Node<MessageLiteralField>> {
+        let name = self.message_literal_field_name()?;
+
+        let has_colon = if let Some(TokenKind::Colon) = self.peek_kind() {
+            self.advance();
+            true
+        } else {
+            false
+        };
+
+        let value = match self.peek_kind() {
+            Some(TokenKind::LBrace | TokenKind::LAngle) => {
+                let message = self.message_literal()?;
+                Node::new(
+                    MessageLiteralValue::Message(message.value),
+                    message.start,
+                    message.end,
+                )
+            }
+            Some(TokenKind::LBracket) => self.message_literal_list(has_colon)?,
+            _ => {
+                if !has_colon {
+                    let position = self.peek_position();
+                    self.errors.push(ParseError::new(
+                        "Expected ':' between field name and scalar value".to_string(),
+                        position,
+                    ));
+                }
+                self.message_literal_scalar()?
+            }
+        };
+
+        let start = name.start;
+        let end = value.end;
+
+        Ok(Node::new(MessageLiteralField { name, value }, start, end))
+    }
+
+    fn message_literal_field_name(&mut self) -> Result<Node<MessageLiteralFieldName>> {
+        if let Some(TokenKind::LBracket) = self.peek_kind() {
+            let start = self.advance().unwrap().position;
+            let first = self.message_literal_type_name()?;
+
+            let name = if let Some(TokenKind::Slash) = self.peek_kind() {
+                // Everything up to the last `/` is the domain of an `Any` type URL,
+                // e.g. `type.googleapis.com/pkg.Type`.
+                let mut domain = first.value.parts.join(".");
+                let mut type_name = loop {
+                    self.advance();
+                    let part = self.message_literal_type_name()?;
+                    if let Some(TokenKind::Slash) = self.peek_kind() {
+                        domain.push('/');
+                        domain.push_str(&part.value.parts.join("."));
+                    } else {
+                        break part;
+                    }
+                };
+                type_name.value.absolute = false;
+
+                MessageLiteralFieldName::AnyTypeUrl {
+                    domain,
+                    type_name: type_name.value,
+                }
+            } else {
+                MessageLiteralFieldName::Extension(first.value)
+            };
+
+            let end = &self.expect(TokenKind::RBracket)?.position + 1;
+
+            return Ok(Node::new(name, start, end));
+        }
+
+        let identifier = self.message_literal_identifier()?;
+        let end = &identifier.position + identifier.value.len();
+
+        Ok(Node::new(
+            MessageLiteralFieldName::Simple(identifier.value),
+            identifier.position,
+            end,
+        ))
+    }
+
+    /// Parses a dotted name inside `[...]`. Unlike regular type names, any
+    /// keyword is allowed as a name part.
+    fn message_literal_type_name(&mut self) -> Result<Node<TypeName>> {
+        let dot = if let Some(TokenKind::Dot) = self.peek_kind() {
+            self.advance()
+        } else {
+            None
+        };
+
+        let identifier = self.message_literal_identifier()?;
+        let start = dot
+            .as_ref()
+            .map(|token| token.position)
+            .unwrap_or(identifier.position);
+        let mut end = &identifier.position + identifier.value.len();
+        let mut parts = vec![identifier.value];
+
+        while let Some(TokenKind::Dot) = self.peek_kind() {
+            self.advance();
+            let identifier = self.message_literal_identifier()?;
+            end = &identifier.position + identifier.value.len();
+            parts.push(identifier.value);
+        }
+
+        let name = TypeName {
+            absolute: dot.is_some(),
+            parts,
+        };
+
+        Ok(Node::new(name, start, end))
+    }
+
+    fn message_literal_list(&mut self, has_colon: bool) -> Result<Node<MessageLiteralValue>> {
+        let start = self.expect(TokenKind::LBracket)?.position;
+        let mut values = Vec::new();
+
+        if !matches!(self.peek_kind(), Some(TokenKind::RBracket)) {
+            loop {
+                let value = match self.peek_kind() {
+                    Some(TokenKind::LBrace | TokenKind::LAngle) => {
+                        let message = self.message_literal()?;
+                        Node::new(
+                            MessageLiteralValue::Message(message.value),
+                            message.start,
+                            message.end,
+                        )
+                    }
+                    _ => {
+                        if !has_colon && values.is_empty() {
+                            self.errors.push(ParseError::new(
+                                "Expected ':' between field name and list of scalar values"
+                                    .to_string(),
+                                start,
+                            ));
+                        }
+                        self.message_literal_scalar()?
+                    }
+                };
+                values.push(value);
+
+                match self.peek_kind() {
+                    Some(TokenKind::Comma) => {
+                        self.advance();
+                    }
+                    Some(TokenKind::RBracket) => break,
+                    _ => return Err(self.unexpected_token("',' or ']'")),
+                }
+            }
+        }
+
+        let end = &self.expect(TokenKind::RBracket)?.position + 1;
+
+        Ok(Node::new(MessageLiteralValue::List(values), start, end))
+    }
+
+    fn message_literal_scalar(&mut self) -> Result<Node<MessageLiteralValue>> {
+        match self.peek_kind() {
+            Some(TokenKind::String | TokenKind::UnterminatedString) => {
+                let literal = self.string()?;
+                Ok(Node::new(
+                    MessageLiteralValue::StringLiteral(literal.value),
+                    literal.start,
+                    literal.end,
+                ))
+            }
+            Some(TokenKind::Minus) => {
+                let start = self.advance().unwrap().position;
+                let token = match self.peek_kind() {
+                    Some(TokenKind::IntLiteral | TokenKind::FloatLiteral) => {
+                        self.advance().unwrap()
+                    }
+                    Some(TokenKind::Identifier | TokenKind::Keyword(_)) => {
+                        self.message_literal_identifier()?
+                    }
+                    _ => return Err(self.unexpected_token("number after '-'")),
+                };
+                let end = &token.position + token.value.len();
+                let value = format!("-{}", token.value);
+                let value = match token.kind {
+                    TokenKind::IntLiteral => MessageLiteralValue::IntLiteral(value),
+                    TokenKind::FloatLiteral => MessageLiteralValue::FloatLiteral(value),
+                    _ => MessageLiteralValue::Identifier(value),
+                };
+                Ok(Node::new(value, start, end))
+            }
+            Some(TokenKind::IntLiteral) => {
+                let token = self.advance().unwrap();
+                let end = &token.position + token.value.len();
+                Ok(Node::new(
+                    MessageLiteralValue::UintLiteral(token.value),
+                    token.position,
+                    end,
+                ))
+            }
+            Some(TokenKind::FloatLiteral) => {
+                let token = self.advance().unwrap();
+                let end = &token.position + token.value.len();
+                Ok(Node::new(
+                    MessageLiteralValue::FloatLiteral(token.value),
+                    token.position,
+                    end,
+                ))
+            }
+            Some(TokenKind::Identifier | TokenKind::Keyword(_)) => {
+                let token = self.advance().unwrap();
+                let end = &token.position + token.value.len();
+                Ok(Node::new(
+                    MessageLiteralValue::Identifier(token.value),
+                    token.position,
+                    end,
+                ))
+            }
+            _ => Err(self.unexpected_token("field value")),
+        }
+    }
+
+    /// Text format field names and enum values may be any identifier,
+    /// including words that are keywords in the `.proto` grammar.
+    fn message_literal_identifier(&mut self) -> Result<Token> {
+        match self.peek_kind() {
+            Some(TokenKind::Identifier | TokenKind::Keyword(_)) => Ok(self.advance().unwrap()),
+            _ => Err(self.unexpected_token("identifier")),
+        }
+    }
+}
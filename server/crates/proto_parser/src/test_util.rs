@@ -0,0 +1,14 @@
+use crate::{Position, PositionEncoding, Source};
+
+/// The position of the byte `offset` in `file`.
+pub(crate) fn position_at(source: &Source, file: &str, offset: usize) -> Position {
+    let (line, column) = source
+        .line_index(file)
+        .unwrap()
+        .line_column(offset, PositionEncoding::Utf32);
+    Position {
+        line,
+        column,
+        offset,
+    }
+}
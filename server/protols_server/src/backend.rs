@@ -1,23 +1,132 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use protols::parser::Source;
+use protols::parser::{
+    completion::CompletionKind, symbols::Location, well_known_types, LineIndex, Position,
+    PositionEncoding, Source,
+};
 use tokio::sync::RwLock;
-use tower_lsp::jsonrpc::Result;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
-    DidSaveTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, InitializedParams,
-    MessageType, TextDocumentSyncCapability, TextDocumentSyncKind,
+    self, CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams,
+    CompletionResponse, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, FileChangeType, FileSystemWatcher,
+    GlobPattern, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, InitializedParams, MarkupContent, MarkupKind, MessageType, OneOf,
+    PositionEncodingKind, ReferenceParams, Registration, SaveOptions, TextDocumentIdentifier,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, Url,
 };
 use tower_lsp::{
     lsp_types::{InitializeParams, InitializeResult, ServerCapabilities},
     Client, LanguageServer,
 };
 
+use self::{documents::Documents, progress::Progress, workspace::Workspace};
+
+mod config;
+mod diagnostics;
+mod documents;
+mod progress;
+mod workspace;
+
 pub struct ProtoLanguageServer(Arc<RwLock<Backend>>);
 
 impl ProtoLanguageServer {
-    pub fn new(client: Client) -> Self {
-        Self(Arc::new(RwLock::new(Backend::new(client))))
+    /// `include_paths` are the include paths given on the command line.
+    pub fn new(client: Client, include_paths: Vec<PathBuf>) -> Self {
+        Self(Arc::new(RwLock::new(Backend::new(client, include_paths))))
+    }
+
+    /// Asks the client to tell the server about changes to `.proto` files,
+    /// including the ones made outside the editor.
+    async fn watch_files(&self) {
+        let backend = self.0.read().await;
+        if !backend.watch_files {
+            return;
+        }
+
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.proto".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "protols/watchedFiles".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(err) = backend.client.register_capability(vec![registration]).await {
+            log::warn!("failed to watch files: {err}");
+        }
+    }
+
+    /// Parses every `.proto` file in the workspace. The files are parsed in
+    /// batches, so requests are answered in between and the client sees the
+    /// progress.
+    async fn index_workspace(&self) {
+        const BATCH_SIZE: usize = 100;
+
+        let (client, work_done_progress, workspace) = {
+            let backend = self.0.read().await;
+            let workspace = backend.workspace.clone();
+            (
+                backend.client.clone(),
+                backend.work_done_progress,
+                workspace,
+            )
+        };
+
+        let progress = Progress::begin(
+            &client,
+            work_done_progress,
+            "protols/indexing",
+            "Indexing workspace",
+        )
+        .await;
+
+        let files = tokio::task::spawn_blocking(move || read_files(workspace.proto_files()))
+            .await
+            .unwrap_or_default();
+        let total = files.len();
+        log::info!("indexing {total} files");
+
+        // Files are parsed without holding the lock, so requests are
+        // answered meanwhile, and only loaded into `Source` at the end.
+        let mut parsed = Vec::with_capacity(total);
+        let mut files = files.into_iter().peekable();
+        while files.peek().is_some() {
+            let batch: Vec<_> = files.by_ref().take(BATCH_SIZE).collect();
+            let batch = tokio::task::spawn_blocking(move || Source::parse_files(batch))
+                .await
+                .unwrap_or_default();
+            parsed.extend(batch);
+
+            let percentage = (parsed.len() * 100 / total) as u32;
+            progress
+                .report(format!("{}/{total} files", parsed.len()), percentage)
+                .await;
+        }
+
+        let mut backend = self.0.write().await;
+        // Documents opened while indexing have newer text than the disk.
+        parsed.retain(|(path, _)| !backend.documents.is_open(path));
+        backend.source.insert_all(parsed);
+        progress.end(format!("Indexed {total} files")).await;
+        backend.publish_diagnostics().await;
+    }
+
+    /// The `protols/virtualFile` request: returns the text of a bundled file,
+    /// so the client can show the targets of go-to-definition that don't
+    /// exist on disk.
+    pub async fn virtual_file(&self, params: TextDocumentIdentifier) -> Result<String> {
+        well_known_types::source(params.uri.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| Error::invalid_params(format!("unknown file {}", params.uri)))
     }
 }
 
@@ -25,22 +134,179 @@ impl ProtoLanguageServer {
 pub struct Backend {
     pub client: Client,
     pub source: Source,
+    pub documents: Documents,
+    pub position_encoding: PositionEncoding,
+    pub workspace: Workspace,
+    /// Whether the client supports `window/workDoneProgress`.
+    pub work_done_progress: bool,
+    /// Whether the client can watch files for the server.
+    pub watch_files: bool,
 }
 
 impl Backend {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, include_paths: Vec<PathBuf>) -> Self {
+        let mut source = Source::new();
+        source.set_include_paths(include_paths);
+
         Self {
             client,
-            source: Source::new(),
+            source,
+            documents: Documents::default(),
+            position_encoding: PositionEncoding::default(),
+            workspace: Workspace::default(),
+            work_done_progress: false,
+            watch_files: false,
         }
     }
 
+    /// Converts an LSP position in the negotiated encoding to a `Position`.
+    /// Files that haven't been parsed yet are assumed to be ASCII.
+    pub fn position(&self, file: &str, position: lsp_types::Position) -> Position {
+        let line = position.line as usize;
+        let character = position.character as usize;
+
+        match self.source.line_index(file) {
+            Some(index) => index.position(line, character, self.position_encoding),
+            None => Position {
+                line,
+                column: character,
+                offset: 0,
+            },
+        }
+    }
+
+    /// Publishes the diagnostics of every open document, since a change to
+    /// one file can fix or break references in the others.
+    pub async fn publish_diagnostics(&self) {
+        for path in self.documents.paths() {
+            let uri = file_uri(path);
+            let diagnostics =
+                diagnostics::file_diagnostics(&self.source, &uri, self.position_encoding);
+            let version = self.documents.version(path);
+
+            self.client
+                .publish_diagnostics(uri, diagnostics, version)
+                .await;
+        }
+    }
+
+    /// Converts a location in a parsed file to an LSP location.
+    pub fn location(&self, location: &Location) -> Option<lsp_types::Location> {
+        let index = self.source.line_index(&location.file)?;
+
+        Some(lsp_types::Location::new(
+            file_uri(&location.file),
+            lsp_range(index, location.start, location.end, self.position_encoding),
+        ))
+    }
+
+    pub fn did_open(&mut self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        let path = &file_name(&document.uri);
+        log::debug!("did open {path} version {}", document.version);
+
+        self.documents
+            .open(&mut self.source, path, document.version, &document.text);
+    }
+
+    /// A change that can't be applied closes the document. The file on disk
+    /// is used instead until the client opens it again with its full text.
+    pub async fn did_change(&mut self, params: DidChangeTextDocumentParams) {
+        let document = params.text_document;
+        let path = &file_name(&document.uri);
+        log::debug!("did change {path} version {}", document.version);
+
+        let was_open = self.documents.is_open(path);
+        let changed = self.documents.change(
+            &mut self.source,
+            self.position_encoding,
+            path,
+            document.version,
+            params.content_changes,
+        );
+        if changed {
+            return;
+        }
+        match self.documents.version(path) {
+            Some(version) => log::warn!(
+                "ignoring change to {path} version {}, open version is {version}",
+                document.version,
+            ),
+            None if was_open => {
+                log::warn!("{path} is out of sync, reading it from disk until it's reopened");
+                self.parse_from_disk(path).await;
+                self.client
+                    .publish_diagnostics(document.uri, Vec::new(), None)
+                    .await;
+                self.client
+                    .show_message(
+                        MessageType::WARNING,
+                        format!("{path} is out of sync, reopen it to update diagnostics"),
+                    )
+                    .await;
+            }
+            None => log::warn!("ignoring change to {path}, it isn't open"),
+        }
+    }
+
+    /// Once the client closes a document, the file on disk is the source of
+    /// truth again. Its diagnostics are cleared since the client only shows
+    /// them for open documents.
+    pub async fn did_close(&mut self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let path = &file_name(&uri);
+        log::debug!("did close {path}");
+
+        if self.documents.close(path) {
+            self.parse_from_disk(path).await;
+        }
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    /// Open documents are already up to date, only files that aren't open
+    /// are read from disk.
     pub async fn did_save(&mut self, params: DidSaveTextDocumentParams) {
-        let path = params.text_document.uri.path();
+        let path = &file_name(&params.text_document.uri);
         log::debug!("did save {path}");
-        let file = tokio::fs::read_to_string(path).await.unwrap();
 
-        self.source.parse(path, &file);
+        if !self.documents.is_open(path) {
+            self.parse_from_disk(path).await;
+        }
+    }
+
+    /// Files that are open are up to date already. Other files are read
+    /// again if they are indexed, or loaded because something imports them.
+    pub async fn did_change_watched_files(&mut self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            let path = &file_name(&change.uri);
+            log::debug!("watched file {path} changed: {:?}", change.typ);
+            if self.documents.is_open(path) {
+                continue;
+            }
+
+            if change.typ == FileChangeType::DELETED {
+                self.source.remove(path);
+            } else if self.workspace.contains(Path::new(path))
+                || self.source.parse_result(path).is_some()
+            {
+                self.parse_from_disk(path).await;
+            }
+        }
+    }
+
+    async fn parse_from_disk(&mut self, path: &str) {
+        if let Some(text) = well_known_types::source(path) {
+            self.source.parse(path, text);
+            return;
+        }
+
+        match tokio::fs::read_to_string(path).await {
+            Ok(file) => self.source.parse(path, &file),
+            Err(err) => {
+                log::warn!("failed to read {path}: {err}");
+                self.source.remove(path);
+            }
+        }
     }
 }
 
@@ -48,17 +314,64 @@ impl Backend {
 impl LanguageServer for ProtoLanguageServer {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
         log::info!("initialize");
-        dbg!(params);
+        log::debug!("{params:?}");
+
+        let position_encoding = negotiate_position_encoding(&params);
+        let mut backend = self.0.write().await;
+        backend.position_encoding = position_encoding;
+
+        let options = params.initialization_options.as_ref();
+        let folders = config::workspace_folders(&params);
+        let include_paths =
+            config::include_paths(backend.source.include_paths(), options, &folders);
+        log::info!("include paths: {include_paths:?}");
+        backend.source.set_include_paths(include_paths);
+        backend.workspace = Workspace::new(
+            folders
+                .iter()
+                .map(|folder| (folder.root.clone(), config::excludes(options, folder)))
+                .collect(),
+        );
+
+        let capabilities = &params.capabilities;
+        backend.work_done_progress = capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        backend.watch_files = capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+            .and_then(|watched_files| watched_files.dynamic_registration)
+            .unwrap_or(false);
+        drop(backend);
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
+                position_encoding: Some(match position_encoding {
+                    PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+                    PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+                    PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+                }),
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                            include_text: Some(false),
+                        })),
+                        ..Default::default()
+                    },
                 )),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
+                    trigger_characters: Some([".", "(", "[", "<"].map(str::to_string).to_vec()),
                     ..Default::default()
                 }),
+                definition_provider: Some(OneOf::Left(true)),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                references_provider: Some(OneOf::Left(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -74,29 +387,61 @@ impl LanguageServer for ProtoLanguageServer {
             .await;
 
         log::info!("initialized");
+
+        self.watch_files().await;
+        self.index_workspace().await;
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let mut backend = self.0.write().await;
+        backend.did_open(params);
+        backend.publish_diagnostics().await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let mut backend = self.0.write().await;
+        backend.did_change(params).await;
+        backend.publish_diagnostics().await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let mut backend = self.0.write().await;
+        backend.did_close(params).await;
+        backend.publish_diagnostics().await;
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        self.0.write().await.did_save(params).await;
+        let mut backend = self.0.write().await;
+        backend.did_save(params).await;
+        backend.publish_diagnostics().await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let mut backend = self.0.write().await;
+        backend.did_change_watched_files(params).await;
+        backend.publish_diagnostics().await;
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         log::debug!("completion");
-        let line = params.text_document_position.position.line as usize;
-        let column = params.text_document_position.position.character as usize;
+        let backend = self.0.read().await;
+        let params = params.text_document_position;
+        let path = &file_name(&params.text_document.uri);
+        let position = backend.position(path, params.position);
 
-        let items = self.0.read().await.source.completions(line, column);
+        let items = backend
+            .source
+            .completions(path, position)
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.label,
+                kind: Some(completion_item_kind(completion.kind)),
+                detail: completion.detail,
+                ..Default::default()
+            })
+            .collect();
 
-        Ok(Some(CompletionResponse::Array(
-            items
-                .iter()
-                .map(|item| CompletionItem {
-                    label: item.to_string(),
-                    kind: Some(CompletionItemKind::STRUCT),
-                    ..Default::default()
-                })
-                .collect(),
-        )))
+        Ok(Some(CompletionResponse::Array(items)))
     }
 
     async fn goto_definition(
@@ -104,8 +449,66 @@ impl LanguageServer for ProtoLanguageServer {
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
         log::debug!("goto_definition");
+        let backend = self.0.read().await;
+        let params = params.text_document_position_params;
+        let path = &file_name(&params.text_document.uri);
+        let position = backend.position(path, params.position);
+
+        let Some(location) = backend.source.goto_definition(path, position) else {
+            return Ok(None);
+        };
 
-        todo!()
+        Ok(backend
+            .location(&location)
+            .map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        log::debug!("hover");
+        let backend = self.0.read().await;
+        let params = params.text_document_position_params;
+        let path = &file_name(&params.text_document.uri);
+        let position = backend.position(path, params.position);
+
+        let (Some(hover), Some(index)) = (
+            backend.source.hover(path, position),
+            backend.source.line_index(path),
+        ) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: hover.contents,
+            }),
+            range: Some(lsp_range(
+                index,
+                hover.start,
+                hover.end,
+                backend.position_encoding,
+            )),
+        }))
+    }
+
+    async fn references(
+        &self,
+        params: ReferenceParams,
+    ) -> Result<Option<Vec<lsp_types::Location>>> {
+        log::debug!("references");
+        let backend = self.0.read().await;
+        let include_declaration = params.context.include_declaration;
+        let params = params.text_document_position;
+        let path = &file_name(&params.text_document.uri);
+        let position = backend.position(path, params.position);
+
+        let locations = backend
+            .source
+            .references(path, position, include_declaration)
+            .iter()
+            .filter_map(|location| backend.location(location))
+            .collect();
+        Ok(Some(locations))
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -113,3 +516,90 @@ impl LanguageServer for ProtoLanguageServer {
         Ok(())
     }
 }
+
+/// Reads the files at `paths`, skipping the ones that can't be read.
+fn read_files(paths: Vec<PathBuf>) -> Vec<(String, String)> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.to_str()?.to_string();
+            match std::fs::read_to_string(&path) {
+                Ok(text) => Some((name, text)),
+                Err(err) => {
+                    log::warn!("failed to read {name}: {err}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The name `Source` knows the document at `uri` by: the file system path
+/// of a `file://` URI, the same name files read from disk get, or the whole
+/// URI of a bundled file.
+fn file_name(uri: &Url) -> String {
+    uri.to_file_path()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| uri.to_string())
+}
+
+/// The URI of a file in `Source`, the inverse of `file_name`.
+fn file_uri(file: &str) -> Url {
+    if well_known_types::is_bundled(file) {
+        return Url::parse(file).expect("valid URI");
+    }
+
+    Url::from_file_path(file).unwrap_or_else(|()| {
+        let mut uri = Url::parse("file:///").expect("valid URI");
+        uri.set_path(file);
+        uri
+    })
+}
+
+/// Converts a span to an LSP range in the negotiated encoding.
+fn lsp_range(
+    index: &LineIndex,
+    start: Position,
+    end: Position,
+    encoding: PositionEncoding,
+) -> lsp_types::Range {
+    let position = |position: Position| {
+        let (line, character) = index.encode(position, encoding);
+        lsp_types::Position::new(line as u32, character as u32)
+    };
+
+    lsp_types::Range::new(position(start), position(end))
+}
+
+fn completion_item_kind(kind: CompletionKind) -> CompletionItemKind {
+    match kind {
+        CompletionKind::Keyword | CompletionKind::Scalar => CompletionItemKind::KEYWORD,
+        CompletionKind::Package => CompletionItemKind::MODULE,
+        CompletionKind::Message => CompletionItemKind::STRUCT,
+        CompletionKind::Enum => CompletionItemKind::ENUM,
+        CompletionKind::EnumValue => CompletionItemKind::ENUM_MEMBER,
+        CompletionKind::Option | CompletionKind::Extension => CompletionItemKind::PROPERTY,
+        CompletionKind::Value => CompletionItemKind::VALUE,
+    }
+}
+
+/// Picks the first encoding the client supports, preferring UTF-8 since
+/// offsets are stored in bytes. Clients that don't say only support UTF-16.
+fn negotiate_position_encoding(params: &InitializeParams) -> PositionEncoding {
+    let encodings = params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_deref())
+        .unwrap_or_default();
+
+    [
+        (PositionEncodingKind::UTF8, PositionEncoding::Utf8),
+        (PositionEncodingKind::UTF32, PositionEncoding::Utf32),
+    ]
+    .into_iter()
+    .find(|(kind, _)| encodings.contains(kind))
+    .map(|(_, encoding)| encoding)
+    .unwrap_or(PositionEncoding::Utf16)
+}
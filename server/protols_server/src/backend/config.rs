@@ -0,0 +1,227 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use tower_lsp::lsp_types::{InitializeParams, LSPAny};
+
+/// The workspace configuration file, looked up in each workspace folder.
+pub const CONFIG_FILE: &str = "protols.toml";
+
+/// The settings of a workspace folder's `protols.toml`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// `include_paths = [...]`, relative to the folder.
+    pub include_paths: Vec<String>,
+    /// `excludes = [...]`, `.gitignore` patterns of files not to index.
+    pub excludes: Vec<String>,
+}
+
+/// A folder of the workspace the client opened, with its configuration.
+#[derive(Debug)]
+pub struct WorkspaceFolder {
+    pub root: PathBuf,
+    pub config: Config,
+}
+
+/// The folders of the workspace the client opened.
+pub fn workspace_folders(params: &InitializeParams) -> Vec<WorkspaceFolder> {
+    let roots: Vec<PathBuf> = match &params.workspace_folders {
+        Some(folders) if !folders.is_empty() => folders
+            .iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect(),
+        #[allow(deprecated)]
+        _ => params
+            .root_uri
+            .iter()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect(),
+    };
+
+    roots
+        .into_iter()
+        .map(|root| {
+            let config = read_config(&root.join(CONFIG_FILE));
+            WorkspaceFolder { root, config }
+        })
+        .collect()
+}
+
+/// Collects the include paths imports are resolved against, in the order
+/// they are searched: the paths given on the command line, then the ones in
+/// the client's `initializationOptions` (`{ "includePaths": [...] }`), then
+/// the ones in each workspace folder's `protols.toml`
+/// (`include_paths = [...]`), and finally the workspace folders themselves.
+///
+/// Relative paths from the client are relative to the first workspace
+/// folder, the ones from a config file to the folder the file is in.
+pub fn include_paths(
+    cli: &[PathBuf],
+    options: Option<&LSPAny>,
+    folders: &[WorkspaceFolder],
+) -> Vec<PathBuf> {
+    let mut include_paths = cli.to_vec();
+
+    let base = folders.first().map(|folder| folder.root.as_path());
+    let options = strings(options, "includePaths").map(|path| match base {
+        Some(base) => base.join(path),
+        None => PathBuf::from(path),
+    });
+    include_paths.extend(options);
+
+    for folder in folders {
+        let paths = &folder.config.include_paths;
+        include_paths.extend(paths.iter().map(|path| folder.root.join(path)));
+    }
+
+    include_paths.extend(folders.iter().map(|folder| folder.root.clone()));
+
+    let mut seen = HashSet::new();
+    include_paths.retain(|path| seen.insert(path.clone()));
+    include_paths
+}
+
+/// The patterns of files not to index in `folder`: the client's
+/// `initializationOptions` (`{ "excludes": [...] }`), which apply to every
+/// folder, followed by the folder's `protols.toml` (`excludes = [...]`).
+pub fn excludes(options: Option<&LSPAny>, folder: &WorkspaceFolder) -> Vec<String> {
+    strings(options, "excludes")
+        .map(str::to_string)
+        .chain(folder.config.excludes.iter().cloned())
+        .collect()
+}
+
+/// The strings in the array `key` of the initialization options.
+fn strings<'a>(options: Option<&'a LSPAny>, key: &str) -> impl Iterator<Item = &'a str> {
+    options
+        .and_then(|options| options.get(key))
+        .and_then(|values| values.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str())
+}
+
+/// Reads a config file. A folder without one, or with an invalid one, uses
+/// the defaults.
+fn read_config(path: &Path) -> Config {
+    if !path.is_file() {
+        return Config::default();
+    }
+
+    let name = path.display();
+    let config = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {name}"))
+        .and_then(|text| {
+            parse_config(&text).with_context(|| format!("Invalid config file {name}"))
+        });
+
+    config.unwrap_or_else(|err| {
+        log::warn!("{err:#}");
+        Config::default()
+    })
+}
+
+fn parse_config(text: &str) -> Result<Config> {
+    let document: toml_edit::DocumentMut = text.parse()?;
+
+    Ok(Config {
+        include_paths: string_array(&document, "include_paths")?,
+        excludes: string_array(&document, "excludes")?,
+    })
+}
+
+/// Returns the array of strings `key` of a config file, empty if it's not set.
+fn string_array(document: &toml_edit::DocumentMut, key: &str) -> Result<Vec<String>> {
+    let Some(item) = document.get(key) else {
+        return Ok(Vec::new());
+    };
+    let error = || format!("{key} must be an array of strings");
+    let values = item.as_array().with_context(error)?;
+
+    values
+        .iter()
+        .map(|value| value.as_str().map(str::to_string).with_context(error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tower_lsp::lsp_types::{self, InitializeParams, LSPAny, Url};
+
+    use super::{
+        excludes, include_paths, parse_config, workspace_folders, Config, WorkspaceFolder,
+    };
+
+    #[test]
+    fn parses_config_file() {
+        let config = "# protos\ninclude_paths = [\"proto\", \"third_party/googleapis\"]\nexcludes = [\"gen/\"]\n";
+
+        assert_eq!(
+            parse_config(config).unwrap(),
+            Config {
+                include_paths: vec!["proto".to_string(), "third_party/googleapis".to_string()],
+                excludes: vec!["gen/".to_string()],
+            }
+        );
+        assert_eq!(parse_config("").unwrap(), Config::default());
+        assert!(parse_config("include_paths = \"proto\"").is_err());
+        assert!(parse_config("excludes = [1]").is_err());
+        assert!(parse_config("include_paths = [").is_err());
+    }
+
+    #[test]
+    fn decodes_workspace_folder_uris() {
+        let params = InitializeParams {
+            workspace_folders: Some(vec![lsp_types::WorkspaceFolder {
+                uri: Url::parse("file:///home/me/My%20Protos").unwrap(),
+                name: "My Protos".to_string(),
+            }]),
+            ..Default::default()
+        };
+
+        let folders = workspace_folders(&params);
+
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].root, PathBuf::from("/home/me/My Protos"));
+    }
+
+    #[test]
+    fn orders_include_paths() {
+        let options = LSPAny::Object(
+            [
+                ("includePaths".to_string(), LSPAny::from(vec!["proto"])),
+                ("excludes".to_string(), LSPAny::from(vec!["build/"])),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let folder = WorkspaceFolder {
+            root: PathBuf::from("/ws"),
+            config: Config {
+                include_paths: vec!["vendor".to_string()],
+                excludes: vec!["vendor/".to_string()],
+            },
+        };
+
+        let paths = include_paths(
+            &[PathBuf::from("/cli")],
+            Some(&options),
+            std::slice::from_ref(&folder),
+        );
+
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("/cli"),
+                PathBuf::from("/ws/proto"),
+                PathBuf::from("/ws/vendor"),
+                PathBuf::from("/ws"),
+            ]
+        );
+        assert_eq!(excludes(Some(&options), &folder), ["build/", "vendor/"]);
+    }
+}
@@ -0,0 +1,316 @@
+use protols::parser::{
+    features::FeatureError, imports::Import, symbols::SymbolError, validation::ValidationError,
+    LineIndex, ParseError, PositionEncoding, RelatedInformation, Source,
+};
+use tower_lsp::lsp_types::{
+    self, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString,
+    Url,
+};
+
+use super::{file_name, lsp_range};
+
+/// Returns the diagnostics of the file at `uri`, or none if it hasn't been
+/// parsed.
+pub fn file_diagnostics(source: &Source, uri: &Url, encoding: PositionEncoding) -> Vec<Diagnostic> {
+    let path = &file_name(uri);
+    let (Some(result), Some(index)) = (source.parse_result(path), source.line_index(path)) else {
+        return Vec::new();
+    };
+
+    let mut diagnostics = parse_diagnostics(uri, &result.errors, index, encoding);
+    diagnostics.extend(import_diagnostics(source.imports(path), index, encoding));
+    diagnostics.extend(symbol_diagnostics(
+        &source.symbol_errors(path),
+        index,
+        encoding,
+    ));
+    diagnostics.extend(validation_diagnostics(
+        uri,
+        &source.validation_errors(path),
+        index,
+        encoding,
+    ));
+    diagnostics.extend(feature_diagnostics(
+        &source.feature_errors(path),
+        index,
+        encoding,
+    ));
+    diagnostics
+}
+
+fn error(range: lsp_types::Range, code: &str, message: &str) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(code.to_string())),
+        source: Some("protols".to_string()),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Adds the other spans of an error in the file at `uri`, if it has any.
+fn with_related(
+    diagnostic: Diagnostic,
+    uri: &Url,
+    related: &[RelatedInformation],
+    index: &LineIndex,
+    encoding: PositionEncoding,
+) -> Diagnostic {
+    let related = related
+        .iter()
+        .map(|related| DiagnosticRelatedInformation {
+            location: Location::new(
+                uri.clone(),
+                lsp_range(index, related.start, related.end, encoding),
+            ),
+            message: related.message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    Diagnostic {
+        related_information: (!related.is_empty()).then_some(related),
+        ..diagnostic
+    }
+}
+
+/// Converts the parse errors of the file at `uri` to LSP diagnostics.
+pub fn parse_diagnostics(
+    uri: &Url,
+    errors: &[ParseError],
+    index: &LineIndex,
+    encoding: PositionEncoding,
+) -> Vec<Diagnostic> {
+    errors
+        .iter()
+        .map(|error| {
+            let range = lsp_range(index, error.position, error.end, encoding);
+            let diagnostic = self::error(range, error.kind.code(), &error.message);
+            with_related(diagnostic, uri, &error.related, index, encoding)
+        })
+        .collect()
+}
+
+/// Reports imports that weren't found. A weak import is allowed to be
+/// missing at runtime, so it only gets a warning.
+fn import_diagnostics<'a>(
+    imports: &'a [Import],
+    index: &'a LineIndex,
+    encoding: PositionEncoding,
+) -> impl Iterator<Item = Diagnostic> + 'a {
+    imports
+        .iter()
+        .filter(|import| import.file.is_none())
+        .map(move |import| {
+            let range = lsp_range(index, import.start, import.end, encoding);
+            let message = format!(
+                "Import `{}` was not found in the include paths",
+                import.path
+            );
+            let diagnostic = error(range, "import-not-found", &message);
+
+            if import.is_weak() {
+                Diagnostic {
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    ..diagnostic
+                }
+            } else {
+                diagnostic
+            }
+        })
+}
+
+fn symbol_diagnostics<'a>(
+    errors: &'a [SymbolError],
+    index: &'a LineIndex,
+    encoding: PositionEncoding,
+) -> impl Iterator<Item = Diagnostic> + 'a {
+    errors.iter().map(move |symbol_error| {
+        let range = lsp_range(index, symbol_error.start, symbol_error.end, encoding);
+        error(range, symbol_error.kind.code(), &symbol_error.message)
+    })
+}
+
+fn feature_diagnostics<'a>(
+    errors: &'a [FeatureError],
+    index: &'a LineIndex,
+    encoding: PositionEncoding,
+) -> impl Iterator<Item = Diagnostic> + 'a {
+    errors.iter().map(move |feature_error| {
+        let range = lsp_range(index, feature_error.start, feature_error.end, encoding);
+        error(range, "invalid-feature", &feature_error.message)
+    })
+}
+
+fn validation_diagnostics<'a>(
+    uri: &'a Url,
+    errors: &'a [ValidationError],
+    index: &'a LineIndex,
+    encoding: PositionEncoding,
+) -> impl Iterator<Item = Diagnostic> + 'a {
+    errors.iter().map(move |validation_error| {
+        let range = lsp_range(
+            index,
+            validation_error.start,
+            validation_error.end,
+            encoding,
+        );
+        let mut diagnostic = error(
+            range,
+            validation_error.kind.code(),
+            &validation_error.message,
+        );
+        if validation_error.kind.is_warning() {
+            diagnostic.severity = Some(DiagnosticSeverity::WARNING);
+        }
+        with_related(diagnostic, uri, &validation_error.related, index, encoding)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use protols::parser::{tokenize, LineIndex, Parser, PositionEncoding, Source};
+    use tower_lsp::lsp_types::{DiagnosticSeverity, NumberOrString, Position, Range, Url};
+
+    use super::{file_diagnostics, parse_diagnostics};
+
+    #[test]
+    fn converts_ranges_codes_and_related_information() {
+        let input = "message A {\n  /* ü */ int32 b = x;\n";
+        let result = Parser::new(tokenize(input)).parse("a.proto");
+        let uri = Url::parse("file:///a.proto").unwrap();
+        let index = LineIndex::new(input);
+
+        let diagnostics = parse_diagnostics(&uri, &result.errors, &index, PositionEncoding::Utf16);
+
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 20), Position::new(1, 21))
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unexpected-token".to_string()))
+        );
+
+        let unclosed = &diagnostics[1];
+        assert_eq!(
+            unclosed.code,
+            Some(NumberOrString::String("unclosed-block".to_string()))
+        );
+        let related = &unclosed.related_information.as_ref().unwrap()[0];
+        assert_eq!(related.message, "Block opened here");
+        assert_eq!(related.location.uri, uri);
+        assert_eq!(
+            related.location.range,
+            Range::new(Position::new(0, 10), Position::new(0, 11))
+        );
+    }
+
+    #[test]
+    fn includes_unresolved_references() {
+        let mut source = Source::new();
+        source.parse(
+            "/a.proto",
+            "import weak \"missing.proto\";\nmessage A {\n  optional Missing m = 1;\n}\n",
+        );
+        let uri = Url::parse("file:///a.proto").unwrap();
+
+        let diagnostics = file_diagnostics(&source, &uri, PositionEncoding::Utf16);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("import-not-found".to_string()))
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 12), Position::new(0, 27))
+        );
+        assert_eq!(
+            diagnostics[1].code,
+            Some(NumberOrString::String("unresolved-type".to_string()))
+        );
+        assert_eq!(
+            diagnostics[1].range,
+            Range::new(Position::new(2, 11), Position::new(2, 18))
+        );
+        assert_eq!(
+            diagnostics[2].code,
+            Some(NumberOrString::String("missing-syntax".to_string()))
+        );
+        assert_eq!(diagnostics[2].severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn includes_validation_errors() {
+        let mut source = Source::new();
+        source.parse(
+            "/a.proto",
+            "syntax = \"proto3\";\nmessage A {\n  int32 a = 1;\n  int32 b = 1;\n}\n",
+        );
+        let uri = Url::parse("file:///a.proto").unwrap();
+
+        let diagnostics = file_diagnostics(&source, &uri, PositionEncoding::Utf16);
+
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("duplicate-field-number".to_string()))
+        );
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(3, 12), Position::new(3, 13))
+        );
+        let related = &diagnostics[0].related_information.as_ref().unwrap()[0];
+        assert_eq!(related.location.uri, uri);
+        assert_eq!(
+            related.location.range,
+            Range::new(Position::new(2, 12), Position::new(2, 13))
+        );
+    }
+
+    #[test]
+    fn includes_feature_errors() {
+        let mut source = Source::new();
+        source.parse(
+            "/a.proto",
+            "edition = \"2023\";\nmessage A {\n  int32 a = 1 [features.field_presence = MAYBE];\n}\n",
+        );
+        let uri = Url::parse("file:///a.proto").unwrap();
+
+        let diagnostics = file_diagnostics(&source, &uri, PositionEncoding::Utf16);
+
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("invalid-feature".to_string()))
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Invalid value MAYBE for feature features.field_presence"
+        );
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(2, 15), Position::new(2, 46))
+        );
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        let mut source = Source::new();
+        source.parse("/My Protos/ü.proto", "message A {");
+        let uri = Url::parse("file:///My%20Protos/%C3%BC.proto").unwrap();
+
+        let diagnostics = file_diagnostics(&source, &uri, PositionEncoding::Utf16);
+
+        let unclosed = Some(NumberOrString::String("unclosed-block".to_string()));
+        assert!(
+            diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.code == unclosed),
+            "{diagnostics:?}"
+        );
+    }
+}
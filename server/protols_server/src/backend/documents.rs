@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use protols::parser::{LineIndex, PositionEncoding, Source};
+use tower_lsp::lsp_types::{self, TextDocumentContentChangeEvent};
+
+/// Versions of the documents the client has open. Their text lives in
+/// `Source`, which follows the client's buffers for these files instead of
+/// the files on disk.
+#[derive(Debug, Default)]
+pub struct Documents {
+    versions: HashMap<String, i32>,
+}
+
+impl Documents {
+    pub fn is_open(&self, path: &str) -> bool {
+        self.versions.contains_key(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.versions.keys().map(String::as_str)
+    }
+
+    pub fn version(&self, path: &str) -> Option<i32> {
+        self.versions.get(path).copied()
+    }
+
+    pub fn open(&mut self, source: &mut Source, path: &str, version: i32, text: &str) {
+        self.versions.insert(path.to_string(), version);
+        source.parse(path, text);
+    }
+
+    /// Applies the changes of a `didChange` notification. Changes to
+    /// documents that aren't open, or that are older than the current
+    /// version, are ignored and `false` is returned. If a change can't be
+    /// applied the text no longer matches the client's, so the document is
+    /// closed and `false` is returned.
+    pub fn change(
+        &mut self,
+        source: &mut Source,
+        encoding: PositionEncoding,
+        path: &str,
+        version: i32,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> bool {
+        match self.versions.get_mut(path) {
+            Some(current) if *current < version => *current = version,
+            _ => return false,
+        }
+
+        if !apply_changes(source, encoding, path, changes) {
+            self.versions.remove(path);
+            return false;
+        }
+        true
+    }
+
+    /// Returns `false` if the document wasn't open.
+    pub fn close(&mut self, path: &str) -> bool {
+        self.versions.remove(path).is_some()
+    }
+}
+
+/// Applies content changes in order. Each change's range refers to the text
+/// after the previous changes. Stops and returns `false` at the first change
+/// that can't be applied.
+fn apply_changes(
+    source: &mut Source,
+    encoding: PositionEncoding,
+    path: &str,
+    changes: Vec<TextDocumentContentChangeEvent>,
+) -> bool {
+    for change in changes {
+        let Some(range) = change.range else {
+            source.parse(path, &change.text);
+            continue;
+        };
+
+        let offsets = source
+            .line_index(path)
+            .map(|index| offset_range(index, range, encoding));
+        if !offsets.is_some_and(|offsets| source.edit(path, offsets, &change.text)) {
+            log::warn!("failed to apply change to {path} at {range:?}");
+            return false;
+        }
+    }
+    true
+}
+
+fn offset_range(
+    index: &LineIndex,
+    range: lsp_types::Range,
+    encoding: PositionEncoding,
+) -> std::ops::Range<usize> {
+    let offset = |position: lsp_types::Position| {
+        index.offset(
+            position.line as usize,
+            position.character as usize,
+            encoding,
+        )
+    };
+
+    let start = offset(range.start);
+    start..offset(range.end).max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use protols::parser::{PositionEncoding, Source};
+    use tower_lsp::lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+
+    use super::Documents;
+
+    fn change(
+        range: Option<((u32, u32), (u32, u32))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|(start, end)| Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn applies_incremental_changes_in_order() {
+        let mut source = Source::new();
+        let mut documents = Documents::default();
+        documents.open(
+            &mut source,
+            "a.proto",
+            1,
+            "message A {\n  int32 a = 1;\n}\n",
+        );
+
+        let changed = documents.change(
+            &mut source,
+            PositionEncoding::Utf16,
+            "a.proto",
+            2,
+            vec![
+                change(Some(((1, 8), (1, 9))), "count"),
+                change(Some(((0, 8), (0, 9))), "Änne"),
+                change(Some(((0, 12), (0, 12))), " "),
+            ],
+        );
+
+        assert!(changed);
+        assert_eq!(documents.version("a.proto"), Some(2));
+        assert_eq!(
+            source.text("a.proto"),
+            Some("message Änne  {\n  int32 count = 1;\n}\n")
+        );
+    }
+
+    #[test]
+    fn replaces_full_text_and_ignores_stale_versions() {
+        let mut source = Source::new();
+        let mut documents = Documents::default();
+        documents.open(&mut source, "a.proto", 3, "message A {}");
+
+        let encoding = PositionEncoding::Utf16;
+        let full = vec![change(None, "message B {}")];
+        assert!(documents.change(&mut source, encoding, "a.proto", 4, full));
+        assert_eq!(source.text("a.proto"), Some("message B {}"));
+
+        let stale = vec![change(None, "message C {}")];
+        assert!(!documents.change(&mut source, encoding, "a.proto", 4, stale));
+        let closed = vec![change(None, "message D {}")];
+        assert!(!documents.change(&mut source, encoding, "b.proto", 1, closed));
+        assert_eq!(source.text("a.proto"), Some("message B {}"));
+
+        assert!(documents.close("a.proto"));
+        assert!(!documents.is_open("a.proto"));
+    }
+
+    #[test]
+    fn closes_documents_when_a_change_fails() {
+        let mut source = Source::new();
+        let mut documents = Documents::default();
+        documents.open(&mut source, "a.proto", 1, "message A {}");
+        source.remove("a.proto");
+
+        let changed = documents.change(
+            &mut source,
+            PositionEncoding::Utf16,
+            "a.proto",
+            2,
+            vec![
+                change(Some(((0, 8), (0, 9))), "B"),
+                change(None, "message C {}"),
+            ],
+        );
+
+        assert!(!changed);
+        assert!(!documents.is_open("a.proto"));
+        assert_eq!(source.text("a.proto"), None);
+    }
+}
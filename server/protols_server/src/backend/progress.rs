@@ -0,0 +1,77 @@
+use tower_lsp::{
+    lsp_types::{
+        notification::Progress as ProgressNotification, request::WorkDoneProgressCreate,
+        NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress,
+        WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+        WorkDoneProgressReport,
+    },
+    Client,
+};
+
+/// A `window/workDoneProgress` the server started. Reporting does nothing if
+/// the client doesn't support it.
+pub struct Progress {
+    client: Client,
+    token: Option<NumberOrString>,
+}
+
+impl Progress {
+    /// Asks the client to show a progress with `title`.
+    pub async fn begin(client: &Client, supported: bool, token: &str, title: &str) -> Self {
+        let mut progress = Self {
+            client: client.clone(),
+            token: None,
+        };
+        if !supported {
+            return progress;
+        }
+
+        let token = NumberOrString::String(token.to_string());
+        let params = WorkDoneProgressCreateParams {
+            token: token.clone(),
+        };
+        if let Err(err) = client.send_request::<WorkDoneProgressCreate>(params).await {
+            log::warn!("failed to create progress {token:?}: {err}");
+            return progress;
+        }
+
+        progress.token = Some(token);
+        progress
+            .notify(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_string(),
+                percentage: Some(0),
+                ..Default::default()
+            }))
+            .await;
+        progress
+    }
+
+    pub async fn report(&self, message: String, percentage: u32) {
+        self.notify(WorkDoneProgress::Report(WorkDoneProgressReport {
+            message: Some(message),
+            percentage: Some(percentage),
+            ..Default::default()
+        }))
+        .await;
+    }
+
+    pub async fn end(self, message: String) {
+        self.notify(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(message),
+        }))
+        .await;
+    }
+
+    async fn notify(&self, progress: WorkDoneProgress) {
+        let Some(token) = &self.token else {
+            return;
+        };
+
+        self.client
+            .send_notification::<ProgressNotification>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
+}
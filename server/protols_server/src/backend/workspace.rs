@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match, WalkBuilder,
+};
+
+/// A workspace folder and the files in it that aren't indexed.
+#[derive(Debug, Clone)]
+pub struct Folder {
+    root: PathBuf,
+    excludes: Gitignore,
+}
+
+/// The workspace folders whose `.proto` files are indexed.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    folders: Vec<Folder>,
+}
+
+impl Workspace {
+    /// `folders` are the workspace folders with their `excludes`, which are
+    /// `.gitignore` patterns relative to the folder.
+    pub fn new(folders: Vec<(PathBuf, Vec<String>)>) -> Self {
+        let folders = folders
+            .into_iter()
+            .map(|(root, excludes)| Folder {
+                excludes: excludes_matcher(&root, &excludes),
+                root,
+            })
+            .collect();
+
+        Self { folders }
+    }
+
+    /// Returns the `.proto` files in the workspace that aren't ignored by a
+    /// `.gitignore` file or excluded.
+    pub fn proto_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for folder in &self.folders {
+            let excludes = folder.excludes.clone();
+            let walk = walker(&folder.root)
+                .filter_entry(move |entry| {
+                    let is_dir = entry
+                        .file_type()
+                        .is_some_and(|file_type| file_type.is_dir());
+                    entry.file_name() != ".git"
+                        && !excludes.matched(entry.path(), is_dir).is_ignore()
+                })
+                .build();
+
+            for entry in walk {
+                match entry {
+                    Ok(entry)
+                        if entry
+                            .file_type()
+                            .is_some_and(|file_type| file_type.is_file())
+                            && is_proto(entry.path()) =>
+                    {
+                        files.push(entry.into_path())
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::warn!("failed to read {}: {err}", folder.root.display()),
+                }
+            }
+        }
+
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Whether `path` is a file `proto_files` would return, without walking
+    /// the whole workspace.
+    pub fn contains(&self, path: &Path) -> bool {
+        if !is_proto(path) {
+            return false;
+        }
+
+        self.folders.iter().any(|folder| {
+            let Ok(relative) = path.strip_prefix(&folder.root) else {
+                return false;
+            };
+            if relative
+                .components()
+                .any(|component| component.as_os_str() == ".git")
+                || folder
+                    .excludes
+                    .matched_path_or_any_parents(path, false)
+                    .is_ignore()
+            {
+                return false;
+            }
+
+            // The `.gitignore` file closest to `path` with a matching
+            // pattern decides.
+            for dir in path.ancestors().skip(1) {
+                let (gitignore, _) = Gitignore::new(dir.join(".gitignore"));
+                match gitignore.matched_path_or_any_parents(path, false) {
+                    Match::Ignore(_) => return false,
+                    Match::Whitelist(_) => return true,
+                    Match::None => {}
+                }
+                if dir == folder.root {
+                    break;
+                }
+            }
+            true
+        })
+    }
+}
+
+/// Walks `root` applying `.gitignore` files, even outside of a git
+/// repository, without skipping hidden files or following symlinks.
+fn walker(root: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .parents(false)
+        .ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .require_git(false);
+    builder
+}
+
+fn excludes_matcher(root: &Path, excludes: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for exclude in excludes {
+        if let Err(err) = builder.add_line(None, exclude) {
+            log::warn!("invalid exclude pattern {exclude:?}: {err}");
+        }
+    }
+    builder.build().unwrap_or_else(|err| {
+        log::warn!("invalid exclude patterns: {err}");
+        Gitignore::empty()
+    })
+}
+
+fn is_proto(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "proto")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::Workspace;
+
+    #[test]
+    fn finds_proto_files() {
+        let dir = std::env::temp_dir().join(format!("protols-workspace-{}", std::process::id()));
+        for path in [
+            "a",
+            "a/generated",
+            "third_party",
+            "node_modules/x",
+            "docs/a/internal",
+        ] {
+            fs::create_dir_all(dir.join(path)).unwrap();
+        }
+        for path in [
+            "root.proto",
+            "a/a.proto",
+            "a/notes.txt",
+            "a/b.gen.proto",
+            "a/keep.gen.proto",
+            "a/generated/gen.proto",
+            "third_party/dep.proto",
+            "node_modules/x/x.proto",
+            "docs/a/internal/doc.proto",
+        ] {
+            fs::write(dir.join(path), "").unwrap();
+        }
+        fs::write(
+            dir.join(".gitignore"),
+            "node_modules/\n*.gen.proto\n!keep.gen.proto\ndocs/**/internal\n",
+        )
+        .unwrap();
+        fs::write(dir.join("a/.gitignore"), "generated\n").unwrap();
+
+        let workspace = Workspace::new(vec![(dir.clone(), vec!["/third_party".to_string()])]);
+
+        assert_eq!(
+            workspace.proto_files(),
+            [
+                dir.join("a/a.proto"),
+                dir.join("a/keep.gen.proto"),
+                dir.join("root.proto")
+            ]
+        );
+        assert!(workspace.contains(&dir.join("a/new.proto")));
+        assert!(workspace.contains(&dir.join("a/keep.gen.proto")));
+        assert!(!workspace.contains(&dir.join("a/b.gen.proto")));
+        assert!(!workspace.contains(&dir.join("a/generated/new.proto")));
+        assert!(!workspace.contains(&dir.join("docs/a/internal/doc.proto")));
+        assert!(!workspace.contains(&dir.join("third_party/new.proto")));
+        assert!(!workspace.contains(&dir.join("a/notes.txt")));
+        assert!(!workspace.contains(&PathBuf::from("/elsewhere/a.proto")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,8 @@
 mod backend;
 mod logger;
 
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use logger::create_logger;
@@ -21,14 +23,14 @@ enum LogLevel {
     Error,
 }
 
-impl Into<log::LevelFilter> for LogLevel {
-    fn into(self) -> log::LevelFilter {
-        match self {
-            Self::Trace => log::LevelFilter::Trace,
-            Self::Debug => log::LevelFilter::Debug,
-            Self::Info => log::LevelFilter::Info,
-            Self::Warn => log::LevelFilter::Warn,
-            Self::Error => log::LevelFilter::Error,
+impl From<LogLevel> for log::LevelFilter {
+    fn from(val: LogLevel) -> Self {
+        match val {
+            LogLevel::Trace => log::LevelFilter::Trace,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Error => log::LevelFilter::Error,
         }
     }
 }
@@ -43,16 +45,24 @@ struct Args {
     /// Use stdio for communication
     #[arg(long)]
     stdio: bool,
+
+    /// A directory imports are resolved against, can be repeated. These are
+    /// searched before the ones from the client and `protols.toml`.
+    #[arg(short = 'I', long = "include-path", value_name = "DIR")]
+    include_paths: Vec<PathBuf>,
 }
 
-async fn start_server<I, O>(input: I, output: O) -> Result<()>
+async fn start_server<I, O>(input: I, output: O, include_paths: Vec<PathBuf>) -> Result<()>
 where
     I: AsyncRead + Unpin,
     O: AsyncWrite,
 {
     log::info!("creating lsp service");
 
-    let (service, socket) = LspService::new(|client| ProtoLanguageServer::new(client));
+    let (service, socket) =
+        LspService::build(|client| ProtoLanguageServer::new(client, include_paths))
+            .custom_method("protols/virtualFile", ProtoLanguageServer::virtual_file)
+            .finish();
 
     log::info!("starting server");
 
@@ -63,13 +73,13 @@ where
     Ok(())
 }
 
-async fn start_stdio_server() -> Result<()> {
+async fn start_stdio_server(include_paths: Vec<PathBuf>) -> Result<()> {
     log::info!("Starting protols in stdio mode");
 
-    start_server(tokio::io::stdin(), tokio::io::stdout()).await
+    start_server(tokio::io::stdin(), tokio::io::stdout(), include_paths).await
 }
 
-async fn start_tcp_server() -> Result<()> {
+async fn start_tcp_server(include_paths: Vec<PathBuf>) -> Result<()> {
     log::info!("Starting protols in tcp mode");
 
     let listener = TcpListener::bind("127.0.0.1:50051").await?;
@@ -80,9 +90,10 @@ async fn start_tcp_server() -> Result<()> {
         log::info!("Accepted connection from {:?}", socket.peer_addr());
 
         let (reader, writer) = tokio::io::split(socket);
+        let include_paths = include_paths.clone();
 
         tokio::spawn(async move {
-            if let Err(err) = start_server(reader, writer).await {
+            if let Err(err) = start_server(reader, writer, include_paths).await {
                 log::error!("Error in tcp server: {}", err);
             }
 
@@ -98,9 +109,16 @@ async fn main() -> Result<()> {
 
     log::info!("Starting protols language server");
 
+    let current_dir = std::env::current_dir()?;
+    let include_paths = args
+        .include_paths
+        .iter()
+        .map(|path| current_dir.join(path))
+        .collect();
+
     if args.stdio {
-        start_stdio_server().await
+        start_stdio_server(include_paths).await
     } else {
-        start_tcp_server().await
+        start_tcp_server(include_paths).await
     }
 }